@@ -1,12 +1,21 @@
 use std::net::SocketAddrV4;
+use std::time::Duration;
 
 use clap::{App, Arg, ArgGroup};
 
 use fracture_core::utils::ipencoding::code_to_ip_safe;
 
+use crate::conf::AWAY_AFTER_MINS;
+use crate::types::Credentials;
+
 pub struct Args {
     pub name: String,
     pub addr: SocketAddrV4,
+    pub tls: bool,
+    pub trust_new_cert: bool,
+    pub credentials: Credentials,
+    /// how long the user can be idle before they are shown as away, None to never
+    pub away_after: Option<Duration>,
 }
 
 /// Get args
@@ -51,6 +60,49 @@ pub fn get_args() -> Result<Args, ()> {
                 .multiple(false)
                 .required(true),
         )
+        .arg(
+            Arg::with_name("tls")
+                .help("connect using TLS. the server must have been launched with --tls")
+                .long("tls")
+                .short("t")
+                .multiple(false),
+        )
+        .arg(
+            Arg::with_name("trust-new-cert")
+                .help("accept the servers certificate even if it is different from last time")
+                .long("trust-new-cert")
+                .multiple(false)
+                .requires("tls"),
+        )
+        .arg(
+            Arg::with_name("password")
+                .help("password for joining the server, if it needs one. you will be asked for it if it is not given here")
+                .long("password")
+                .short("p")
+                .takes_value(true)
+                .multiple(false),
+        )
+        .arg(
+            Arg::with_name("account-password")
+                .help("password for your account (named after --name), if the server has accounts. you will be asked for it if it is not given here")
+                .long("account-password")
+                .takes_value(true)
+                .multiple(false),
+        )
+        .arg(
+            Arg::with_name("register")
+                .help("create a new account instead of logging in")
+                .long("register")
+                .multiple(false)
+                .requires("account-password"),
+        )
+        .arg(
+            Arg::with_name("away-after")
+                .help("minutes without doing anything before you are shown as away, 0 to never be")
+                .long("away-after")
+                .takes_value(true)
+                .multiple(false),
+        )
         .get_matches();
 
     // println!("{:#?}", args);
@@ -87,5 +139,32 @@ pub fn get_args() -> Result<Args, ()> {
 
     let name = args.value_of("name").unwrap().to_string();
 
-    Ok(Args { name, addr })
+    let tls = args.is_present("tls");
+    let trust_new_cert = args.is_present("trust-new-cert");
+    let credentials = Credentials {
+        server_password: args.value_of("password").map(String::from),
+        account_password: args.value_of("account-password").map(String::from),
+        register: args.is_present("register"),
+    };
+
+    let away_after = match args.value_of("away-after").map(str::parse::<u64>) {
+        None => AWAY_AFTER_MINS,
+        Some(Ok(mins)) => mins,
+        Some(Err(_)) => {
+            eprintln!("--away-after must be a number of minutes!");
+            return Err(());
+        }
+    };
+    let away_after = Some(away_after)
+        .filter(|mins| *mins > 0)
+        .map(|mins| Duration::from_secs(mins * 60));
+
+    Ok(Args {
+        name,
+        addr,
+        tls,
+        trust_new_cert,
+        credentials,
+        away_after,
+    })
 }
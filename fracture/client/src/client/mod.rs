@@ -4,21 +4,24 @@ use queues::queue;
 use queues::IsQueue;
 use queues::Queue;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
 use uuid::Uuid;
 
+use fracture_core::codec::FractureCodec;
 use fracture_core::handler::MessageHandler;
+use fracture_core::heartbeat::{Heartbeat, HeartbeatSettings};
 use fracture_core::msg;
 use fracture_core::stat::SendStatus;
 use fracture_core::stat::SendError;
+use fracture_core::transport::BoxedTransport;
 use fracture_core::SocketUtils;
 
 use crate::types::CommMessage;
-use crate::types::{stati, ClientState, HandlerOperation, ServerInfo};
+use crate::types::{stati, ClientState, Credentials, HandlerOperation, Roster, RosterChange, ServerInfo};
 
 pub struct Client {
     name: String, //the name of the client
-    sock: TcpStream,
+    sock: Framed<BoxedTransport, FractureCodec>,
     pub incoming: Queue<msg::Message>,
     outgoing: Queue<msg::Message>,
     /// Pending handler operations
@@ -27,17 +30,27 @@ pub struct Client {
     pub server_info: Option<ServerInfo>,
     pub state: ClientState,
     gui_send: MPSCSender<CommMessage>,
+    /// passwords to send if the server asks for them, if they were given ahead of time (or entered for an earlier connection)
+    credentials: Credentials,
+    /// if the server is still there
+    heartbeat: Heartbeat,
+    /// token to resume the session with after losing the connection, once the server has given one
+    session_token: Option<String>,
+    /// if this connection resumed a earlier session
+    resumed: bool,
 }
 
 impl Client {
     pub fn new(
-        sock: TcpStream,
+        sock: BoxedTransport,
         name: String,
         handlers: Vec<Box<dyn MessageHandler<Operation = HandlerOperation> + Send>>,
         gui_send: MPSCSender<CommMessage>,
+        credentials: Credentials,
+        session_token: Option<String>,
     ) -> Self {
         Self {
-            sock,
+            sock: Framed::new(sock, FractureCodec::new()),
             incoming: queue![],
             outgoing: queue![],
             pending_op: queue![],
@@ -46,13 +59,40 @@ impl Client {
             server_info: None,
             state: ClientState::Begin,
             gui_send,
+            credentials,
+            heartbeat: Heartbeat::new(&HeartbeatSettings::default()),
+            session_token,
+            resumed: false,
         }
     }
 
+    /// The name of the client, which the server may have changed
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub const fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    pub const fn session_token(&self) -> Option<&String> {
+        self.session_token.as_ref()
+    }
+
+    /// If this connection took back the session of a earlier one, instead of starting over
+    pub const fn resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// If the client is through the handshake, and can send normal messages
+    pub const fn is_ready(&self) -> bool {
+        matches!(self.state, ClientState::Ready)
+    }
+
     pub async fn close(&mut self, method: stati::CloseType) {
         match method {
             stati::CloseType::Force => {
-                match self.sock.shutdown().await {
+                match self.sock.get_mut().shutdown().await {
                     Ok(_) => {}
                     Err(err) => {
                         println!("Error whilest closing connection to server:\n{:#?}", err);
@@ -60,7 +100,7 @@ impl Client {
                 };
             }
             stati::CloseType::ServerDisconnected => {
-                match self.sock.shutdown().await {
+                match self.sock.get_mut().shutdown().await {
                     Ok(_) => {}
                     Err(err) => {
                         if err.kind() != std::io::ErrorKind::NotConnected {
@@ -89,6 +129,7 @@ impl Client {
                                 };
                             }
                             SendError::SeriError(_) => panic!("Could not serialize shutdown msg"),
+                            SendError::TooLarge { .. } => unreachable!("shutdown msg is tiny"),
                         }
                     }
                     _ => {}
@@ -113,7 +154,15 @@ impl Client {
         let mut sent = 0;
         let mut sent_bytes = 0;
         loop {
-            let res = self.send_queued_message().await?;
+            let res = match self.send_queued_message().await {
+                Ok(res) => res,
+                // the server would disconnect on getting it, so it is dropped instead
+                Err(err @ SendError::TooLarge { .. }) => {
+                    eprintln!("Not sending a message: {}", err);
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
             match res {
                 SendStatus::NoTask => {
                     return if sent == 0 {
@@ -137,24 +186,97 @@ impl Client {
         self.outgoing.add(msg).unwrap();
     }
 
+    /// Answer the servers `AuthRequest` with a password the user entered
+    pub fn submit_password(&mut self, password: String) {
+        if let ClientState::AwaitingPassword = self.state {
+            // kept, so it does not have to be entered again when reconnecting
+            self.credentials.server_password = Some(password.clone());
+            self.queue_msg(msg::Message {
+                data: msg::MessageVarient::Authenticate { password },
+            });
+            self.state = ClientState::Hanshake;
+        }
+    }
+
+    /// Answer the servers `LoginRequest` with a account password the user entered
+    pub fn submit_login(&mut self, password: String, register: bool) {
+        if let ClientState::AwaitingPassword = self.state {
+            self.credentials.account_password = Some(password.clone());
+            self.credentials.register = register;
+            self.queue_msg(self.login_message(password, register));
+            self.state = ClientState::Hanshake;
+        }
+    }
+
+    /// Log in to (or register) the account with this clients name
+    fn login_message(&self, password: String, register: bool) -> msg::Message {
+        let username = self.name.clone();
+        msg::Message {
+            data: if register {
+                msg::MessageVarient::Register { username, password }
+            } else {
+                msg::MessageVarient::Login { username, password }
+            },
+        }
+    }
+
+    /// Ping the server if it is time to, so it is known to still be there
+    pub fn send_heartbeat(&mut self) {
+        if self.heartbeat.ping_due() {
+            self.queue_msg(msg::Message {
+                data: msg::MessageVarient::Ping,
+            });
+        }
+    }
+
+    /// If the server has not sent anything (not even a answer to a ping) in too long
+    pub fn is_server_dead(&self) -> bool {
+        self.heartbeat.is_dead()
+    }
+
     /// Handles the reading message half of updating the client.
     /// for the most part it handles errors that occur in it, but it will return info for some situations,
     /// Like disconnects.
     pub async fn update_read(&mut self) -> Result<(), stati::UpdateReadError> {
         let read = self.read_msg().await;
         if let Ok(stat) = read {
-            if let msg::MessageVarient::ServerForceDisconnect {
-                reason,
-                close_message,
-            } = stat.msg.data
-            {
-                Err(stati::UpdateReadError::ServerClosed {
+            self.heartbeat.received();
+            match stat.msg.data {
+                // these can come at any time, so they are dealt with here and not in `update`
+                msg::MessageVarient::Ping => {
+                    self.queue_msg(msg::Message {
+                        data: msg::MessageVarient::Pong,
+                    });
+                    Ok(())
+                }
+                msg::MessageVarient::Pong => {
+                    if let Some(rtt) = self.heartbeat.pong() {
+                        let _ = self.gui_send.send(CommMessage::Latency(rtt));
+                    }
+                    Ok(())
+                }
+                msg::MessageVarient::SessionStarted { token, resumed } => {
+                    self.session_token = Some(token);
+                    self.resumed = resumed;
+                    Ok(())
+                }
+                msg::MessageVarient::ServerForceDisconnect {
                     reason,
                     close_message,
-                })
-            } else {
-                self.incoming.add(stat.msg).unwrap();
-                Ok(())
+                } => Err(stati::UpdateReadError::ServerClosed {
+                    reason,
+                    close_message,
+                }),
+                // the server closes the connection right after refusing, so this has to be dealt with here
+                // instead of waiting for `update` to get to it
+                msg::MessageVarient::ServerInfo {
+                    conn_status: msg::types::ConnectionStatus::Refused { reason },
+                    ..
+                } => Err(stati::UpdateReadError::ConnectionRefused { reason }),
+                _ => {
+                    self.incoming.add(stat.msg).unwrap();
+                    Ok(())
+                }
             }
         } else if let Err(err) = read {
             match err {
@@ -171,13 +293,15 @@ impl Client {
     /// Processes incoming messages, and then queues messages for sending
     pub async fn update(&mut self) -> Result<stati::UpdateStatus, stati::UpdateError> {
         use stati::{UpdateStatus, UpdateError};
-        use ClientState::{Begin, GetHandlerDefaultOps, Hanshake, Ready};
+        use ClientState::{AwaitingPassword, Begin, GetHandlerDefaultOps, Hanshake, Ready};
         match &self.state {
             Begin => {
-                // send message to server about the client
-                match self
-                    .send_message(fracture_core::common::gen_connect(self.name.clone()))
-                    .await
+                // send message to server about the client, taking back the last session if there was one
+                let connect = match &self.session_token {
+                    Some(token) => fracture_core::common::gen_resume(self.name.clone(), token.clone()),
+                    None => fracture_core::common::gen_connect(self.name.clone()),
+                };
+                match self.send_message(connect).await
                 {
                     Ok(stat) => {
                         match stat {
@@ -201,32 +325,91 @@ impl Client {
                     Ok(msg) => {
                         match msg.data {
                             fracture_core::msg::MessageVarient::ServerInfo {
-                                server_name,
+                                protocol,
                                 conn_status,
-                                connected_users: _, //TODO stop ignoring the servers lies, prehaps when it stops lying
+                                server_name,
+                                connected_users,
                                 your_uuid,
+                                capabilities,
+                                max_message_size,
                             } => {
                                 if let msg::types::ConnectionStatus::Refused { reason } =
                                     conn_status
                                 {
                                     println!("Connection refused:{}", reason);
-                                    return Err(UpdateError::ConnectionRefused);
+                                    return Err(UpdateError::ConnectionRefused(reason));
+                                }
+                                if !msg::PROTOCOL_VERSION.is_compatible(&protocol) {
+                                    return Err(UpdateError::ConnectionRefused(format!(
+                                        "Incompatible protocol version: the server speaks {}, but this client speaks {}. Please upgrade {}.",
+                                        protocol,
+                                        msg::PROTOCOL_VERSION,
+                                        if protocol.major > msg::PROTOCOL_VERSION.major { "your client" } else { "the server" }
+                                    )));
                                 }
-                                println!("Connected to: {}", server_name);
+                                println!("Connected to: {} (protocol {}, capabilities {:?})", server_name, protocol, capabilities);
+                                // the server may allow bigger messages than the default, and it sends ones up to its own limit
+                                self.sock.codec_mut().set_max_size(max_message_size as usize);
+                                // the account exists now, so reconnecting logs in to it instead of registering it again
+                                self.credentials.register = false;
                                 let real_uuid = Uuid::from_u128(your_uuid);
-                                self.server_info = Some(ServerInfo {
+                                let server_info = ServerInfo {
                                     client_uuid: real_uuid,
                                     name: server_name.clone(),
-                                });
+                                    capabilities,
+                                };
+                                // it would not take the token when reconnecting, so do not bother offering it
+                                if !server_info.supports(msg::capabilities::RESUME) {
+                                    self.session_token = None;
+                                }
+                                self.server_info = Some(server_info);
                                 // does not matter if it worked or not
                                 let _ = self.gui_send.send(
                                     CommMessage::ServerInfo {
-                                        server_name
+                                        server_name,
+                                        uuid: real_uuid,
                                     }
                                 );
+                                let mut roster = Roster::default();
+                                for user in connected_users {
+                                    roster.apply(user.into());
+                                }
+                                let _ = self.gui_send.send(CommMessage::RosterChanged(RosterChange::Replace(
+                                    roster.users().to_vec(),
+                                )));
                                 self.state = ClientState::GetHandlerDefaultOps;
                                 Ok(UpdateStatus::Success)
                             }
+                            fracture_core::msg::MessageVarient::AuthRequest {} => {
+                                if let Some(password) = self.credentials.server_password.clone() {
+                                    self.queue_msg(msg::Message {
+                                        data: msg::MessageVarient::Authenticate { password },
+                                    });
+                                } else {
+                                    // does not matter if it worked or not
+                                    let _ = self.gui_send.send(CommMessage::PasswordRequired);
+                                    self.state = AwaitingPassword;
+                                }
+                                Ok(UpdateStatus::Success)
+                            }
+                            fracture_core::msg::MessageVarient::LoginRequest {} => {
+                                if let Some(password) = self.credentials.account_password.clone() {
+                                    let login = self.login_message(password, self.credentials.register);
+                                    self.queue_msg(login);
+                                } else {
+                                    // does not matter if it worked or not
+                                    let _ = self.gui_send.send(CommMessage::LoginRequired);
+                                    self.state = AwaitingPassword;
+                                }
+                                Ok(UpdateStatus::Success)
+                            }
+                            fracture_core::msg::MessageVarient::NameAssigned { name } => {
+                                println!("Name {:?} is taken, the server named you {:?}", self.name, name);
+                                self.name = name.clone();
+                                // does not matter if it worked or not
+                                let _ = self.gui_send.send(CommMessage::NameAssigned { name });
+                                Ok(UpdateStatus::Success)
+                            }
                             _ => {
                                 self.incoming.add(msg.clone()).unwrap();
                                 Err(UpdateError::Unexpected(msg))
@@ -236,6 +419,7 @@ impl Client {
                     Err(_) => Ok(UpdateStatus::Noop)
                 }
             }
+            AwaitingPassword => Ok(UpdateStatus::Noop),
             GetHandlerDefaultOps => {
                 for h in &mut self.handlers {
                     for op in h.get_default_operations() {
@@ -320,7 +504,9 @@ impl Client {
 }
 
 impl SocketUtils for Client {
-    fn get_sock(&mut self) -> &mut TcpStream {
+    type Transport = BoxedTransport;
+
+    fn get_framed(&mut self) -> &mut Framed<BoxedTransport, FractureCodec> {
         &mut self.sock
     }
 }
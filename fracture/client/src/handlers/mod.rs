@@ -9,5 +9,7 @@ pub fn get_default() -> Vec<Box<dyn MessageHandler<Operation = HandlerOperation>
         // modules::test_handler::TestHandler::new()
         modules::incoming_chat::IncomingChatHandler::new(),
         modules::server_info_update::InfoUpdateHandler::new(),
+        modules::channels::ChannelHandler::new(),
+        modules::typing::TypingHandler::new(),
     ]
 }
@@ -0,0 +1,68 @@
+use crate::handlers::imports::{GlobalHandlerOperation, HandlerOperation, MessageHandler};
+use crate::types::{ChannelEvent, InterfaceOperation};
+use fracture_core::msg::MessageVarient;
+
+/// Passes on what the server says about channels to the user
+pub struct ChannelHandler {
+    pending: Vec<HandlerOperation>,
+}
+
+impl MessageHandler for ChannelHandler {
+    type Operation = HandlerOperation;
+
+    fn new() -> Box<Self> {
+        Box::new(Self { pending: vec![] })
+    }
+
+    fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
+        let event = match msg.data.clone() {
+            MessageVarient::ChannelJoined { channel, topic } => ChannelEvent::Joined { channel, topic },
+            MessageVarient::ChannelLeft { channel } => ChannelEvent::Left { channel },
+            MessageVarient::ChannelList { channels } => ChannelEvent::List(channels),
+            MessageVarient::ChannelError { channel, reason } => {
+                ChannelEvent::Error { channel, reason }
+            }
+            MessageVarient::History { channel, messages } => {
+                ChannelEvent::History { channel, messages }
+            }
+            MessageVarient::ChatEdited { id, channel, content, .. } => {
+                ChannelEvent::Edited { channel, id, content }
+            }
+            MessageVarient::ChatDeleted { id, channel } => ChannelEvent::Deleted { channel, id },
+            MessageVarient::ChatChangeFailed { id, reason } => {
+                ChannelEvent::ChangeFailed { id, reason }
+            }
+            MessageVarient::Thread { channel, parent, replies } => {
+                ChannelEvent::Thread { channel, parent, replies }
+            }
+            MessageVarient::ThreadFailed { id, reason } => ChannelEvent::ThreadFailed { id, reason },
+            MessageVarient::ReactionsChanged { message_id, channel, reactions } => {
+                ChannelEvent::Reactions { channel, id: message_id, reactions }
+            }
+            _ => return false,
+        };
+        self.pending.push(HandlerOperation::InterfaceOperation(
+            InterfaceOperation::ChannelEvent(event),
+        ));
+        true
+    }
+
+    fn handle_global_op(&mut self, _op: &GlobalHandlerOperation) {}
+    fn get_global_operations(&mut self) -> Option<Vec<GlobalHandlerOperation>> {
+        None
+    }
+
+    fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            let res = Some(self.pending.clone());
+            self.pending.clear();
+            res
+        }
+    }
+
+    fn get_default_operations(&mut self) -> Vec<Self::Operation> {
+        vec![]
+    }
+}
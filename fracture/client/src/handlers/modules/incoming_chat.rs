@@ -1,6 +1,8 @@
 use crate::handlers::imports::{GlobalHandlerOperation, HandlerOperation, MessageHandler};
-use crate::types::{ChatMessage, InterfaceOperation};
-use fracture_core::msg::MessageVarient::ServerSendChat;
+use crate::types::{ChatMessage, InterfaceOperation, RawMessage};
+use fracture_core::msg::MessageVarient::{
+    ChatFailed, ModerationResult, RateLimited, ServerSendChat, ServerSendDirect,
+};
 
 pub struct IncomingChatHandler {
     pending: Vec<HandlerOperation>,
@@ -15,19 +17,60 @@ impl MessageHandler for IncomingChatHandler {
 
     fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
         if let ServerSendChat {
+            id,
             author,
             content,
             author_uuid,
+            channel,
+            ..
         } = msg.data.clone()
         {
             println!(
-                "Received chat (uuid: {}): <{}> {} ",
-                author_uuid, author, content
+                "Received chat {} in {} (uuid: {}): <{}> {} ",
+                id, channel, author_uuid, author, content
             );
             self.pending.push(HandlerOperation::InterfaceOperation(
                 InterfaceOperation::ReceivedChat(ChatMessage::try_from(msg.data.clone()).unwrap()),
             ));
             true
+        } else if let ServerSendDirect {
+            id,
+            author,
+            content,
+            author_uuid,
+            ..
+        } = msg.data.clone()
+        {
+            println!(
+                "Received direct message {} (uuid: {}): <{}> {} ",
+                id, author_uuid, author, content
+            );
+            self.pending.push(HandlerOperation::InterfaceOperation(
+                InterfaceOperation::ReceivedChat(ChatMessage::try_from(msg.data.clone()).unwrap()),
+            ));
+            true
+        } else if let ChatFailed { nonce, reason } = msg.data.clone() {
+            println!("Message {} was not sent: {}", nonce, reason);
+            self.pending.push(HandlerOperation::InterfaceOperation(
+                InterfaceOperation::ChatFailed { nonce, reason },
+            ));
+            true
+        } else if let RateLimited { retry_after_ms, warnings_left } = msg.data {
+            let wait = (retry_after_ms as f64 / 1000.0).max(0.1);
+            let warning = match warnings_left {
+                0 => format!("You are sending messages too fast, wait {:.1}s. You will be disconnected if you do it again", wait),
+                left => format!("You are sending messages too fast, wait {:.1}s ({} warnings left)", wait, left),
+            };
+            self.pending.push(HandlerOperation::InterfaceOperation(
+                InterfaceOperation::ReceivedRawMessage(RawMessage::new(warning)),
+            ));
+            true
+        } else if let ModerationResult { ok, message } = msg.data.clone() {
+            let message = if ok { message } else { format!("Could not do that: {}", message) };
+            self.pending.push(HandlerOperation::InterfaceOperation(
+                InterfaceOperation::ReceivedRawMessage(RawMessage::new(message)),
+            ));
+            true
         } else {
             false
         }
@@ -1,4 +1,6 @@
+pub mod channels;
 pub mod incoming_chat;
 pub mod server_info_update;
 /// All handlers go here
 pub mod test_handler;
+pub mod typing;
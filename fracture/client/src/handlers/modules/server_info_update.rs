@@ -23,42 +23,48 @@ impl MessageHandler for InfoUpdateHandler {
         {
             for update in user_updates {
                 self.pending.push(HandlerOperation::InterfaceOperation(
-                    InterfaceOperation::ReceivedRawMessage(match update {
-                        UserNameUpdate::NewUser { uuid } => {
-                            if !SHOW_USERS_UUIDS {
-                                return true;
-                            }
-                            let dconv_uuid = uuid::Uuid::from_u128(uuid);
-                            RawMessage::new(format!("New user joined with uuid {}", dconv_uuid))
+                    InterfaceOperation::RosterChanged(update.clone().into()),
+                ));
+                let announcement = match update {
+                    // shown in the roster, announcing every time someone goes idle would be too much
+                    UserNameUpdate::PresenceChanged { .. } => continue,
+                    UserNameUpdate::NewUser { uuid } => {
+                        if !SHOW_USERS_UUIDS {
+                            continue;
                         }
-                        UserNameUpdate::UserNamed { uuid, name } => {
-                            let dconv_uuid = uuid::Uuid::from_u128(uuid);
-                            if SHOW_USERS_UUIDS {
-                                RawMessage::new(format!(
-                                    "User {} named themselves {}",
-                                    dconv_uuid, name
-                                ))
-                            } else {
-                                RawMessage::new(
-                                    format!("{} joined the server.", name), //join message here, since it was not shown earlyer
-                                )
-                            }
+                        let dconv_uuid = uuid::Uuid::from_u128(uuid);
+                        RawMessage::new(format!("New user joined with uuid {}", dconv_uuid))
+                    }
+                    UserNameUpdate::UserNamed { uuid, name } => {
+                        let dconv_uuid = uuid::Uuid::from_u128(uuid);
+                        if SHOW_USERS_UUIDS {
+                            RawMessage::new(format!(
+                                "User {} named themselves {}",
+                                dconv_uuid, name
+                            ))
+                        } else {
+                            RawMessage::new(
+                                format!("{} joined the server.", name), //join message here, since it was not shown earlyer
+                            )
                         }
-                        UserNameUpdate::UserLeft { uuid, name } => {
-                            let dconv_uuid = uuid::Uuid::from_u128(uuid);
-                            if let Some(name) = name {
-                                RawMessage::new(if SHOW_USERS_UUIDS {
-                                    format!("{} (id: {}) left the server.", name, dconv_uuid)
-                                } else {
-                                    format!("{} left the server.", name)
-                                })
+                    }
+                    UserNameUpdate::UserLeft { uuid, name } => {
+                        let dconv_uuid = uuid::Uuid::from_u128(uuid);
+                        if let Some(name) = name {
+                            RawMessage::new(if SHOW_USERS_UUIDS {
+                                format!("{} (id: {}) left the server.", name, dconv_uuid)
                             } else {
-                                RawMessage::new(
-                                    format!("unnamed user (id: {}) left the server.", dconv_uuid), //show UUID anyway, because there is nothing left to identify it by
-                                )
-                            }
+                                format!("{} left the server.", name)
+                            })
+                        } else {
+                            RawMessage::new(
+                                format!("unnamed user (id: {}) left the server.", dconv_uuid), //show UUID anyway, because there is nothing left to identify it by
+                            )
                         }
-                    }),
+                    }
+                };
+                self.pending.push(HandlerOperation::InterfaceOperation(
+                    InterfaceOperation::ReceivedRawMessage(announcement),
                 ));
             }
             true
@@ -0,0 +1,49 @@
+use crate::handlers::imports::{GlobalHandlerOperation, HandlerOperation, MessageHandler};
+use crate::types::{ChatTarget, InterfaceOperation};
+use fracture_core::msg::MessageVarient::UserTyping;
+
+/// Passes on who is typing to the user
+pub struct TypingHandler {
+    pending: Vec<HandlerOperation>,
+}
+
+impl MessageHandler for TypingHandler {
+    type Operation = HandlerOperation;
+
+    fn new() -> Box<Self> {
+        Box::new(Self { pending: vec![] })
+    }
+
+    fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
+        if let UserTyping { user, name, target, typing } = msg.data.clone() {
+            self.pending.push(HandlerOperation::InterfaceOperation(
+                InterfaceOperation::Typing {
+                    user: uuid::Uuid::from_u128(user),
+                    name,
+                    target: ChatTarget::from(target),
+                    typing,
+                },
+            ));
+            true
+        } else {
+            false
+        }
+    }
+
+    fn handle_global_op(&mut self, _op: &GlobalHandlerOperation) {}
+    fn get_global_operations(&mut self) -> Option<Vec<GlobalHandlerOperation>> {
+        None
+    }
+
+    fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.drain(0..).collect())
+        }
+    }
+
+    fn get_default_operations(&mut self) -> Vec<Self::Operation> {
+        vec![]
+    }
+}
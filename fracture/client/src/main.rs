@@ -2,6 +2,7 @@ mod args;
 mod client;
 mod handlers;
 mod main_task;
+mod tls;
 mod types;
 mod ui;
 
@@ -39,6 +40,9 @@ fn main() -> Result<(), ()> {
                 comm_outgoing_recv,
                 args.addr,
                 args.name,
+                args.tls,
+                args.trust_new_cert,
+                args.credentials,
             ))
             .expect("Connected sucsessuflly to server");
     });
@@ -46,6 +50,7 @@ fn main() -> Result<(), ()> {
     FractureClientGUI::run(Settings::with_flags(FractureGUIFlags::new(
         gui_comm_channels,
         name_clone,
+        args.away_after,
     )))
     .unwrap();
     comm_res.join().unwrap();
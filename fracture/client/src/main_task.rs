@@ -1,4 +1,6 @@
+use std::collections::VecDeque;
 use std::sync::mpsc::{Receiver as MPSCReceiver, Sender as MPSCSender};
+use std::time::Duration;
 
 use tokio::io;
 use tokio::join;
@@ -6,21 +8,50 @@ use tokio::net::TcpStream;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::task;
 use tokio::task::JoinHandle;
+use tokio::time::sleep;
 
 use fracture_core::msg;
 use fracture_core::stat;
+use fracture_core::transport::BoxedTransport;
 use fracture_core::utils::wait_update_time;
 
 use crate::client::Client;
+use crate::conf::{RECONNECT_MAX_DELAY_SECS, RECONNECT_MIN_DELAY_MS};
 use crate::handlers::get_default;
-use crate::types::{stati, ShutdownMessage};
+use crate::tls;
+use crate::types::{stati, Credentials, ShutdownMessage};
 
 use crate::{types, CommMessage};
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
 pub enum CommMainError {
+    #[error("Connection Refused! The server may not be online, or there may be a problem with the network: {0}")]
     ConnectionRefused(std::io::Error),
+    #[error("Error while connecting: {0}")]
     GenericConnectionError(std::io::Error),
+    #[error("{0}")]
+    Tls(tls::TlsConnectError),
+}
+
+/// What carries over from one connection to the next, when reconnecting
+struct Session {
+    comm_recv: MPSCReceiver<CommMessage>,
+    /// messages from the GUI that are waiting for the client to be ready, like ones typed while reconnecting
+    held: VecDeque<CommMessage>,
+    name: String,
+    credentials: Credentials,
+    /// token to resume the session with, if the server gave one
+    token: Option<String>,
+    /// if this is a new connection after losing the last one, and the GUI has not been told it is back yet
+    reconnecting: bool,
+}
+
+/// Why a connection ended
+enum SessionEnd {
+    /// it was closed on purpose, refused or kicked, so there is no point reconnecting
+    Closed,
+    /// the connection was lost, so the client should reconnect
+    Lost(String),
 }
 
 pub async fn comm_main(
@@ -28,47 +59,149 @@ pub async fn comm_main(
     comm_recv: MPSCReceiver<CommMessage>,
     addr: std::net::SocketAddrV4,
     name: String,
+    use_tls: bool,
+    trust_new_cert: bool,
+    credentials: Credentials,
 ) -> Result<(), CommMainError> {
-    let stream = match TcpStream::connect(addr).await {
-        Ok(st) => st,
+    if !use_tls {
+        eprintln!("Warning: not using TLS, messages will be sent unencrypted");
+    }
+    let stream = match connect(addr, use_tls, trust_new_cert).await {
+        Ok(stream) => stream,
         Err(err) => {
-            use std::io::ErrorKind::ConnectionRefused;
-
-            let return_val = if err.kind() == ConnectionRefused {
-                eprintln!("Connection Refused! The server may not be online, or there may be a problem with the network. Error folows:\n{:#?}", err);
-                Err(CommMainError::ConnectionRefused(err))
-            } else {
-                eprintln!("Error while connecting:\n{:#?}", err);
-                Err(CommMainError::GenericConnectionError(err))
-            };
             eprintln!("Aborting!");
-            return return_val;
+            return Err(err);
         }
     };
     let (shutdown_tx, _): (Sender<ShutdownMessage>, Receiver<ShutdownMessage>) = channel(5);
     let ctrlc_transmitter = shutdown_tx.clone();
+    let session = Session {
+        comm_recv,
+        held: VecDeque::new(),
+        name,
+        credentials,
+        token: None,
+        reconnecting: false,
+    };
 
     let _task_results = join!(
-        get_main_task(shutdown_tx, stream, name, comm_send, comm_recv),
+        run_sessions(shutdown_tx, stream, session, comm_send, addr, use_tls, trust_new_cert),
         get_ctrlc_listener(ctrlc_transmitter)
     );
     println!("Exited");
     Ok(())
 }
 
-pub fn get_main_task(
+/// Open a connection to the server, printing what went wrong if it could not be
+async fn connect(
+    addr: std::net::SocketAddrV4,
+    use_tls: bool,
+    trust_new_cert: bool,
+) -> Result<BoxedTransport, CommMainError> {
+    let stream = match TcpStream::connect(addr).await {
+        Ok(st) => st,
+        Err(err) => {
+            use std::io::ErrorKind::ConnectionRefused;
+
+            let err = if err.kind() == ConnectionRefused {
+                CommMainError::ConnectionRefused(err)
+            } else {
+                CommMainError::GenericConnectionError(err)
+            };
+            eprintln!("{}", err);
+            return Err(err);
+        }
+    };
+    if use_tls {
+        match tls::connect(stream, &addr, trust_new_cert).await {
+            Ok(tls_stream) => Ok(Box::new(tls_stream)),
+            Err(err) => {
+                let err = CommMainError::Tls(err);
+                eprintln!("{}", err);
+                Err(err)
+            }
+        }
+    } else {
+        Ok(Box::new(stream))
+    }
+}
+
+/// Run the client until it is closed, reconnecting every time the connection is lost
+async fn run_sessions(
     shutdown_tx: Sender<ShutdownMessage>,
-    stream: TcpStream,
-    name: String,
+    mut stream: BoxedTransport,
+    mut session: Session,
     comm_send: MPSCSender<CommMessage>,
-    comm_recv: MPSCReceiver<CommMessage>,
-) -> JoinHandle<()> {
+    addr: std::net::SocketAddrV4,
+    use_tls: bool,
+    trust_new_cert: bool,
+) {
+    loop {
+        let (returned, end) = get_main_task(shutdown_tx.clone(), stream, session, comm_send.clone())
+            .await
+            .expect("main task exited normally");
+        session = returned;
+        let reason = match end {
+            SessionEnd::Closed => return,
+            SessionEnd::Lost(reason) => reason,
+        };
+        let _ = comm_send.send(CommMessage::ConnectionLost { reason });
+        stream = match reconnect(&shutdown_tx, &comm_send, addr, use_tls, trust_new_cert).await {
+            Some(stream) => stream,
+            None => return,
+        };
+        session.reconnecting = true;
+    }
+}
+
+/// Keep trying to connect to the server, waiting twice as long after every failed attempt.
+/// returns None if the user quit while waiting
+async fn reconnect(
+    shutdown_tx: &Sender<ShutdownMessage>,
+    comm_send: &MPSCSender<CommMessage>,
+    addr: std::net::SocketAddrV4,
+    use_tls: bool,
+    trust_new_cert: bool,
+) -> Option<BoxedTransport> {
+    let mut close_rcv = shutdown_tx.subscribe();
+    let mut delay = Duration::from_millis(RECONNECT_MIN_DELAY_MS);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        println!("Reconnecting in {:?} (attempt {})", delay, attempt);
+        let _ = comm_send.send(CommMessage::Reconnecting { attempt, delay });
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = close_rcv.recv() => {
+                return None;
+            }
+        }
+        if let Ok(stream) = connect(addr, use_tls, trust_new_cert).await {
+            return Some(stream);
+        }
+        delay = (delay * 2).min(Duration::from_secs(RECONNECT_MAX_DELAY_SECS));
+    }
+}
+
+fn get_main_task(
+    shutdown_tx: Sender<ShutdownMessage>,
+    stream: BoxedTransport,
+    mut session: Session,
+    comm_send: MPSCSender<CommMessage>,
+) -> JoinHandle<(Session, SessionEnd)> {
     tokio::spawn(async move {
         let mut close_rcv = shutdown_tx.subscribe();
-        let mut client = Client::new(stream, name, get_default(), comm_send.clone());
+        let mut client = Client::new(
+            stream,
+            session.name.clone(),
+            get_default(),
+            comm_send.clone(),
+            session.credentials.clone(),
+            session.token.take(),
+        );
+        let mut end = SessionEnd::Closed;
         loop {
             tokio::select! {
-                //TODO fix this so that it wont get canceled whiel reading a message
                 stat = client.update_read() => {
                     match stat {
                         Ok(_) => {}
@@ -82,21 +215,44 @@ pub fn get_main_task(
                                         }
                                         ServerDisconnectReason::Closed => {
                                             println!("Disconnected by server: {}", close_message);
+                                            end = SessionEnd::Lost(format!("the server closed the connection: {}", close_message));
                                         }
                                         ServerDisconnectReason::InvalidConnectionSequence => {
                                             println!("Kicked for invalid connection sequence:\n{}", close_message);
                                         }
+                                        ServerDisconnectReason::MessageTooLarge => {
+                                            println!("Kicked for sending a message that was too large:\n{}", close_message);
+                                        }
+                                        ServerDisconnectReason::RateLimited => {
+                                            println!("Kicked for sending messages too fast:\n{}", close_message);
+                                        }
+                                        ServerDisconnectReason::Kicked => {
+                                            println!("Kicked by an admin:\n{}", close_message);
+                                            let _ = comm_send.send(CommMessage::Disconnected { reason: close_message });
+                                        }
+                                        ServerDisconnectReason::Banned => {
+                                            println!("Banned from the server:\n{}", close_message);
+                                            let _ = comm_send.send(CommMessage::Disconnected { reason: close_message });
+                                        }
                                     }
                                     client.close(stati::CloseType::ServerDisconnected).await;
                                     break;
                                 }
+                                stati::UpdateReadError::ConnectionRefused { reason } => {
+                                    eprintln!("Connection to server refused!\n{}", reason);
+                                    let _ = comm_send.send(CommMessage::ConnectionRefused { reason });
+                                    client.close(stati::CloseType::ServerDisconnected).await;
+                                    break;
+                                }
                                 stati::UpdateReadError::ServerDisconnect => {
                                     println!("Server disconnected!");
+                                    end = SessionEnd::Lost(String::from("the server closed the connection"));
                                     client.close(stati::CloseType::ServerDisconnected).await;
                                     break;
                                 }
                                 stati::UpdateReadError::ReadError (err) => {
                                     eprintln!("Error whilst reading message!\n{:#?}", err);
+                                    end = SessionEnd::Lost(String::from("could not read from the server"));
                                     client.close(stati::CloseType::Force).await;
                                     break;
                                 }
@@ -105,11 +261,48 @@ pub fn get_main_task(
                     };
                 }
                 _ = wait_update_time() => {// client update loop
-                    while let Ok(cmsg) = comm_recv.try_recv() {
+                    if client.is_server_dead() {
+                        println!("Connection to the server was lost");
+                        end = SessionEnd::Lost(String::from("the server stopped answering"));
+                        client.close(stati::CloseType::ServerDisconnected).await;
+                        break;
+                    }
+                    client.send_heartbeat();
+                    session.held.extend(session.comm_recv.try_iter());
+                    let ready = client.is_ready();
+                    for cmsg in std::mem::take(&mut session.held) {
                         match cmsg {
+                            CommMessage::SubmitPassword(password) => {
+                                client.submit_password(password);
+                            }
+                            CommMessage::SubmitLogin { password, register } => {
+                                client.submit_login(password, register);
+                            }
+                            // anything else would confuse the server during the handshake, so it waits
+                            cmsg if !ready => {
+                                session.held.push_back(cmsg);
+                            }
                             CommMessage::SendChat(msg) => {
                                 client.manual_handler_operation(types::HandlerOperation::ServerMsg{msg: msg.into()});
                             }
+                            CommMessage::ChannelCommand(command) => {
+                                client.queue_msg(command.into());
+                            }
+                            CommMessage::SetTyping { target, typing } => {
+                                client.queue_msg(fracture_core::msg::Message {
+                                    data: fracture_core::msg::MessageVarient::Typing { target: target.into(), typing },
+                                });
+                            }
+                            CommMessage::SetPresence { status, text } => {
+                                client.queue_msg(fracture_core::msg::Message {
+                                    data: fracture_core::msg::MessageVarient::SetPresence { status, text },
+                                });
+                            }
+                            CommMessage::Moderate { target, action, reason } => {
+                                client.queue_msg(fracture_core::msg::Message {
+                                    data: fracture_core::msg::MessageVarient::Moderate { target, action, reason },
+                                });
+                            }
                             other => {
                                 panic!("Client handler received unexpected CommMessage\n{:#?}", other);
                             }
@@ -120,9 +313,9 @@ pub fn get_main_task(
                         Err(e) => {
                             use stati::UpdateError;
                             match e {
-                                UpdateError::ConnectionRefused => {
-                                    //TODO this should actualy never happen, so remove it or implement it
-                                    eprintln!("Connection to server refused!");
+                                UpdateError::ConnectionRefused (reason) => {
+                                    eprintln!("Connection to server refused!\n{}", reason);
+                                    let _ = comm_send.send(CommMessage::ConnectionRefused { reason });
                                     client.close(stati::CloseType::ServerDisconnected).await;
                                     break;
                                 }
@@ -136,12 +329,18 @@ pub fn get_main_task(
                                 }
                                 UpdateError::SendError(err) => {
                                     eprintln!("Send error: {:#?}", err);
+                                    end = SessionEnd::Lost(String::from("could not send to the server"));
                                     client.close(stati::CloseType::Force).await;
                                     break;
                                 }
                             }
                         }
                     };
+                    if session.reconnecting && client.is_ready() {
+                        session.reconnecting = false;
+                        println!("Reconnected (resumed session: {})", client.resumed());
+                        let _ = comm_send.send(CommMessage::Reconnected { resumed: client.resumed() });
+                    }
                     client.collect_actions();
                     loop {
                         match client.execute_action().await {
@@ -151,17 +350,30 @@ pub fn get_main_task(
                                     match msg {
                                         HandlerOperation::InterfaceOperation (oper) => {
                                             match oper {
-                                                InterfaceOperation::ReceivedChat (msg) => {
-                                                    //Ignore messages from self
+                                                InterfaceOperation::ReceivedChat (mut msg) => {
                                                     if msg.author_uuid.expect("message has a uuid") == client.server_info.clone().expect("glient has a uuid").client_uuid {
-                                                        println!("Ignoring {:?} because it was sent by this client", msg);
+                                                        msg.sent_by_self();
                                                     } else {
-                                                        comm_send.send(CommMessage::HandleChat(msg)).expect("GUI received CommMessage");
+                                                        // every client picks its own nonces, so they only mean something on messages from this one
+                                                        msg.nonce = None;
                                                     }
+                                                    comm_send.send(CommMessage::HandleChat(msg)).expect("GUI received CommMessage");
+                                                }
+                                                InterfaceOperation::ChatFailed { nonce, reason } => {
+                                                    comm_send.send(CommMessage::ChatFailed { nonce, reason }).expect("GUI received CommMessage");
                                                 }
                                                 InterfaceOperation::ReceivedRawMessage (msg) => {
                                                     comm_send.send(CommMessage::RawMessage(msg)).expect("GUI received CommMessage");
                                                 }
+                                                InterfaceOperation::RosterChanged (change) => {
+                                                    comm_send.send(CommMessage::RosterChanged(change)).expect("GUI received CommMessage");
+                                                }
+                                                InterfaceOperation::ChannelEvent (event) => {
+                                                    comm_send.send(CommMessage::ChannelEvent(event)).expect("GUI received CommMessage");
+                                                }
+                                                InterfaceOperation::Typing { user, name, target, typing } => {
+                                                    comm_send.send(CommMessage::Typing { user, name, target, typing }).expect("GUI received CommMessage");
+                                                }
                                                 #[allow(unreachable_patterns)]//not a problem
                                                 unexpected => {panic!("unhandled InterfaceOperation:\n{:#?}", unexpected)}
                                             }
@@ -192,12 +404,14 @@ pub fn get_main_task(
                                 } else {
                                     eprintln!("Error while sending message:\n{:#?}", err);
                                 }
+                                end = SessionEnd::Lost(String::from("could not send to the server"));
                                 client.close(stati::CloseType::ServerDisconnected).await;
                                 break;
                             }
                             stat::SendError::SeriError (err) => {
                                 panic!("Could not serialize msessage:\n{:#?}", err);
                             }
+                            stat::SendError::TooLarge { .. } => unreachable!("messages that are too large are skipped"),
                         }
                     }
                 }
@@ -208,7 +422,12 @@ pub fn get_main_task(
                 }
             };
         }
+        // the client may have been renamed, given a session, or had a password entered, which the next connection needs
+        session.name = client.name().to_string();
+        session.credentials = client.credentials().clone();
+        session.token = client.session_token().cloned();
         println!("Exiting");
+        (session, end)
     })
 }
 
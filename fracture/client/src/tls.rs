@@ -0,0 +1,160 @@
+/// TLS with trust-on-first-use certificate pinning
+///
+/// Servers use self signed certificates, so instead of checking them against a CA
+/// the fingerprint is remembered the first time we connect to a server (in `~/.fracture/known_servers.json`),
+/// and the connection is refused if it is ever different.
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddrV4;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, ServerName};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+
+use fracture_core::utils::fingerprint::fingerprint;
+
+/// servers do not have real domain names, and the certificate is checked by fingerprint, so any valid name will do
+const SERVER_NAME: &str = "fracture-server";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsConnectError {
+    #[error("TLS handshake failed: {0}")]
+    Handshake(io::Error),
+    #[error("The server certificate has changed (expected {expected}, got {found})")]
+    CertificateChanged { expected: String, found: String },
+    #[error("Could not access the known servers file {path}: {err}")]
+    KnownServers { path: PathBuf, err: io::Error },
+    #[error("The known servers file is corrupted: {0}")]
+    KnownServersFormat(#[from] serde_json::Error),
+}
+
+/// Fingerprints of servers we have connected to before, by address
+struct KnownServers {
+    path: PathBuf,
+    servers: HashMap<String, String>,
+}
+
+impl KnownServers {
+    fn path() -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".fracture")
+            .join("known_servers.json")
+    }
+
+    fn load() -> Result<Self, TlsConnectError> {
+        let path = Self::path();
+        let servers = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(TlsConnectError::KnownServers { path, err }),
+        };
+        Ok(Self { path, servers })
+    }
+
+    fn save(&self) -> Result<(), TlsConnectError> {
+        let to_err = |err| TlsConnectError::KnownServers {
+            path: self.path.clone(),
+            err,
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(to_err)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.servers)?).map_err(to_err)
+    }
+}
+
+/// Accepts the certificate if it matches the pinned one (or if there is none yet),
+/// and remembers what was presented so it can be pinned after the handshake
+struct PinningVerifier {
+    pinned: Option<String>,
+    trust_new_cert: bool,
+    presented: Mutex<Option<String>>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let presented = fingerprint(&end_entity.0);
+        *self.presented.lock().unwrap() = Some(presented.clone());
+        match &self.pinned {
+            Some(pinned) if *pinned != presented && !self.trust_new_cert => Err(
+                rustls::Error::General(String::from("server certificate fingerprint changed")),
+            ),
+            _ => Ok(ServerCertVerified::assertion()),
+        }
+    }
+}
+
+fn warn_certificate_changed(addr: &SocketAddrV4, expected: &str, found: &str) {
+    eprintln!("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@");
+    eprintln!("@    WARNING: THE SERVER CERTIFICATE HAS CHANGED!          @");
+    eprintln!("@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@");
+    eprintln!("The certificate presented by {} is not the one it used before.", addr);
+    eprintln!("Someone may be intercepting your connection, or the server may have regenerated its certificate.");
+    eprintln!("Expected fingerprint: {}", expected);
+    eprintln!("Presented fingerprint: {}", found);
+    eprintln!("Check the new fingerprint with the server admin, and then connect with --trust-new-cert to accept it.");
+}
+
+/// Do the TLS handshake over `stream`, pinning or checking the servers certificate
+pub async fn connect(
+    stream: TcpStream,
+    addr: &SocketAddrV4,
+    trust_new_cert: bool,
+) -> Result<TlsStream<TcpStream>, TlsConnectError> {
+    let mut known = KnownServers::load()?;
+    let key = addr.to_string();
+    let verifier = Arc::new(PinningVerifier {
+        pinned: known.servers.get(&key).cloned(),
+        trust_new_cert,
+        presented: Mutex::new(None),
+    });
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let connector = TlsConnector::from(Arc::new(config));
+    let server_name = ServerName::try_from(SERVER_NAME).expect("SERVER_NAME is a valid dns name");
+    let res = connector.connect(server_name, stream).await;
+    let presented = verifier.presented.lock().unwrap().clone();
+    match (res, &verifier.pinned, presented) {
+        (Ok(tls_stream), pinned, Some(presented)) => {
+            match pinned {
+                None => {
+                    println!("First connection to {}, trusting its certificate", addr);
+                    println!("Fingerprint: {}", presented);
+                }
+                Some(pinned) if *pinned != presented => {
+                    warn_certificate_changed(addr, pinned, &presented);
+                    println!("Trusting the new certificate, as --trust-new-cert was given");
+                }
+                Some(_) => return Ok(tls_stream),
+            }
+            known.servers.insert(key, presented);
+            known.save()?;
+            Ok(tls_stream)
+        }
+        (Err(_), Some(pinned), Some(presented)) if *pinned != presented => {
+            warn_certificate_changed(addr, pinned, &presented);
+            Err(TlsConnectError::CertificateChanged {
+                expected: pinned.clone(),
+                found: presented,
+            })
+        }
+        (Err(err), _, _) => Err(TlsConnectError::Handshake(err)),
+        (Ok(_), _, None) => unreachable!("the verifier is always run during a sucsessful handshake"),
+    }
+}
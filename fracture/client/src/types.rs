@@ -1,7 +1,11 @@
 use std::sync::mpsc::{Receiver as MPSCReceiver, Sender as MPSCSender};
 
-use iced::{Align, Element, Row, Text};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use iced::{button, Align, Button, Column, Container, Element, Length, Row, Text};
 
+use fracture_core::msg::types::{ModerationAction, Presence, PresenceStatus, Reaction, ReplyParent};
+
+use crate::ui::style;
 use crate::ui::types::GUIMessage;
 
 pub mod stati {
@@ -46,6 +50,8 @@ pub mod stati {
         },
         #[error("Server disconnected")]
         ServerDisconnect,
+        #[error("Connection refused: {reason}")]
+        ConnectionRefused { reason: String },
         #[error("Failed to read message: {0}")]
         ReadError(#[from] fracture_core::stat::ReadMessageError),
     }
@@ -64,13 +70,84 @@ pub mod stati {
         SendError(#[from] fracture_core::stat::SendError),
         #[error("Unhandled message: {:?}", 0)]
         Unhandled(msg::Message),
-        #[error("Connection refused")]
-        ConnectionRefused,
+        #[error("Connection refused: {0}")]
+        ConnectionRefused(String),
     }
 }
 
 pub trait ChatViewable<T> {
     fn view(&mut self) -> Element<T>;
+
+    /// Get at the chat message, if this is one
+    fn as_chat_mut(&mut self) -> Option<&mut ChatMessage> {
+        None
+    }
+}
+
+/// Where a chat message was said
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatTarget {
+    Channel(String),
+    /// a private conversation with the user with this uuid
+    Direct(uuid::Uuid),
+}
+
+impl From<fracture_core::msg::types::TypingTarget> for ChatTarget {
+    fn from(item: fracture_core::msg::types::TypingTarget) -> Self {
+        use fracture_core::msg::types::TypingTarget;
+        match item {
+            TypingTarget::Channel(channel) => ChatTarget::Channel(channel),
+            TypingTarget::Direct(uuid) => ChatTarget::Direct(uuid::Uuid::from_u128(uuid)),
+        }
+    }
+}
+
+impl From<ChatTarget> for fracture_core::msg::types::TypingTarget {
+    fn from(item: ChatTarget) -> Self {
+        match item {
+            ChatTarget::Channel(channel) => Self::Channel(channel),
+            ChatTarget::Direct(uuid) => Self::Direct(uuid.as_u128()),
+        }
+    }
+}
+
+/// Convert a timestamp from the server (milliseconds since the unix epoch, UTC) to local time.
+/// messages from before the server kept timestamps have 0, which means unknown
+fn local_time(timestamp: i64) -> Option<DateTime<Local>> {
+    if timestamp <= 0 {
+        return None;
+    }
+    Utc.timestamp_millis_opt(timestamp)
+        .single()
+        .map(|time| time.with_timezone(&Local))
+}
+
+/// If a message this client sent got to the server
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeliveryStatus {
+    /// sent, and waiting for the server to send it back
+    Pending,
+    Delivered,
+    Failed(String),
+}
+
+/// The buttons on a chat message
+#[derive(Clone, Debug, Default)]
+struct MessageButtons {
+    reply: button::State,
+    react: button::State,
+    quote: button::State,
+    thread: button::State,
+}
+
+/// Everyone who reacted to a message with one emoji, shown as a button under it
+#[derive(Clone, Debug)]
+struct ReactionChip {
+    emoji: String,
+    count: usize,
+    /// this user is one of them
+    mine: bool,
+    button: button::State,
 }
 
 #[derive(Clone, Debug)]
@@ -78,43 +155,282 @@ pub struct ChatMessage {
     msg_text: String,
     author_name: String,
     pub author_uuid: Option<uuid::Uuid>,
+    pub target: ChatTarget,
+    /// given by the server, None for messages this client is sending
+    pub id: Option<u64>,
+    /// when the server got it, or when it was sent for messages from this client
+    pub time: Option<DateTime<Local>>,
+    /// picked by this client when sending, so the servers copy can be matched up with it
+    pub nonce: Option<u64>,
+    pub status: DeliveryStatus,
+    /// who a direct message was sent to
+    recipient: Option<uuid::Uuid>,
+    pub edited: bool,
+    pub deleted: bool,
+    /// the message this replies to
+    pub parent: Option<ReplyParent>,
+    /// the user jumped to this message
+    pub highlighted: bool,
+    reactions: Vec<ReactionChip>,
+    buttons: MessageButtons,
 }
 
 impl ChatMessage {
-    pub fn new(msg_text: String, author_name: String) -> Self {
+    /// A message this client is sending
+    pub fn new(
+        msg_text: String,
+        author_name: String,
+        target: ChatTarget,
+        nonce: u64,
+        parent: Option<ReplyParent>,
+    ) -> Self {
         Self {
             msg_text,
             author_name,
             author_uuid: None,
+            target,
+            id: None,
+            time: Some(Local::now()),
+            nonce: Some(nonce),
+            status: DeliveryStatus::Pending,
+            recipient: None,
+            edited: false,
+            deleted: false,
+            parent,
+            highlighted: false,
+            reactions: vec![],
+            buttons: MessageButtons::default(),
+        }
+    }
+
+    /// This is the servers copy of a message this client sent, so a direct message belongs with whoever it was sent to
+    pub fn sent_by_self(&mut self) {
+        if let Some(recipient) = self.recipient {
+            self.target = ChatTarget::Direct(recipient);
         }
     }
+
+    /// A message that was said in `channel` before the user saw it. `own_uuid` is who this user is, to show which reactions are theirs
+    pub fn from_history(
+        message: fracture_core::msg::types::HistoryMessage,
+        channel: String,
+        own_uuid: Option<uuid::Uuid>,
+    ) -> Self {
+        let mut msg = Self {
+            msg_text: message.content,
+            author_name: message.author,
+            author_uuid: Some(uuid::Uuid::from_u128(message.author_uuid)),
+            target: ChatTarget::Channel(channel),
+            id: Some(message.id),
+            time: local_time(message.timestamp),
+            nonce: None,
+            status: DeliveryStatus::Delivered,
+            recipient: None,
+            edited: message.edited_at.is_some(),
+            deleted: message.deleted,
+            parent: message.parent,
+            highlighted: false,
+            reactions: vec![],
+            buttons: MessageButtons::default(),
+        };
+        msg.set_reactions(message.reactions, own_uuid);
+        msg
+    }
+
+    /// Show these reactions under the message, in place of the ones there were
+    pub fn set_reactions(&mut self, reactions: Vec<Reaction>, own_uuid: Option<uuid::Uuid>) {
+        let own_uuid = own_uuid.map(|uuid| uuid.as_u128());
+        self.reactions = reactions
+            .into_iter()
+            .map(|reaction| ReactionChip {
+                count: reaction.users.len(),
+                mine: own_uuid.is_some_and(|uuid| reaction.users.contains(&uuid)),
+                emoji: reaction.emoji,
+                button: button::State::new(),
+            })
+            .collect();
+    }
+
+    /// If this user reacted to the message with `emoji`
+    pub fn reacted(&self, emoji: &str) -> bool {
+        self.reactions.iter().any(|chip| chip.mine && chip.emoji == emoji)
+    }
+
+    /// This message, to quote above a reply to it
+    pub fn as_parent(&self) -> Option<ReplyParent> {
+        Some(ReplyParent {
+            id: self.id?,
+            author: self.author_name.clone(),
+            snippet: self.msg_text.clone(),
+        })
+    }
+
+    /// The author changed what the message says
+    pub fn edit(&mut self, msg_text: String) {
+        self.msg_text = msg_text;
+        self.edited = true;
+    }
+
+    pub fn delete(&mut self) {
+        self.msg_text.clear();
+        self.deleted = true;
+    }
+
+    pub fn author_name(&self) -> &str {
+        &self.author_name
+    }
 }
 
 impl ChatViewable<GUIMessage> for ChatMessage {
     fn view(&mut self) -> Element<GUIMessage> {
-        Row::new()
+        let MessageButtons { reply, react, quote, thread } = &mut self.buttons;
+        let mut row = Row::new()
             .align_items(Align::Start)
             .spacing(4)
             .padding(3)
+            .push(Text::new(match self.time {
+                // older messages need the day too, or it looks like they were said today
+                Some(time) if time.date_naive() != Local::now().date_naive() => {
+                    time.format("%Y-%m-%d %H:%M").to_string()
+                }
+                Some(time) => time.format("%H:%M").to_string(),
+                None => String::from("--:--"),
+            }).size(14))
             .push(Text::new(self.author_name.clone() + ": "))
-            .push(Text::new(self.msg_text.clone()))
-            .into()
+            .push(if self.deleted {
+                Text::new("message deleted").color(iced::Color::from_rgb8(140, 140, 140))
+            } else {
+                Text::new(self.msg_text.clone())
+            })
+            .push(Text::new(match &self.status {
+                DeliveryStatus::Pending => String::from("(sending...)"),
+                DeliveryStatus::Delivered if self.edited && !self.deleted => String::from("(edited)"),
+                DeliveryStatus::Delivered => String::new(),
+                DeliveryStatus::Failed(reason) => format!("(not sent: {})", reason),
+            }).size(14));
+        // only channel messages are kept, so only they can be replied or reacted to
+        if let (Some(id), ChatTarget::Channel(_), false) = (self.id, &self.target, self.deleted) {
+            row = row
+                .push(
+                    Button::new(reply, Text::new("reply").size(14))
+                        .on_press(GUIMessage::Reply(id))
+                        .style(style::chat::Link),
+                )
+                .push(
+                    Button::new(react, Text::new("react").size(14))
+                        .on_press(GUIMessage::React(id))
+                        .style(style::chat::Link),
+                );
+        }
+        let mut body = Column::new();
+        if let Some(parent) = &self.parent {
+            let snippet = if parent.snippet.is_empty() {
+                String::from("message deleted")
+            } else {
+                parent.snippet.clone()
+            };
+            body = body.push(
+                Row::new()
+                    .spacing(4)
+                    .padding(3)
+                    .push(
+                        Button::new(quote, Text::new(format!("↪ {}: {}", parent.author, snippet)).size(14))
+                            .on_press(GUIMessage::JumpTo(parent.id))
+                            .style(style::chat::Quote),
+                    )
+                    .push(
+                        Button::new(thread, Text::new("thread").size(14))
+                            .on_press(GUIMessage::ShowThread(parent.id))
+                            .style(style::chat::Link),
+                    ),
+            );
+        }
+        body = body.push(row);
+        if let (Some(id), false) = (self.id, self.reactions.is_empty()) {
+            body = body.push(self.reactions.iter_mut().fold(
+                Row::new().spacing(4).padding(3),
+                |chips, chip| {
+                    chips.push(
+                        Button::new(&mut chip.button, Text::new(format!("{} {}", chip.emoji, chip.count)).size(14))
+                            .on_press(GUIMessage::ToggleReaction {
+                                message_id: id,
+                                emoji: chip.emoji.clone(),
+                            })
+                            .style(style::chat::Chip { mine: chip.mine }),
+                    )
+                },
+            ));
+        }
+        if self.highlighted {
+            Container::new(body)
+                .width(Length::Fill)
+                .style(style::chat::Highlight)
+                .into()
+        } else {
+            body.into()
+        }
+    }
+
+    fn as_chat_mut(&mut self) -> Option<&mut ChatMessage> {
+        Some(self)
     }
 }
 
 impl TryFrom<fracture_core::msg::MessageVarient> for ChatMessage {
     type Error = ();
     fn try_from(item: fracture_core::msg::MessageVarient) -> Result<Self, Self::Error> {
-        use fracture_core::msg::MessageVarient::ServerSendChat;
+        use fracture_core::msg::MessageVarient::{ServerSendChat, ServerSendDirect};
         match item {
             ServerSendChat {
+                id,
+                timestamp,
                 content,
                 author,
                 author_uuid,
+                channel,
+                nonce,
+                parent,
             } => Ok(Self {
                 msg_text: content,
                 author_name: author,
                 author_uuid: Some(uuid::Uuid::from_u128(author_uuid)),
+                target: ChatTarget::Channel(channel),
+                id: Some(id),
+                time: local_time(timestamp),
+                nonce,
+                status: DeliveryStatus::Delivered,
+                recipient: None,
+                edited: false,
+                deleted: false,
+                parent,
+                highlighted: false,
+                reactions: vec![],
+                buttons: MessageButtons::default(),
+            }),
+            ServerSendDirect {
+                id,
+                timestamp,
+                content,
+                author,
+                author_uuid,
+                to,
+                nonce,
+            } => Ok(Self {
+                msg_text: content,
+                author_name: author,
+                author_uuid: Some(uuid::Uuid::from_u128(author_uuid)),
+                target: ChatTarget::Direct(uuid::Uuid::from_u128(author_uuid)),
+                id: Some(id),
+                time: local_time(timestamp),
+                nonce,
+                status: DeliveryStatus::Delivered,
+                recipient: Some(uuid::Uuid::from_u128(to)),
+                edited: false,
+                deleted: false,
+                parent: None,
+                highlighted: false,
+                reactions: vec![],
+                buttons: MessageButtons::default(),
             }),
             _ => Err(()),
         }
@@ -123,14 +439,105 @@ impl TryFrom<fracture_core::msg::MessageVarient> for ChatMessage {
 
 impl From<ChatMessage> for fracture_core::msg::Message {
     fn from(item: ChatMessage) -> fracture_core::msg::Message {
+        use fracture_core::msg::MessageVarient;
+        let nonce = item.nonce.expect("only messages from this client are sent");
         fracture_core::msg::Message {
-            data: fracture_core::msg::MessageVarient::ClientSendChat {
-                content: item.msg_text,
+            data: match item.target {
+                ChatTarget::Channel(channel) => MessageVarient::ClientSendChat {
+                    content: item.msg_text,
+                    channel,
+                    nonce,
+                    parent: item.parent.map(|parent| parent.id),
+                },
+                ChatTarget::Direct(to) => MessageVarient::ClientSendDirect {
+                    content: item.msg_text,
+                    to: to.as_u128(),
+                    nonce,
+                },
             },
         }
     }
 }
 
+/// Something the user asked to do with channels
+#[derive(Clone, Debug)]
+pub enum ChannelCommand {
+    Join(String),
+    Leave(String),
+    Create { channel: String, topic: Option<String> },
+    List,
+    /// get the messages said in the channel before the message with the id `before` (or the newest ones)
+    History { channel: String, before: Option<u64> },
+    /// change what the message with the id `id` says
+    Edit { id: u64, content: String },
+    Delete { id: u64 },
+    /// get the message with the id `id`, and every reply to it
+    Thread { id: u64 },
+    /// react to the message with the id `message_id`, or take the reaction back if `add` is false
+    React { message_id: u64, emoji: String, add: bool },
+}
+
+impl From<ChannelCommand> for fracture_core::msg::Message {
+    fn from(item: ChannelCommand) -> fracture_core::msg::Message {
+        use fracture_core::msg::MessageVarient;
+        fracture_core::msg::Message {
+            data: match item {
+                ChannelCommand::Join(channel) => MessageVarient::JoinChannel { channel },
+                ChannelCommand::Leave(channel) => MessageVarient::LeaveChannel { channel },
+                ChannelCommand::Create { channel, topic } => {
+                    MessageVarient::CreateChannel { channel, topic }
+                }
+                ChannelCommand::List => MessageVarient::ListChannels {},
+                ChannelCommand::History { channel, before } => MessageVarient::RequestHistory {
+                    channel,
+                    before,
+                    limit: crate::conf::HISTORY_PAGE_SIZE,
+                },
+                ChannelCommand::Edit { id, content } => MessageVarient::EditChat { id, content },
+                ChannelCommand::Delete { id } => MessageVarient::DeleteChat { id },
+                ChannelCommand::Thread { id } => MessageVarient::RequestThread { id },
+                ChannelCommand::React { message_id, emoji, add: true } => {
+                    MessageVarient::AddReaction { message_id, emoji }
+                }
+                ChannelCommand::React { message_id, emoji, add: false } => {
+                    MessageVarient::RemoveReaction { message_id, emoji }
+                }
+            },
+        }
+    }
+}
+
+/// Something that happened with channels, that the user should see
+#[derive(Clone, Debug)]
+pub enum ChannelEvent {
+    Joined { channel: String, topic: Option<String> },
+    Left { channel: String },
+    List(Vec<fracture_core::msg::types::ChannelInfo>),
+    Error { channel: String, reason: String },
+    /// messages said in the channel before, oldest first. empty if there are no more
+    History {
+        channel: String,
+        messages: Vec<fracture_core::msg::types::HistoryMessage>,
+    },
+    Edited { channel: String, id: u64, content: String },
+    Deleted { channel: String, id: u64 },
+    /// the message with the id `id` could not be edited or deleted
+    ChangeFailed { id: u64, reason: String },
+    /// a message, and every reply to it (oldest first)
+    Thread {
+        channel: String,
+        parent: fracture_core::msg::types::HistoryMessage,
+        replies: Vec<fracture_core::msg::types::HistoryMessage>,
+    },
+    ThreadFailed { id: u64, reason: String },
+    /// every reaction to the message with the id `id`
+    Reactions {
+        channel: String,
+        id: u64,
+        reactions: Vec<Reaction>,
+    },
+}
+
 #[derive(Clone, Debug)]
 pub struct RawMessage {
     text: String,
@@ -153,11 +560,132 @@ impl ChatViewable<GUIMessage> for RawMessage {
     }
 }
 
+/// Someone who is connected to the server
+#[derive(Clone, Debug)]
+pub struct RosterEntry {
+    pub uuid: uuid::Uuid,
+    /// None untill the server says what they are called
+    pub name: Option<String>,
+    /// None untill the server says what they are up to, which means they are online
+    pub presence: Option<Presence>,
+}
+
+impl RosterEntry {
+    pub fn status(&self) -> PresenceStatus {
+        self.presence
+            .as_ref()
+            .map_or(PresenceStatus::Online, |presence| presence.status)
+    }
+
+    /// What to show under their name, like "away since 14:02 - lunch"
+    pub fn status_line(&self) -> Option<String> {
+        let presence = self.presence.as_ref()?;
+        let status = match presence.status {
+            PresenceStatus::Online => None,
+            PresenceStatus::Away => Some(match local_time(presence.last_seen) {
+                Some(since) => format!("away since {}", since.format("%H:%M")),
+                None => String::from("away"),
+            }),
+            PresenceStatus::Busy => Some(String::from("busy")),
+        };
+        match (status, &presence.text) {
+            (Some(status), Some(text)) => Some(format!("{} - {}", status, text)),
+            (status, text) => status.or_else(|| text.clone()),
+        }
+    }
+}
+
+/// A change to who is connected to the server
+#[derive(Clone, Debug)]
+pub enum RosterChange {
+    /// Everyone who is connected, sent when joining
+    Replace(Vec<RosterEntry>),
+    Joined(uuid::Uuid),
+    Named { uuid: uuid::Uuid, name: String },
+    Left(uuid::Uuid),
+    Presence { uuid: uuid::Uuid, presence: Presence },
+}
+
+impl From<fracture_core::msg::types::UserNameUpdate> for RosterChange {
+    fn from(item: fracture_core::msg::types::UserNameUpdate) -> Self {
+        use fracture_core::msg::types::UserNameUpdate;
+        match item {
+            UserNameUpdate::NewUser { uuid } => Self::Joined(uuid::Uuid::from_u128(uuid)),
+            UserNameUpdate::UserNamed { uuid, name } => Self::Named {
+                uuid: uuid::Uuid::from_u128(uuid),
+                name,
+            },
+            UserNameUpdate::UserLeft { uuid, name: _ } => Self::Left(uuid::Uuid::from_u128(uuid)),
+            UserNameUpdate::PresenceChanged { uuid, presence } => Self::Presence {
+                uuid: uuid::Uuid::from_u128(uuid),
+                presence,
+            },
+        }
+    }
+}
+
+/// Everyone who is connected to the server, kept up to date with `RosterChange`s
+#[derive(Clone, Debug, Default)]
+pub struct Roster {
+    users: Vec<RosterEntry>,
+}
+
+impl Roster {
+    pub fn users(&self) -> &[RosterEntry] {
+        &self.users
+    }
+
+    pub fn apply(&mut self, change: RosterChange) {
+        match change {
+            RosterChange::Replace(users) => {
+                self.users = users;
+            }
+            RosterChange::Joined(uuid) => {
+                if !self.users.iter().any(|user| user.uuid == uuid) {
+                    self.users.push(RosterEntry {
+                        uuid,
+                        name: None,
+                        presence: None,
+                    });
+                }
+            }
+            RosterChange::Named { uuid, name } => {
+                match self.users.iter_mut().find(|user| user.uuid == uuid) {
+                    Some(user) => user.name = Some(name),
+                    None => self.users.push(RosterEntry {
+                        uuid,
+                        name: Some(name),
+                        presence: None,
+                    }),
+                }
+            }
+            RosterChange::Left(uuid) => {
+                self.users.retain(|user| user.uuid != uuid);
+            }
+            RosterChange::Presence { uuid, presence } => {
+                if let Some(user) = self.users.iter_mut().find(|user| user.uuid == uuid) {
+                    user.presence = Some(presence);
+                }
+            }
+        }
+    }
+}
+
 /// For stuff to interact with the user
 #[derive(Clone, Debug)]
 pub enum InterfaceOperation {
     ReceivedChat(ChatMessage),
+    /// a message this client sent was not delivered
+    ChatFailed { nonce: u64, reason: String },
     ReceivedRawMessage(RawMessage),
+    RosterChanged(RosterChange),
+    ChannelEvent(ChannelEvent),
+    Typing {
+        user: uuid::Uuid,
+        name: String,
+        target: ChatTarget,
+        typing: bool,
+    },
 }
 
 //TODO add more of these
@@ -178,6 +706,8 @@ pub enum ClientState {
     Begin,
     /// sent the connect message, waiting for a response
     Hanshake,
+    /// the server wants a password or a login, waiting for the user to enter one
+    AwaitingPassword,
     /// Get all default operations
     GetHandlerDefaultOps,
     /// Ready for normal stuff
@@ -188,21 +718,108 @@ pub enum ClientState {
 pub struct ServerInfo {
     pub name: String,
     pub client_uuid: uuid::Uuid,
+    /// capabilities both this client and the server support
+    pub capabilities: Vec<String>,
+}
+
+impl ServerInfo {
+    /// If the capability `cap` was agreed on with the server
+    pub fn supports(&self, cap: &str) -> bool {
+        self.capabilities.iter().any(|supported| supported == cap)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct ShutdownMessage {}
 
+/// Passwords given on the command line (or entered for a earlier connection), so the user is not asked for them
+#[derive(Clone, Default)]
+pub struct Credentials {
+    /// sent if the server asks for a password
+    pub server_password: Option<String>,
+    /// used to log in to (or register) a account named after the client, if the server has accounts
+    pub account_password: Option<String>,
+    /// create the account instead of logging in to it
+    pub register: bool,
+}
+
 #[derive(Debug, Clone)]
 pub enum CommMessage {
     //GUI -> Comm
     SendChat(ChatMessage),
+    SubmitPassword(String),
+    SubmitLogin {
+        password: String,
+        register: bool,
+    },
+    ChannelCommand(ChannelCommand),
+    /// the user started or stopped typing in `target`
+    SetTyping {
+        target: ChatTarget,
+        typing: bool,
+    },
+    /// the user changed their status, or went idle or came back
+    SetPresence {
+        status: PresenceStatus,
+        text: Option<String>,
+    },
+    /// a admin kicked, muted or banned someone
+    Moderate {
+        target: String,
+        action: ModerationAction,
+        reason: Option<String>,
+    },
     //Comm -> GUI
     HandleChat(ChatMessage),
+    ChatFailed {
+        nonce: u64,
+        reason: String,
+    },
     RawMessage(RawMessage),
     ServerInfo {
         server_name: String,
-    }
+        /// who this user is
+        uuid: uuid::Uuid,
+    },
+    /// the server needs a password, and none was given on the command line
+    PasswordRequired,
+    /// the server needs the user to log in, and no account password was given on the command line
+    LoginRequired,
+    /// the name the user asked for was taken, so the server gave them this one
+    NameAssigned {
+        name: String,
+    },
+    RosterChanged(RosterChange),
+    ChannelEvent(ChannelEvent),
+    /// someone else started or stopped typing
+    Typing {
+        user: uuid::Uuid,
+        name: String,
+        target: ChatTarget,
+        typing: bool,
+    },
+    ConnectionRefused {
+        reason: String,
+    },
+    /// the user was kicked or banned, so the client is not reconnecting
+    Disconnected {
+        reason: String,
+    },
+    /// the connection to the server was lost, and the client is going to reconnect
+    ConnectionLost {
+        reason: String,
+    },
+    /// trying to reconnect after waiting `delay`
+    Reconnecting {
+        attempt: u32,
+        delay: std::time::Duration,
+    },
+    /// connected to the server again. if the session was not `resumed`, the server has forgotten which channels the user was in
+    Reconnected {
+        resumed: bool,
+    },
+    /// how long the last ping to the server took to be answered
+    Latency(std::time::Duration),
 }
 
 pub struct CommChannels {
@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+
+use iced::{button, scrollable, Align, Button, Column, Length, Row, Scrollable, Text};
+
+use fracture_core::msg::types::{ModerationAction, PresenceStatus};
+
+use super::style;
+use super::types::GUIMessage;
+use crate::types::{ChannelCommand, ChatMessage, ChatTarget, ChatViewable, DeliveryStatus};
+
+/// A channel the user is in (or a direct conversation with someone), with its own scrollback
+pub struct ChannelView {
+    pub target: ChatTarget,
+    /// the channel name, or who the conversation is with
+    pub name: String,
+    pub topic: Option<String>,
+    pub chat_elems: Vec<Box<dyn ChatViewable<GUIMessage>>>,
+    scroll_state: scrollable::State,
+    tab_button: button::State,
+    /// something was said here while another channel was open
+    pub unread: bool,
+    /// id of the oldest message from the history that is shown, so older ones can be asked for
+    pub oldest: Option<u64>,
+    /// ids of the messages shown, so none are shown twice
+    seen: HashSet<u64>,
+}
+
+impl ChannelView {
+    pub fn new(target: ChatTarget, name: String, topic: Option<String>) -> Self {
+        Self {
+            target,
+            name,
+            topic,
+            chat_elems: vec![],
+            scroll_state: scrollable::State::new(),
+            tab_button: button::State::new(),
+            unread: false,
+            oldest: None,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Show a message at the bottom, returning false if it is already shown
+    pub fn push_chat(&mut self, msg: ChatMessage) -> bool {
+        if let Some(id) = msg.id {
+            if !self.seen.insert(id) {
+                return false;
+            }
+        }
+        self.chat_elems.push(Box::new(msg));
+        true
+    }
+
+    /// Show a message from the server, in place of this clients pending copy if it is one.
+    /// returns false if nothing new is shown
+    pub fn receive_chat(&mut self, msg: ChatMessage) -> bool {
+        if let Some(pending) = msg.nonce.and_then(|nonce| find_pending(&mut self.chat_elems, nonce)) {
+            if let Some(id) = msg.id {
+                self.seen.insert(id);
+            }
+            *pending = msg;
+            return false;
+        }
+        self.push_chat(msg)
+    }
+
+    /// This clients message with `nonce`, if it is still waiting for the server
+    pub fn pending_chat(&mut self, nonce: u64) -> Option<&mut ChatMessage> {
+        find_pending(&mut self.chat_elems, nonce)
+    }
+
+    /// Mark every message still waiting for the server as not sent, for when the connection it was sent on is lost
+    pub fn fail_pending(&mut self, reason: &str) {
+        for chat in self.chat_elems.iter_mut().filter_map(|elem| elem.as_chat_mut()) {
+            if chat.status == DeliveryStatus::Pending {
+                chat.status = DeliveryStatus::Failed(reason.to_string());
+            }
+        }
+    }
+
+    /// The message with the id `id`, if it is shown
+    pub fn chat_by_id(&mut self, id: u64) -> Option<&mut ChatMessage> {
+        self.chat_elems
+            .iter_mut()
+            .rev()
+            .filter_map(|elem| elem.as_chat_mut())
+            .find(|chat| chat.id == Some(id))
+    }
+
+    /// Scroll to the message `id` and highlight it, returning false if it is not shown
+    pub fn jump_to(&mut self, id: u64) -> bool {
+        let mut found = None;
+        for (index, chat) in self.chat_elems.iter_mut().enumerate() {
+            if let Some(chat) = chat.as_chat_mut() {
+                chat.highlighted = chat.id == Some(id);
+                if chat.highlighted {
+                    found = Some(index);
+                }
+            }
+        }
+        match found {
+            Some(index) => {
+                // iced can only scroll by an amount, so guess how far down the message is. it is clamped to the end when drawn
+                let offset = index as f32 * crate::conf::CHAT_ROW_HEIGHT;
+                self.scroll_state.scroll_to(
+                    1.0,
+                    iced::Rectangle::default(),
+                    iced::Rectangle {
+                        height: offset,
+                        ..iced::Rectangle::default()
+                    },
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Id of the last message this client sent here that got to the server, and was not deleted
+    pub fn last_own_id(&mut self) -> Option<u64> {
+        self.chat_elems
+            .iter_mut()
+            .rev()
+            .filter_map(|elem| elem.as_chat_mut())
+            .find(|chat| chat.nonce.is_some() && !chat.deleted)
+            .and_then(|chat| chat.id)
+    }
+
+    /// Show messages from the history above everything else, skipping ones that are already shown
+    pub fn prepend_history(&mut self, messages: Vec<ChatMessage>) {
+        if let Some(oldest) = messages.iter().filter_map(|msg| msg.id).min() {
+            self.oldest = Some(oldest);
+        }
+        let older: Vec<Box<dyn ChatViewable<GUIMessage>>> = messages
+            .into_iter()
+            .filter(|msg| msg.id.is_none_or(|id| self.seen.insert(id)))
+            .map(|msg| Box::new(msg) as Box<dyn ChatViewable<GUIMessage>>)
+            .collect();
+        self.chat_elems.splice(0..0, older);
+    }
+
+    fn label(&self) -> String {
+        let label = match self.target {
+            ChatTarget::Channel(_) => format!("#{}", self.name),
+            ChatTarget::Direct(_) => format!("@{}", self.name),
+        };
+        if self.unread {
+            label + " *"
+        } else {
+            label
+        }
+    }
+}
+
+fn find_pending(
+    elems: &mut [Box<dyn ChatViewable<GUIMessage>>],
+    nonce: u64,
+) -> Option<&mut ChatMessage> {
+    elems
+        .iter_mut()
+        .rev()
+        .filter_map(|elem| elem.as_chat_mut())
+        .find(|chat| chat.nonce == Some(nonce) && chat.status == DeliveryStatus::Pending)
+}
+
+/// A command typed in the message box
+pub enum UserCommand {
+    Channel(ChannelCommand),
+    /// open a conversation with the user called `name`, and say `text` if there is any
+    Direct { name: String, text: Option<String> },
+    /// close the open direct conversation
+    CloseDirect,
+    /// show older messages in the open channel
+    History,
+    /// change what your last message in the open channel says
+    Edit { text: String },
+    /// delete the message with the id, or your last message in the open channel
+    Delete { id: Option<u64> },
+    /// reply to the message with the id `id` in the open channel
+    Reply { id: u64, text: String },
+    /// react to the message with the id `id`, or take the reaction back
+    React { id: u64, emoji: String },
+    /// tell everyone if you are online, away or busy, and what you are up to
+    Status { status: PresenceStatus, text: Option<String> },
+    /// kick, mute or ban someone, for admins
+    Moderate { target: String, action: ModerationAction, reason: Option<String> },
+}
+
+/// Split `<user> [reason]` for a moderation command
+fn target_and_reason(arg: &str) -> (String, Option<String>) {
+    let mut parts = arg.splitn(2, ' ');
+    let target = parts.next().unwrap_or_default().trim_start_matches('@').to_string();
+    let reason = parts.next().map(str::trim).filter(|reason| !reason.is_empty()).map(String::from);
+    (target, reason)
+}
+
+/// Parse a `/command` typed in the message box. returns None if it is not a command
+/// `current` is what is open, which `/leave` leaves if no channel is given
+pub fn parse_command(input: &str, current: Option<&ChatTarget>) -> Option<Result<UserCommand, String>> {
+    let input = input.strip_prefix('/')?;
+    let mut parts = input.splitn(2, ' ');
+    let command = parts.next().unwrap_or_default();
+    let arg = parts.next().map(str::trim).filter(|arg| !arg.is_empty());
+    Some(match (command, arg) {
+        ("join", Some(channel)) => Ok(UserCommand::Channel(ChannelCommand::Join(
+            channel.trim_start_matches('#').to_string(),
+        ))),
+        ("leave", Some(channel)) => Ok(UserCommand::Channel(ChannelCommand::Leave(
+            channel.trim_start_matches('#').to_string(),
+        ))),
+        ("leave", None) => match current {
+            Some(ChatTarget::Channel(channel)) => {
+                Ok(UserCommand::Channel(ChannelCommand::Leave(channel.clone())))
+            }
+            Some(ChatTarget::Direct(_)) => Ok(UserCommand::CloseDirect),
+            None => Err(String::from("You are not in a channel")),
+        },
+        ("create", Some(arg)) => {
+            let mut parts = arg.splitn(2, ' ');
+            let channel = parts.next().unwrap_or_default().trim_start_matches('#').to_string();
+            let topic = parts.next().map(str::trim).filter(|topic| !topic.is_empty()).map(String::from);
+            Ok(UserCommand::Channel(ChannelCommand::Create { channel, topic }))
+        }
+        ("list", None) => Ok(UserCommand::Channel(ChannelCommand::List)),
+        ("history", None) => Ok(UserCommand::History),
+        ("edit", Some(text)) => Ok(UserCommand::Edit { text: text.to_string() }),
+        ("delete", None) => Ok(UserCommand::Delete { id: None }),
+        ("delete", Some(id)) => match id.trim_start_matches('#').parse() {
+            Ok(id) => Ok(UserCommand::Delete { id: Some(id) }),
+            Err(_) => Err(format!("{} is not a message id", id)),
+        },
+        ("reply", Some(arg)) => {
+            let mut parts = arg.splitn(2, ' ');
+            let id = parts.next().unwrap_or_default().trim_start_matches('#');
+            let text = parts.next().map(str::trim).filter(|text| !text.is_empty());
+            match (id.parse(), text) {
+                (Ok(id), Some(text)) => Ok(UserCommand::Reply { id, text: text.to_string() }),
+                (Ok(_), None) => Err(String::from("Say something in your reply")),
+                (Err(_), _) => Err(format!("{} is not a message id", id)),
+            }
+        }
+        ("react", Some(arg)) => {
+            let mut parts = arg.splitn(2, ' ');
+            let id = parts.next().unwrap_or_default().trim_start_matches('#');
+            let emoji = parts.next().map(str::trim).filter(|emoji| !emoji.is_empty());
+            match (id.parse(), emoji) {
+                (Ok(id), Some(emoji)) => Ok(UserCommand::React { id, emoji: emoji.to_string() }),
+                (Ok(_), None) => Err(String::from("Pick a reaction, like /react 12 👍")),
+                (Err(_), _) => Err(format!("{} is not a message id", id)),
+            }
+        }
+        ("thread", Some(id)) => match id.trim_start_matches('#').parse() {
+            Ok(id) => Ok(UserCommand::Channel(ChannelCommand::Thread { id })),
+            Err(_) => Err(format!("{} is not a message id", id)),
+        },
+        ("status", Some(arg)) => {
+            let mut parts = arg.splitn(2, ' ');
+            let status = parts.next().unwrap_or_default();
+            let text = parts.next().map(str::trim).filter(|text| !text.is_empty()).map(String::from);
+            match status.to_lowercase().as_str() {
+                "online" => Ok(UserCommand::Status { status: PresenceStatus::Online, text }),
+                "away" => Ok(UserCommand::Status { status: PresenceStatus::Away, text }),
+                "busy" => Ok(UserCommand::Status { status: PresenceStatus::Busy, text }),
+                _ => Err(format!("{} is not a status, use online, away or busy", status)),
+            }
+        }
+        ("kick", Some(arg)) => {
+            let (target, reason) = target_and_reason(arg);
+            Ok(UserCommand::Moderate { target, action: ModerationAction::Kick, reason })
+        }
+        ("mute", Some(arg)) => {
+            let (target, rest) = target_and_reason(arg);
+            let rest = rest.unwrap_or_default();
+            let mut parts = rest.splitn(2, ' ');
+            let minutes = parts.next().unwrap_or_default();
+            let reason = parts.next().map(str::trim).filter(|reason| !reason.is_empty()).map(String::from);
+            match minutes.parse::<u64>() {
+                Ok(minutes) if minutes > 0 => Ok(UserCommand::Moderate {
+                    target,
+                    action: ModerationAction::Mute { secs: minutes * 60 },
+                    reason,
+                }),
+                _ => Err(String::from("Say how many minutes to mute them for, like /mute someone 10 spamming")),
+            }
+        }
+        ("unmute", Some(arg)) => Ok(UserCommand::Moderate {
+            target: target_and_reason(arg).0,
+            action: ModerationAction::Unmute,
+            reason: None,
+        }),
+        ("ban", Some(arg)) => {
+            let (target, reason) = target_and_reason(arg);
+            Ok(UserCommand::Moderate { target, action: ModerationAction::Ban { address: false }, reason })
+        }
+        ("banip", Some(arg)) => {
+            let (target, reason) = target_and_reason(arg);
+            Ok(UserCommand::Moderate { target, action: ModerationAction::Ban { address: true }, reason })
+        }
+        ("unban", Some(arg)) => Ok(UserCommand::Moderate {
+            target: target_and_reason(arg).0,
+            action: ModerationAction::Unban,
+            reason: None,
+        }),
+        ("msg", Some(arg)) => {
+            let mut parts = arg.splitn(2, ' ');
+            let name = parts.next().unwrap_or_default().trim_start_matches('@').to_string();
+            let text = parts.next().map(str::trim).filter(|text| !text.is_empty()).map(String::from);
+            Ok(UserCommand::Direct { name, text })
+        }
+        _ => Err(String::from(
+            "Commands: /join <channel>, /leave [channel], /create <channel> [topic], /list, /history, /msg <user> [message], /edit <message>, /delete [id], /reply <id> <message>, /thread <id>, /react <id> <emoji>, /status <online|away|busy> [text]. \
+            Admins: /kick <user> [reason], /mute <user> <minutes> [reason], /unmute <user>, /ban <user> [reason], /banip <user|address> [reason], /unban <user|address>",
+        )),
+    })
+}
+
+fn chat_scroll(state: &mut scrollable::State) -> Scrollable<'_, GUIMessage> {
+    Scrollable::new(state)
+        .padding(5)
+        .spacing(3)
+        .align_items(Align::Start)
+        .width(Length::Fill)
+        .height(Length::Fill)
+}
+
+/// Tabs for every channel, and the scrollback of the open one (or `lobby` if no channel is open)
+pub fn get_channel_view<'a>(
+    channels: &'a mut [ChannelView],
+    current: Option<usize>,
+    lobby: &'a mut [Box<dyn ChatViewable<GUIMessage>>],
+    lobby_scroll: &'a mut scrollable::State,
+) -> Column<'a, GUIMessage> {
+    let mut tabs = Row::new().spacing(2).padding(2);
+    let mut topic = None;
+    let mut chat = None;
+    for (index, channel) in channels.iter_mut().enumerate() {
+        let selected = Some(index) == current;
+        let label = channel.label();
+        tabs = tabs.push(
+            Button::new(&mut channel.tab_button, Text::new(label).size(16))
+                .on_press(GUIMessage::SelectChannel(index))
+                .style(style::channels::Tab { selected }),
+        );
+        if selected {
+            topic = channel.topic.clone();
+            chat = Some(
+                channel
+                    .chat_elems
+                    .iter_mut()
+                    .fold(chat_scroll(&mut channel.scroll_state), |scroll, msg| {
+                        scroll.push(msg.view())
+                    }),
+            );
+        }
+    }
+    let chat = chat.unwrap_or_else(|| {
+        lobby
+            .iter_mut()
+            .fold(chat_scroll(lobby_scroll), |scroll, msg| scroll.push(msg.view()))
+    });
+    let view = Column::new().width(Length::Fill).height(Length::Fill).push(tabs);
+    match topic {
+        Some(topic) => view.push(Text::new(topic).size(14)).push(chat),
+        None => view.push(chat),
+    }
+}
@@ -1,5 +1,8 @@
+pub mod channels;
 pub mod style;
+pub mod thread;
 pub mod types;
+pub mod typing;
 use types::*;
 
 use std::sync::mpsc::TryRecvError;
@@ -9,21 +12,69 @@ use iced::{
     Command, Container, Element, Length, Row, Scrollable, Space, Subscription, Text, TextInput,
 };
 
-use crate::types::{ChatMessage, ChatViewable, CommChannels, CommMessage};
+use crate::types::{
+    ChannelCommand, ChannelEvent, ChatMessage, ChatTarget, DeliveryStatus, ChatViewable, CommChannels, CommMessage, RawMessage,
+    Roster,
+};
+use fracture_core::msg::types::{PresenceStatus, ReplyParent};
+use channels::{get_channel_view, parse_command, ChannelView, UserCommand};
+use thread::{get_thread_sidebar, ThreadView};
+use typing::TypingUsers;
 
-use crate::conf::GUI_BUSYLOOP_SLEEP_TIME_MS;
+use crate::conf::{GUI_BUSYLOOP_SLEEP_TIME_MS, TYPING_RESEND_SECS};
 
 pub struct FractureClientGUI {
     send_button: button::State,
     close_button: button::State,
     msg_input: text_input::State,
+    password_input: text_input::State,
+    password_button: button::State,
+    register_button: button::State,
+    roster_button: button::State,
+    /// scrollback for when no channel is open
     scroll_state: scrollable::State,
+    roster_scroll_state: scrollable::State,
     comm: CommChannels,
     username: String,
+    /// messages shown when no channel is open
     chat_elems: Vec<Box<dyn ChatViewable<GUIMessage>>>,
+    channels: Vec<ChannelView>,
+    /// index in `channels` of the open channel
+    current_channel: Option<usize>,
     current_input: String,
     exit: bool,
     server_name: Option<String>,
+    /// the server asked for a password or a login, so show the password prompt instead of the message box
+    password_prompt: Option<PasswordPrompt>,
+    current_password: String,
+    /// why the connection was refused or lost. the window stays open to show this
+    disconnected: Option<String>,
+    /// round trip time to the server, measured with pings
+    rtt: Option<std::time::Duration>,
+    /// the connection was lost: which attempt at reconnecting is next, and when it will be made
+    reconnecting: Option<(u32, std::time::Instant)>,
+    /// everyone connected to the server
+    roster: Roster,
+    show_roster: bool,
+    /// nonce for the next message sent
+    next_nonce: u64,
+    /// the replies to a message, shown next to the chat
+    thread: Option<ThreadView>,
+    /// who this user is, once the server has said
+    own_uuid: Option<uuid::Uuid>,
+    /// who else is typing
+    typing: TypingUsers,
+    /// where the server was last told this user is typing, and when
+    typing_sent: Option<(ChatTarget, std::time::Instant)>,
+    /// what this user set their status to with /status
+    status: PresenceStatus,
+    status_text: Option<String>,
+    /// when the user last did something in the window
+    last_active: std::time::Instant,
+    /// how long the user can do nothing before they are shown as away, None to never
+    away_after: Option<std::time::Duration>,
+    /// the server was told the user is away because they were idle, so they come back when they do something
+    idle: bool,
 }
 
 impl Application for FractureClientGUI {
@@ -37,13 +88,37 @@ impl Application for FractureClientGUI {
                 send_button: button::State::new(),
                 close_button: button::State::new(),
                 msg_input: text_input::State::new(),
+                password_input: text_input::State::new(),
+                password_button: button::State::new(),
+                register_button: button::State::new(),
+                roster_button: button::State::new(),
                 scroll_state: scrollable::State::new(),
+                roster_scroll_state: scrollable::State::new(),
                 comm: flags.comm,
                 username: flags.name,
                 chat_elems: vec![],
+                channels: vec![],
+                current_channel: None,
                 current_input: String::new(),
                 exit: false,
                 server_name: None,
+                password_prompt: None,
+                current_password: String::new(),
+                disconnected: None,
+                rtt: None,
+                reconnecting: None,
+                roster: Roster::default(),
+                show_roster: true,
+                next_nonce: 0,
+                thread: None,
+                own_uuid: None,
+                typing: TypingUsers::default(),
+                typing_sent: None,
+                status: PresenceStatus::Online,
+                status_text: None,
+                last_active: std::time::Instant::now(),
+                away_after: flags.away_after,
+                idle: false,
             },
             Command::none(),
         )
@@ -63,47 +138,253 @@ impl Application for FractureClientGUI {
         message: Self::Message,
         _clipboard: &mut Clipboard,
     ) -> Command<Self::Message> {
+        // anything but the timer means the user did something
+        if !matches!(message, GUIMessage::Ticked) {
+            self.active();
+        }
         match message {
             GUIMessage::SubmitMessage => {
-                if !self.current_input.is_empty() {
-                    println!("Sent msg: \"{}\"", self.current_input);
-                    let chat_msg =
-                        ChatMessage::new(self.current_input.clone(), self.username.clone());
-                    self.comm
-                        .sending
-                        .send(CommMessage::SendChat(chat_msg.clone()))
-                        .expect("Sent message to comm thread");
-                    self.chat_elems.push(Box::new(chat_msg));
+                let current = self.current_channel.map(|index| self.channels[index].target.clone());
+                if let Some(command) = parse_command(&self.current_input, current.as_ref()) {
+                    match command {
+                        Ok(UserCommand::Channel(command)) => self
+                            .comm
+                            .sending
+                            .send(CommMessage::ChannelCommand(command))
+                            .expect("Sent message to comm thread"),
+                        Ok(UserCommand::Direct { name, text }) => self.message_user(&name, text),
+                        Ok(UserCommand::History) => self.request_history(),
+                        Ok(UserCommand::Edit { text }) => self.edit_last(text),
+                        Ok(UserCommand::Delete { id }) => self.delete_message(id),
+                        Ok(UserCommand::Reply { id, text }) => self.reply(id, text),
+                        Ok(UserCommand::React { id, emoji }) => self.toggle_reaction(id, emoji),
+                        Ok(UserCommand::Status { status, text }) => self.set_status(status, text),
+                        Ok(UserCommand::Moderate { target, action, reason }) => {
+                            self.comm
+                                .sending
+                                .send(CommMessage::Moderate { target, action, reason })
+                                .expect("Sent message to comm thread");
+                        }
+                        Ok(UserCommand::CloseDirect) => {
+                            if let Some(index) = self.current_channel {
+                                self.close_view(index);
+                            }
+                        }
+                        Err(usage) => self.show(RawMessage::new(usage)),
+                    }
                     self.current_input = "".to_string();
+                } else if !self.current_input.is_empty() {
+                    match current {
+                        Some(target) => {
+                            let text = std::mem::take(&mut self.current_input);
+                            self.send_chat(target, text, None);
+                        }
+                        None => self.show(RawMessage::new(String::from(
+                            "You are not in a channel, join one with /join <channel>",
+                        ))),
+                    }
                 }
+                self.update_typing();
             }
             GUIMessage::TextInputChanged(new_content) => {
                 self.current_input = new_content;
+                self.update_typing();
+            }
+            GUIMessage::PasswordInputChanged(new_content) => {
+                self.current_password = new_content;
+            }
+            GUIMessage::SubmitPassword => {
+                if let Some(PasswordPrompt::ServerPassword) = self.password_prompt {
+                    self.comm
+                        .sending
+                        .send(CommMessage::SubmitPassword(std::mem::take(&mut self.current_password)))
+                        .expect("Sent message to comm thread");
+                    self.password_prompt = None;
+                }
+            }
+            GUIMessage::SubmitLogin { register } => {
+                if let Some(PasswordPrompt::Login) = self.password_prompt {
+                    self.comm
+                        .sending
+                        .send(CommMessage::SubmitLogin {
+                            password: std::mem::take(&mut self.current_password),
+                            register,
+                        })
+                        .expect("Sent message to comm thread");
+                    self.password_prompt = None;
+                }
+            }
+            GUIMessage::Ticked => {
+                self.typing.expire();
+                self.check_idle();
+                match self.comm.receiving.try_recv() {
+                    Ok(msg) => match msg {
+                        CommMessage::HandleChat(chat_msg) => {
+                            if let Some(author) = chat_msg.author_uuid {
+                                self.typing.stop(author, &chat_msg.target);
+                            }
+                            if let Some(thread) = &mut self.thread {
+                                thread.receive_chat(&chat_msg);
+                            }
+                            let index = match &chat_msg.target {
+                                ChatTarget::Direct(uuid) => {
+                                    // this could be this clients own message, so the name is not always the authors
+                                    let name = self
+                                        .roster
+                                        .users()
+                                        .iter()
+                                        .find(|user| user.uuid == *uuid)
+                                        .and_then(|user| user.name.clone())
+                                        .unwrap_or_else(|| chat_msg.author_name().to_string());
+                                    Some(self.open_direct(*uuid, name))
+                                }
+                                target => self.channels.iter().position(|channel| &channel.target == target),
+                            };
+                            match index {
+                                Some(index) => {
+                                    let channel = &mut self.channels[index];
+                                    if channel.receive_chat(chat_msg) {
+                                        channel.unread |= Some(index) != self.current_channel;
+                                    }
+                                }
+                                None => self.show(chat_msg),
+                            }
+                        }
+                        CommMessage::ChatFailed { nonce, reason } => {
+                            let pending = self
+                                .channels
+                                .iter_mut()
+                                .find_map(|channel| channel.pending_chat(nonce));
+                            match pending {
+                                Some(chat_msg) => chat_msg.status = DeliveryStatus::Failed(reason),
+                                None => self.show(RawMessage::new(format!("Message not sent: {}", reason))),
+                            }
+                        }
+                        CommMessage::RawMessage(raw_msg) => {
+                            self.show(raw_msg);
+                        }
+                        CommMessage::ChannelEvent(event) => {
+                            self.handle_channel_event(event);
+                        }
+                        CommMessage::Typing { user, name, target, typing } => {
+                            self.typing.set(user, name, target, typing);
+                        }
+                        CommMessage::ServerInfo { server_name, uuid } => {
+                            self.server_name = Some(server_name);
+                            self.own_uuid = Some(uuid);
+                        }
+                        CommMessage::PasswordRequired => {
+                            self.password_prompt = Some(PasswordPrompt::ServerPassword);
+                        }
+                        CommMessage::LoginRequired => {
+                            self.password_prompt = Some(PasswordPrompt::Login);
+                        }
+                        CommMessage::NameAssigned { name } => {
+                            self.show(RawMessage::new(format!(
+                                "Someone else is using the name {}, so you are {}",
+                                self.username, name
+                            )));
+                            self.username = name;
+                        }
+                        CommMessage::RosterChanged(change) => {
+                            self.roster.apply(change);
+                        }
+                        CommMessage::ConnectionRefused { reason } => {
+                            self.password_prompt = None;
+                            self.show(RawMessage::new(format!(
+                                "Connection refused: {}",
+                                reason
+                            )));
+                            self.disconnected = Some(reason);
+                        }
+                        CommMessage::Disconnected { reason } => {
+                            self.password_prompt = None;
+                            self.show(RawMessage::new(reason.clone()));
+                            self.disconnected = Some(reason);
+                            self.reconnecting = None;
+                            self.rtt = None;
+                        }
+                        CommMessage::ConnectionLost { reason } => {
+                            self.show(RawMessage::new(format!("Lost connection to the server, {}", reason)));
+                            self.rtt = None;
+                            // they may or may not have got to the server, messages typed from now on are sent once it is back
+                            for channel in &mut self.channels {
+                                channel.fail_pending("the connection was lost");
+                            }
+                        }
+                        CommMessage::Reconnecting { attempt, delay } => {
+                            self.reconnecting = Some((attempt, std::time::Instant::now() + delay));
+                        }
+                        CommMessage::Reconnected { resumed } => {
+                            self.reconnecting = None;
+                            if resumed {
+                                self.show(RawMessage::new(String::from("Reconnected to the server")));
+                            } else {
+                                self.show(RawMessage::new(String::from(
+                                    "Reconnected to the server, but it had forgotten about you, so rejoining your channels",
+                                )));
+                                self.rejoin();
+                            }
+                        }
+                        CommMessage::Latency(rtt) => {
+                            self.rtt = Some(rtt);
+                        }
+                        _ => panic!("GUI side received a message that it should not have!"),
+                    },
+                    Err(err) => match err {
+                        TryRecvError::Disconnected => {
+                            // stay open so the user can see why they were refused or disconnected
+                            if self.disconnected.is_none() {
+                                println!("Connection to server closed, closing GUI");
+                                self.exit = true;
+                            }
+                        }
+                        TryRecvError::Empty => {}
+                    },
+                }
             }
-            GUIMessage::Ticked => match self.comm.receiving.try_recv() {
-                Ok(msg) => match msg {
-                    CommMessage::HandleChat(chat_msg) => {
-                        self.chat_elems.push(Box::new(chat_msg));
-                    }
-                    CommMessage::RawMessage(raw_msg) => {
-                        self.chat_elems.push(Box::new(raw_msg));
-                    }
-                    CommMessage::ServerInfo { server_name } => {
-                        self.server_name = Some(server_name);
-                    }
-                    _ => panic!("GUI side received a message that it should not have!"),
-                },
-                Err(err) => match err {
-                    TryRecvError::Disconnected => {
-                        println!("Connection to server closed, closing GUI");
-                        self.exit = true;
-                    }
-                    TryRecvError::Empty => {}
-                },
-            },
             GUIMessage::Close => {
                 self.exit = true;
             }
+            GUIMessage::ToggleRoster => {
+                self.show_roster = !self.show_roster;
+            }
+            GUIMessage::SelectChannel(index) => {
+                if let Some(channel) = self.channels.get_mut(index) {
+                    channel.unread = false;
+                    self.current_channel = Some(index);
+                }
+                self.update_typing();
+            }
+            GUIMessage::Reply(id) => {
+                self.current_input = format!("/reply {} ", id);
+                self.msg_input.focus();
+                self.msg_input.move_cursor_to_end();
+            }
+            GUIMessage::JumpTo(id) => {
+                let shown = self
+                    .current_channel
+                    .is_some_and(|index| self.channels[index].jump_to(id));
+                if !shown {
+                    self.show(RawMessage::new(String::from(
+                        "That message is not shown, use /history to see older messages",
+                    )));
+                }
+            }
+            GUIMessage::ShowThread(id) => self
+                .comm
+                .sending
+                .send(CommMessage::ChannelCommand(ChannelCommand::Thread { id }))
+                .expect("Sent message to comm thread"),
+            GUIMessage::CloseThread => {
+                self.thread = None;
+            }
+            GUIMessage::React(id) => {
+                self.current_input = format!("/react {} ", id);
+                self.msg_input.focus();
+                self.msg_input.move_cursor_to_end();
+            }
+            GUIMessage::ToggleReaction { message_id, emoji } => self.toggle_reaction(message_id, emoji),
         }
         Command::none()
     }
@@ -117,9 +398,395 @@ impl Application for FractureClientGUI {
     }
 }
 
+impl FractureClientGUI {
+    /// Show something in the open channel (or with no channel open)
+    fn show(&mut self, elem: impl ChatViewable<GUIMessage> + 'static) {
+        match self.current_channel {
+            Some(index) => self.channels[index].chat_elems.push(Box::new(elem)),
+            None => self.chat_elems.push(Box::new(elem)),
+        }
+    }
+
+    /// Tell the server if the user is typing a message in the open channel, at most every `TYPING_RESEND_SECS`
+    fn update_typing(&mut self) {
+        let target = self.current_channel.map(|index| self.channels[index].target.clone());
+        let typing = !self.current_input.trim().is_empty() && !self.current_input.starts_with('/');
+        let target = target.filter(|_| typing);
+        let resend = match (&self.typing_sent, &target) {
+            (Some((sent, at)), Some(target)) => {
+                sent != target || at.elapsed() >= std::time::Duration::from_secs(TYPING_RESEND_SECS)
+            }
+            (None, None) => false,
+            _ => true,
+        };
+        if !resend {
+            return;
+        }
+        // they stopped typing where they were before
+        if let Some((sent, _)) = self.typing_sent.take() {
+            if target.as_ref() != Some(&sent) {
+                self.comm
+                    .sending
+                    .send(CommMessage::SetTyping { target: sent, typing: false })
+                    .expect("Sent message to comm thread");
+            }
+        }
+        if let Some(target) = target {
+            self.comm
+                .sending
+                .send(CommMessage::SetTyping { target: target.clone(), typing: true })
+                .expect("Sent message to comm thread");
+            self.typing_sent = Some((target, std::time::Instant::now()));
+        }
+    }
+
+    /// The user did something, so they are not idle, and come back if they were
+    fn active(&mut self) {
+        self.last_active = std::time::Instant::now();
+        if self.idle {
+            self.idle = false;
+            self.send_presence(self.status);
+        }
+    }
+
+    /// Tell the server the user is away if they have not done anything in `away_after`.
+    /// users who said they are busy (or away) stay that way
+    fn check_idle(&mut self) {
+        let idle = self
+            .away_after
+            .is_some_and(|away_after| self.last_active.elapsed() >= away_after);
+        if idle && !self.idle && self.status == PresenceStatus::Online && self.own_uuid.is_some() {
+            self.idle = true;
+            self.send_presence(PresenceStatus::Away);
+        }
+    }
+
+    /// Change the status this user set, with /status
+    fn set_status(&mut self, status: PresenceStatus, text: Option<String>) {
+        if self.own_uuid.is_none() {
+            self.show(RawMessage::new(String::from("You are not connected yet")));
+            return;
+        }
+        self.status = status;
+        self.status_text = text;
+        self.idle = false;
+        self.send_presence(status);
+    }
+
+    /// Join the open channels again, and tell the server this users status again, after it forgot about them
+    fn rejoin(&mut self) {
+        for open in &self.channels {
+            if let ChatTarget::Channel(channel) = &open.target {
+                self.comm
+                    .sending
+                    .send(CommMessage::ChannelCommand(ChannelCommand::Join(channel.clone())))
+                    .expect("Sent message to comm thread");
+            }
+        }
+        if self.idle {
+            self.send_presence(PresenceStatus::Away);
+        } else if self.status != PresenceStatus::Online || self.status_text.is_some() {
+            self.send_presence(self.status);
+        }
+    }
+
+    fn send_presence(&mut self, status: PresenceStatus) {
+        self.comm
+            .sending
+            .send(CommMessage::SetPresence {
+                status,
+                text: self.status_text.clone(),
+            })
+            .expect("Sent message to comm thread");
+    }
+
+    /// Send `text` to a channel or user (in reply to `parent`), and show it
+    fn send_chat(&mut self, target: ChatTarget, text: String, parent: Option<ReplyParent>) {
+        println!("Sent msg: \"{}\" to {:?}", text, target);
+        let chat_msg = ChatMessage::new(text, self.username.clone(), target, self.next_nonce, parent);
+        self.next_nonce += 1;
+        self.comm
+            .sending
+            .send(CommMessage::SendChat(chat_msg.clone()))
+            .expect("Sent message to comm thread");
+        self.show(chat_msg);
+    }
+
+    /// Find the conversation with `uuid`, opening a new one (in the background) if there is none
+    fn open_direct(&mut self, uuid: uuid::Uuid, name: String) -> usize {
+        let target = ChatTarget::Direct(uuid);
+        match self.channels.iter().position(|open| open.target == target) {
+            Some(index) => index,
+            None => {
+                self.channels.push(ChannelView::new(target, name, None));
+                self.channels.len() - 1
+            }
+        }
+    }
+
+    /// Open (and switch to) a conversation with whoever is called `name`, saying `text` if there is any
+    fn message_user(&mut self, name: &str, text: Option<String>) {
+        let user = self.roster.users().iter().find(|user| {
+            user.name
+                .as_deref()
+                .is_some_and(|user_name| user_name.to_lowercase() == name.to_lowercase())
+        });
+        let (uuid, name) = match user {
+            Some(user) => (user.uuid, user.name.clone().unwrap_or_default()),
+            None => {
+                self.show(RawMessage::new(format!("No one called {} is online", name)));
+                return;
+            }
+        };
+        let index = self.open_direct(uuid, name);
+        self.channels[index].unread = false;
+        self.current_channel = Some(index);
+        if let Some(text) = text {
+            self.send_chat(ChatTarget::Direct(uuid), text, None);
+        }
+    }
+
+    /// Ask for the messages said in the open channel before the oldest one that is shown
+    fn request_history(&mut self) {
+        let open = self.current_channel.map(|index| &self.channels[index]);
+        match open.map(|open| (&open.target, open.oldest)) {
+            Some((ChatTarget::Channel(channel), before)) => {
+                let command = ChannelCommand::History {
+                    channel: channel.clone(),
+                    before,
+                };
+                self.comm
+                    .sending
+                    .send(CommMessage::ChannelCommand(command))
+                    .expect("Sent message to comm thread");
+            }
+            Some((ChatTarget::Direct(_), _)) => self.show(RawMessage::new(String::from(
+                "Direct messages are not kept in the history",
+            ))),
+            None => self.show(RawMessage::new(String::from("You are not in a channel"))),
+        }
+    }
+
+    /// The id of the last message this client said in the open channel, telling the user if there is none
+    fn last_own_id(&mut self) -> Option<u64> {
+        let open = self.current_channel.map(|index| &mut self.channels[index]);
+        let id = match open {
+            Some(open) if matches!(open.target, ChatTarget::Channel(_)) => open.last_own_id(),
+            Some(_) => {
+                self.show(RawMessage::new(String::from(
+                    "Direct messages can not be changed",
+                )));
+                return None;
+            }
+            None => None,
+        };
+        if id.is_none() {
+            self.show(RawMessage::new(String::from(
+                "You have not said anything here that can be changed",
+            )));
+        }
+        id
+    }
+
+    /// Change what the last message this client said in the open channel says
+    fn edit_last(&mut self, content: String) {
+        if let Some(id) = self.last_own_id() {
+            self.comm
+                .sending
+                .send(CommMessage::ChannelCommand(ChannelCommand::Edit { id, content }))
+                .expect("Sent message to comm thread");
+        }
+    }
+
+    /// Delete the message `id`, or the last message this client said in the open channel
+    fn delete_message(&mut self, id: Option<u64>) {
+        if let Some(id) = id.or_else(|| self.last_own_id()) {
+            self.comm
+                .sending
+                .send(CommMessage::ChannelCommand(ChannelCommand::Delete { id }))
+                .expect("Sent message to comm thread");
+        }
+    }
+
+    /// Reply to the message `id` in the open channel
+    fn reply(&mut self, id: u64, text: String) {
+        let open = self.current_channel.map(|index| &mut self.channels[index]);
+        let target = match open {
+            Some(open) if matches!(open.target, ChatTarget::Channel(_)) => open.target.clone(),
+            Some(_) => {
+                self.show(RawMessage::new(String::from("Direct messages can not be replied to")));
+                return;
+            }
+            None => {
+                self.show(RawMessage::new(String::from("You are not in a channel")));
+                return;
+            }
+        };
+        let parent = match &target {
+            ChatTarget::Channel(channel) => self.chats_in(channel, id).next(),
+            ChatTarget::Direct(_) => None,
+        }
+        .and_then(|chat| chat.as_parent());
+        match parent {
+            Some(parent) => self.send_chat(target, text, Some(parent)),
+            None => self.show(RawMessage::new(format!("Message #{} is not shown here", id))),
+        }
+    }
+
+    /// React to the message `id` with `emoji`, or take the reaction back if this user already reacted with it
+    fn toggle_reaction(&mut self, message_id: u64, emoji: String) {
+        // ids are unique on the server, so it does not matter where it is shown
+        let reacted = self
+            .channels
+            .iter_mut()
+            .find_map(|open| open.chat_by_id(message_id))
+            .or_else(|| self.thread.as_mut().and_then(|thread| thread.chat_by_id(message_id)))
+            .is_some_and(|chat| chat.reacted(&emoji));
+        let command = ChannelCommand::React {
+            message_id,
+            emoji,
+            add: !reacted,
+        };
+        self.comm
+            .sending
+            .send(CommMessage::ChannelCommand(command))
+            .expect("Sent message to comm thread");
+    }
+
+    /// Every copy of the message `id` in `channel` that is shown, in the channel and the open thread
+    fn chats_in<'a>(&'a mut self, channel: &str, id: u64) -> impl Iterator<Item = &'a mut ChatMessage> {
+        let target = ChatTarget::Channel(channel.to_string());
+        let in_channel = self
+            .channels
+            .iter_mut()
+            .find(|open| open.target == target)
+            .and_then(|open| open.chat_by_id(id));
+        let in_thread = self
+            .thread
+            .as_mut()
+            .filter(|thread| thread.channel == channel)
+            .and_then(|thread| thread.chat_by_id(id));
+        in_channel.into_iter().chain(in_thread)
+    }
+
+    /// Stop showing the channel or conversation at `index`
+    fn close_view(&mut self, index: usize) {
+        self.channels.remove(index);
+        self.current_channel = match self.current_channel {
+            _ if self.channels.is_empty() => None,
+            Some(current) if current > index => Some(current - 1),
+            Some(current) if current == index => Some(index.saturating_sub(1)),
+            other => other,
+        };
+    }
+
+    fn handle_channel_event(&mut self, event: ChannelEvent) {
+        match event {
+            ChannelEvent::Joined { channel, topic } => {
+                let target = ChatTarget::Channel(channel.clone());
+                let index = match self.channels.iter().position(|open| open.target == target) {
+                    Some(index) => index,
+                    None => {
+                        self.channels
+                            .push(ChannelView::new(target, channel.clone(), topic.clone()));
+                        self.channels.len() - 1
+                    }
+                };
+                self.current_channel = Some(index);
+                self.show(RawMessage::new(match topic {
+                    Some(topic) => format!("Joined #{}: {}", channel, topic),
+                    None => format!("Joined #{}", channel),
+                }));
+            }
+            ChannelEvent::Left { channel } => {
+                let target = ChatTarget::Channel(channel.clone());
+                if let Some(index) = self.channels.iter().position(|open| open.target == target) {
+                    self.close_view(index);
+                }
+                self.show(RawMessage::new(format!("Left #{}", channel)));
+            }
+            ChannelEvent::List(channels) => {
+                self.show(RawMessage::new(String::from("Channels:")));
+                for channel in channels {
+                    self.show(RawMessage::new(match channel.topic {
+                        Some(topic) => format!("#{} ({} here): {}", channel.name, channel.members, topic),
+                        None => format!("#{} ({} here)", channel.name, channel.members),
+                    }));
+                }
+            }
+            ChannelEvent::Error { channel, reason } => {
+                self.show(RawMessage::new(format!("#{}: {}", channel, reason)));
+            }
+            ChannelEvent::History { channel, messages } => {
+                let target = ChatTarget::Channel(channel.clone());
+                let open = match self.channels.iter_mut().find(|open| open.target == target) {
+                    Some(open) => open,
+                    None => return,
+                };
+                if messages.is_empty() {
+                    open.chat_elems.insert(
+                        0,
+                        Box::new(RawMessage::new(format!("No older messages in #{}", channel))),
+                    );
+                } else {
+                    open.prepend_history(
+                        messages
+                            .into_iter()
+                            .map(|message| ChatMessage::from_history(message, channel.clone(), self.own_uuid))
+                            .collect(),
+                    );
+                }
+            }
+            ChannelEvent::Edited { channel, id, content } => {
+                for chat_msg in self.chats_in(&channel, id) {
+                    chat_msg.edit(content.clone());
+                }
+            }
+            ChannelEvent::Deleted { channel, id } => {
+                for chat_msg in self.chats_in(&channel, id) {
+                    chat_msg.delete();
+                }
+            }
+            ChannelEvent::ChangeFailed { id, reason } => {
+                self.show(RawMessage::new(format!("Could not change message #{}: {}", id, reason)));
+            }
+            ChannelEvent::Thread { channel, parent, replies } => {
+                self.thread = Some(ThreadView::new(channel, parent, replies, self.own_uuid));
+            }
+            ChannelEvent::Reactions { channel, id, reactions } => {
+                let own_uuid = self.own_uuid;
+                for chat_msg in self.chats_in(&channel, id) {
+                    chat_msg.set_reactions(reactions.clone(), own_uuid);
+                }
+            }
+            ChannelEvent::ThreadFailed { id, reason } => {
+                self.show(RawMessage::new(format!("Could not show the replies to #{}: {}", id, reason)));
+            }
+        }
+    }
+}
+
+/// What the banner says while reconnecting, counting down to the next attempt
+fn reconnect_banner(attempt: u32, at: std::time::Instant) -> String {
+    let wait = at.saturating_duration_since(std::time::Instant::now());
+    if wait.is_zero() {
+        format!("Connection lost, reconnecting (attempt {})...", attempt)
+    } else {
+        format!(
+            "Connection lost, reconnecting in {}s (attempt {}). Messages you send will be sent once you are back",
+            wait.as_secs() + 1,
+            attempt
+        )
+    }
+}
+
 fn get_main_ui(
     this: &mut FractureClientGUI,
 ) -> Element<<FractureClientGUI as Application>::Message> {
+    let typing = this
+        .current_channel
+        .and_then(|index| this.typing.label(&this.channels[index].target))
+        .unwrap_or_default();
     Column::new()
         .padding(0)
         .align_items(Align::Center)
@@ -141,7 +808,28 @@ fn get_main_ui(
                         Text::new(format!("{}", this.server_name.as_ref().unwrap_or(&"Unidentified Server".to_string())))
                         .vertical_alignment(iced::VerticalAlignment::Center)
                     )
+                    .push(Space::with_width(Length::Units(5)))
+                    .push(
+                        Text::new(match (this.rtt, &this.disconnected, this.reconnecting) {
+                            (_, Some(_), _) => String::from("disconnected"),
+                            (_, None, Some(_)) => String::from("reconnecting"),
+                            (Some(rtt), None, None) => format!("{} ms", rtt.as_millis()),
+                            (None, None, None) => String::new(),
+                        })
+                        .size(14)
+                        .vertical_alignment(iced::VerticalAlignment::Center)
+                    )
                     .push(Space::with_width(Length::Fill))
+                    .push(
+                        Button::new(
+                            &mut this.roster_button,
+                            Text::new(format!("users ({})", this.roster.users().len())),
+                        )
+                        .on_press(GUIMessage::ToggleRoster)
+                        .height(Length::Shrink)
+                        .style(style::menubar::MenuButton),
+                    )
+                    .push(Space::with_width(Length::Units(5)))
                     .push(
                         Text::new(format!("{}", this.username))
                         .vertical_alignment(iced::VerticalAlignment::Center)
@@ -156,19 +844,49 @@ fn get_main_ui(
                 .width(Length::Fill)
                 .style(style::menubar::Spacer),
         )
+        .push(match this.reconnecting {
+            Some((attempt, at)) => Container::new(Text::new(reconnect_banner(attempt, at)).size(14))
+                .width(Length::Fill)
+                .padding(4)
+                .style(style::menubar::Banner),
+            None => Container::new(Space::with_height(Length::Units(0))),
+        })
+        .push({
+            let chat = get_channel_view(
+                &mut this.channels,
+                this.current_channel,
+                &mut this.chat_elems,
+                &mut this.scroll_state,
+            );
+            let mut body = Row::new().height(Length::Fill).push(chat);
+            if let Some(thread) = &mut this.thread {
+                body = body.push(get_thread_sidebar(thread));
+            }
+            if this.show_roster {
+                body.push(get_roster_sidebar(
+                    &this.roster,
+                    &this.username,
+                    &mut this.roster_scroll_state,
+                ))
+            } else {
+                body
+            }
+        })
         .push(
-            this.chat_elems.iter_mut().fold(
-                Scrollable::new(&mut this.scroll_state)
-                    .padding(5)
-                    .spacing(3)
-                    .align_items(Align::Start)
-                    .width(Length::Fill)
-                    .height(Length::Fill),
-                |scroll, msg| scroll.push(msg.view()),
-            ),
+            Row::new()
+                .height(Length::Units(18))
+                .push(Space::with_width(Length::Units(10)))
+                .push(Text::new(typing).size(14)),
         )
-        .push(Space::with_height(Length::from(5)))
-        .push(
+        .push(if let Some(prompt) = this.password_prompt {
+            get_password_prompt(
+                prompt,
+                &mut this.password_input,
+                &mut this.password_button,
+                &mut this.register_button,
+                &this.current_password,
+            )
+        } else {
             Row::new()
                 .padding(10)
                 .align_items(Align::Center)
@@ -184,7 +902,87 @@ fn get_main_ui(
                 .push(
                     Button::new(&mut this.send_button, Text::new("Send"))
                         .on_press(GUIMessage::SubmitMessage),
-                ),
-        )
+                )
+        })
         .into()
 }
+
+/// List of everyone connected, shown next to the chat
+fn get_roster_sidebar<'a>(
+    roster: &Roster,
+    username: &str,
+    scroll: &'a mut scrollable::State,
+) -> Container<'a, GUIMessage> {
+    Container::new(roster.users().iter().fold(
+        Scrollable::new(scroll)
+            .padding(5)
+            .spacing(3)
+            .align_items(Align::Start)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .push(Text::new("Online")),
+        |scroll, user| {
+            let name = user.name.as_deref().unwrap_or("(joining)");
+            // names are unique, so this is us
+            let label = if name == username {
+                format!("{} (you)", name)
+            } else {
+                name.to_string()
+            };
+            let mut entry = Column::new().push(
+                Row::new()
+                    .spacing(4)
+                    .push(Text::new("●").size(16).color(style::roster::status_color(user.status())))
+                    .push(Text::new(label).size(16)),
+            );
+            if let Some(status) = user.status_line() {
+                entry = entry.push(Text::new(status).size(12).color(iced::Color::from_rgb8(110, 110, 110)));
+            }
+            scroll.push(entry)
+        },
+    ))
+    .width(Length::Units(160))
+    .height(Length::Fill)
+    .style(style::roster::Sidebar)
+}
+
+/// Shown in place of the message box when the server asks for a password or a login
+fn get_password_prompt<'a>(
+    prompt: PasswordPrompt,
+    input: &'a mut text_input::State,
+    button: &'a mut button::State,
+    register_button: &'a mut button::State,
+    current_password: &str,
+) -> Row<'a, GUIMessage> {
+    let (label, submit) = match prompt {
+        PasswordPrompt::ServerPassword => ("This server needs a password:", GUIMessage::SubmitPassword),
+        PasswordPrompt::Login => ("Account password:", GUIMessage::SubmitLogin { register: false }),
+    };
+    let row = Row::new()
+        .padding(10)
+        .align_items(Align::Center)
+        .push(Text::new(label))
+        .push(Space::with_width(Length::from(5)))
+        .push(
+            TextInput::new(input, "password", current_password, |content| {
+                GUIMessage::PasswordInputChanged(content)
+            })
+            .password()
+            .width(Length::Fill)
+            .on_submit(submit.clone())
+            .padding(6),
+        )
+        .push(Space::with_width(Length::from(5)));
+    match prompt {
+        PasswordPrompt::ServerPassword => {
+            row.push(Button::new(button, Text::new("Join")).on_press(submit))
+        }
+        PasswordPrompt::Login => row
+            .push(Button::new(button, Text::new("Log in")).on_press(submit))
+            .push(Space::with_width(Length::from(5)))
+            .push(
+                Button::new(register_button, Text::new("Register"))
+                    .on_press(GUIMessage::SubmitLogin { register: true }),
+            ),
+    }
+}
@@ -27,6 +27,35 @@ pub mod menubar {
         }
     }
 
+    /// Shown under the menu bar while reconnecting
+    pub struct Banner;
+
+    impl container::StyleSheet for Banner {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(Background::Color(Color::from_rgb8(255, 230, 180))),
+                text_color: Some(Color::BLACK),
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..container::Style::default()
+            }
+        }
+    }
+
+    pub struct MenuButton;
+
+    impl button::StyleSheet for MenuButton {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: Some(Background::Color(Color::from_rgb8(220, 220, 220))),
+                text_color: Color::BLACK,
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..button::Style::default()
+            }
+        }
+    }
+
     pub struct CloseButton;
 
     impl button::StyleSheet for CloseButton {
@@ -42,3 +71,123 @@ pub mod menubar {
         }
     }
 }
+
+pub mod roster {
+    use fracture_core::msg::types::PresenceStatus;
+    use iced::{container, Background, Color};
+    pub struct Sidebar;
+
+    /// Color of the dot next to someones name
+    pub fn status_color(status: PresenceStatus) -> Color {
+        match status {
+            PresenceStatus::Online => Color::from_rgb8(60, 170, 80),
+            PresenceStatus::Away => Color::from_rgb8(220, 170, 40),
+            PresenceStatus::Busy => Color::from_rgb8(200, 60, 60),
+        }
+    }
+
+    impl container::StyleSheet for Sidebar {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(Background::Color(Color::from_rgb8(240, 240, 240))),
+                text_color: Some(Color::BLACK),
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..container::Style::default()
+            }
+        }
+    }
+}
+
+pub mod channels {
+    use iced::{button, Background, Color};
+    pub struct Tab {
+        pub selected: bool,
+    }
+
+    impl button::StyleSheet for Tab {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: Some(Background::Color(if self.selected {
+                    Color::from_rgb8(200, 200, 200)
+                } else {
+                    Color::from_rgb8(240, 240, 240)
+                })),
+                text_color: Color::BLACK,
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..button::Style::default()
+            }
+        }
+    }
+}
+
+pub mod chat {
+    use iced::{button, container, Background, Color};
+
+    /// Small buttons on messages, that look like links
+    pub struct Link;
+
+    impl button::StyleSheet for Link {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: None,
+                text_color: Color::from_rgb8(40, 90, 170),
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..button::Style::default()
+            }
+        }
+    }
+
+    /// The message a reply is to, quoted above it
+    pub struct Quote;
+
+    impl button::StyleSheet for Quote {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: Some(Background::Color(Color::from_rgb8(235, 235, 235))),
+                text_color: Color::from_rgb8(90, 90, 90),
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..button::Style::default()
+            }
+        }
+    }
+
+    /// A reaction under a message, darker if it is the users own
+    pub struct Chip {
+        pub mine: bool,
+    }
+
+    impl button::StyleSheet for Chip {
+        fn active(&self) -> button::Style {
+            button::Style {
+                background: Some(Background::Color(if self.mine {
+                    Color::from_rgb8(200, 215, 240)
+                } else {
+                    Color::from_rgb8(235, 235, 235)
+                })),
+                text_color: Color::BLACK,
+                border_radius: 8f32,
+                border_width: 1f32,
+                border_color: Color::from_rgb8(200, 200, 200),
+                ..button::Style::default()
+            }
+        }
+    }
+
+    /// A message the user jumped to
+    pub struct Highlight;
+
+    impl container::StyleSheet for Highlight {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(Background::Color(Color::from_rgb8(255, 245, 200))),
+                border_radius: 0f32,
+                border_width: 0f32,
+                ..container::Style::default()
+            }
+        }
+    }
+}
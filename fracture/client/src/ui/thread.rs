@@ -0,0 +1,98 @@
+use iced::{button, scrollable, Align, Button, Column, Container, Length, Row, Scrollable, Space, Text};
+
+use fracture_core::msg::types::HistoryMessage;
+
+use super::style;
+use super::types::GUIMessage;
+use crate::types::{ChatMessage, ChatTarget, ChatViewable};
+
+/// A message and every reply to it, shown next to the chat
+pub struct ThreadView {
+    pub channel: String,
+    parent: ChatMessage,
+    replies: Vec<ChatMessage>,
+    scroll_state: scrollable::State,
+    close_button: button::State,
+}
+
+impl ThreadView {
+    pub fn new(
+        channel: String,
+        parent: HistoryMessage,
+        replies: Vec<HistoryMessage>,
+        own_uuid: Option<uuid::Uuid>,
+    ) -> Self {
+        Self {
+            parent: ChatMessage::from_history(parent, channel.clone(), own_uuid),
+            replies: replies
+                .into_iter()
+                .map(|reply| Self::as_reply(ChatMessage::from_history(reply, channel.clone(), own_uuid)))
+                .collect(),
+            channel,
+            scroll_state: scrollable::State::new(),
+            close_button: button::State::new(),
+        }
+    }
+
+    /// the parent is shown at the top, so there is no need to quote it above every reply
+    fn as_reply(mut msg: ChatMessage) -> ChatMessage {
+        msg.parent = None;
+        msg.highlighted = false;
+        msg
+    }
+
+    /// Show `msg` if it was just said in reply to the parent
+    pub fn receive_chat(&mut self, msg: &ChatMessage) {
+        let is_reply = msg.target == ChatTarget::Channel(self.channel.clone())
+            && msg.parent.as_ref().map(|parent| parent.id) == self.parent.id
+            && msg.id.is_some();
+        if is_reply && !self.replies.iter().any(|reply| reply.id == msg.id) {
+            self.replies.push(Self::as_reply(msg.clone()));
+        }
+    }
+
+    /// The message with the id `id`, if it is shown
+    pub fn chat_by_id(&mut self, id: u64) -> Option<&mut ChatMessage> {
+        std::iter::once(&mut self.parent)
+            .chain(self.replies.iter_mut())
+            .find(|chat| chat.id == Some(id))
+    }
+}
+
+/// The open thread, shown next to the chat
+pub fn get_thread_sidebar(thread: &mut ThreadView) -> Container<'_, GUIMessage> {
+    let count = match thread.replies.len() {
+        1 => String::from("1 reply"),
+        count => format!("{} replies", count),
+    };
+    let replies = thread.replies.iter_mut().fold(
+        Scrollable::new(&mut thread.scroll_state)
+            .padding(5)
+            .spacing(3)
+            .align_items(Align::Start)
+            .width(Length::Fill)
+            .height(Length::Fill),
+        |scroll, reply| scroll.push(reply.view()),
+    );
+    Container::new(
+        Column::new()
+            .push(
+                Row::new()
+                    .padding(5)
+                    .align_items(Align::Center)
+                    .push(Text::new(format!("Thread in #{}", thread.channel)))
+                    .push(Space::with_width(Length::Fill))
+                    .push(
+                        Button::new(&mut thread.close_button, Text::new("close").size(14))
+                            .on_press(GUIMessage::CloseThread)
+                            .style(style::menubar::MenuButton),
+                    ),
+            )
+            .push(thread.parent.view())
+            .push(Container::new(Text::new(count).size(14)).padding(5))
+            .push(replies),
+    )
+    .width(Length::Units(320))
+    .height(Length::Fill)
+    .style(style::roster::Sidebar)
+}
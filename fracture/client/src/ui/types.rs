@@ -4,20 +4,48 @@ use crate::types::CommChannels;
 pub enum GUIMessage {
     SubmitMessage,
     TextInputChanged(String),
+    PasswordInputChanged(String),
+    SubmitPassword,
+    SubmitLogin { register: bool },
     Ticked,
     Close,
+    ToggleRoster,
+    SelectChannel(usize),
+    /// start replying to the message with this id
+    Reply(u64),
+    /// scroll to the message with this id in the open channel
+    JumpTo(u64),
+    /// show the replies to the message with this id
+    ShowThread(u64),
+    CloseThread,
+    /// start reacting to the message with this id
+    React(u64),
+    /// add this users reaction to a message, or take it back if they already reacted
+    ToggleReaction { message_id: u64, emoji: String },
+}
+
+/// What the password prompt is asking for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordPrompt {
+    /// the servers password
+    ServerPassword,
+    /// the password for the users account
+    Login,
 }
 
 pub struct FractureGUIFlags {
     pub comm: CommChannels,
     pub name: String,
+    /// how long the user can be idle before they are shown as away, None to never
+    pub away_after: Option<std::time::Duration>,
 }
 
 impl FractureGUIFlags {
-    pub fn new(comm: CommChannels, name: impl Into<String>) -> Self {
+    pub fn new(comm: CommChannels, name: impl Into<String>, away_after: Option<std::time::Duration>) -> Self {
         FractureGUIFlags {
             comm,
             name: name.into(),
+            away_after,
         }
     }
 }
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use crate::conf::TYPING_TIMEOUT_SECS;
+use crate::types::ChatTarget;
+
+/// Someone who is typing
+struct TypingUser {
+    uuid: uuid::Uuid,
+    name: String,
+    target: ChatTarget,
+    /// forget about them after this, in case the message saying they stopped never comes
+    until: Instant,
+}
+
+/// Everyone who is typing, in every channel and conversation
+#[derive(Default)]
+pub struct TypingUsers {
+    users: Vec<TypingUser>,
+}
+
+impl TypingUsers {
+    /// `uuid` started (or is still) typing in `target`, or stopped if `typing` is false
+    pub fn set(&mut self, uuid: uuid::Uuid, name: String, target: ChatTarget, typing: bool) {
+        self.stop(uuid, &target);
+        if typing {
+            self.users.push(TypingUser {
+                uuid,
+                name,
+                target,
+                until: Instant::now() + Duration::from_secs(TYPING_TIMEOUT_SECS),
+            });
+        }
+    }
+
+    /// `uuid` is not typing in `target` anymore, for example because they sent their message
+    pub fn stop(&mut self, uuid: uuid::Uuid, target: &ChatTarget) {
+        self.users.retain(|user| user.uuid != uuid || &user.target != target);
+    }
+
+    /// Forget about anyone who has not said they are still typing in a while
+    pub fn expire(&mut self) {
+        let now = Instant::now();
+        self.users.retain(|user| user.until > now);
+    }
+
+    /// What to show under `target`, if anyone is typing there
+    pub fn label(&self, target: &ChatTarget) -> Option<String> {
+        let names: Vec<&str> = self
+            .users
+            .iter()
+            .filter(|user| &user.target == target)
+            .map(|user| user.name.as_str())
+            .collect();
+        match names.as_slice() {
+            [] => None,
+            [name] => Some(format!("{} is typing…", name)),
+            [first, second] => Some(format!("{} and {} are typing…", first, second)),
+            _ => Some(String::from("Several people are typing…")),
+        }
+    }
+}
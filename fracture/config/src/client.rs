@@ -1,2 +1,9 @@
 pub const GUI_BUSYLOOP_SLEEP_TIME_MS: u64 = 100;
 pub const SHOW_USERS_UUIDS: bool = false;
+pub const HISTORY_PAGE_SIZE: u32 = 50; // Messages asked for at a time with /history
+pub const TYPING_RESEND_SECS: u64 = 3; // How often to tell the server the user is still typing
+pub const TYPING_TIMEOUT_SECS: u64 = 6; // How long someone is shown as typing, if they do not say they still are
+pub const CHAT_ROW_HEIGHT: f32 = 30.0; // Rough height of a message, used to scroll to one (iced can not scroll to a widget)
+pub const AWAY_AFTER_MINS: u64 = 10; // Minutes without doing anything before the user is shown as away, unless --away-after is given
+pub const RECONNECT_MIN_DELAY_MS: u64 = 500; // Time waited before the first attempt to reconnect, it doubles with every failed attempt
+pub const RECONNECT_MAX_DELAY_SECS: u64 = 30; // Most time waited between attempts to reconnect
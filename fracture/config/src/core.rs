@@ -1,2 +1,5 @@
 pub const UPDATE_TIME: u64 = 50; //ms to wait between updates
 pub const SOCK_DBG: bool = false; //debug printlns in sock_readable.rs
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024; // largest message (in bytes, not including the header) that will be read, unless configured otherwise
+pub const HEARTBEAT_INTERVAL_SECS: u64 = 10; // Seconds between pings sent to check the other end of a connection is still there
+pub const HEARTBEAT_MAX_MISSED: u32 = 3; // Pings the other end can miss before the connection is considered lost
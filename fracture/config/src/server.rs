@@ -1,2 +1,20 @@
-// pub const USER_UPDATE_CHANNEL_LIMIT: usize = 30;// Ammount of user updates (leaves and joins) that the ClientTracker can keep track of at once
+pub const USER_UPDATE_CHANNEL_LIMIT: usize = 30;// Ammount of user updates (leaves and joins) that the ClientTracker can keep track of at once
 pub const GLOBAL_HANDLER_OP_LIMIT: usize = 40; // Number of `GlobalHandlerOperation` that a client can be waiting on at once
+pub const DEFAULT_CHANNEL: &str = "general"; // Channel every client joins when it connects
+pub const HISTORY_REPLAY_LENGTH: usize = 50; // Messages sent to a client when it joins a channel
+pub const MEMORY_HISTORY_LENGTH: usize = 1000; // Messages kept (so they can be replayed, edited and deleted) when the history is not saved to disk
+pub const MAX_HISTORY_PAGE: u32 = 200; // Most messages sent in response to one `RequestHistory`
+pub const MAX_THREAD_REPLIES: usize = 200; // Most replies sent in response to one `RequestThread`, the newest ones are sent if there are more (or they do not all fit in one message)
+pub const REPLY_SNIPPET_LENGTH: usize = 80; // Characters of the parent message quoted with a reply
+pub const MAX_REACTION_LENGTH: usize = 16; // Characters in a reaction, emoji can be made of several
+pub const MAX_REACTIONS: usize = 20; // Different reactions a message can have
+pub const MAX_STATUS_TEXT_LENGTH: usize = 64; // Characters in the text someone can set along with their status
+pub const FORGIVE_VIOLATIONS_SECS: u64 = 60; // Seconds a client has to stay within the rate limits before its earlier violations are forgotten
+pub const RESUME_GRACE_SECS: u64 = 120; // Seconds a client that lost its connection keeps its name and channels, waiting for it to reconnect
+pub const TYPING_FORWARD_SECS: u64 = 3; // Least seconds between passing on that a client is still typing somewhere, so it can not flood everyone with them
+pub const PRESENCE_CHANGE_SECS: u64 = 5; // Least seconds between passing on changes to a clients status, changes in between are merged into the last of them
+pub const MAX_CHANNELS: usize = 100; // Channels that can be created, including the default one
+pub const MAX_TOPIC_LENGTH: usize = 128; // Characters in the topic of a channel
+pub const MAX_HELD_DIRECT: usize = 50; // Direct messages kept for a client that lost its connection, and given to it when it resumes its session
+pub const TURN_AWAY_SECS: u64 = 5; // Seconds a connection that is turned away before connecting (like for being over the connection limits) gets to be told why
+pub const MAX_MUTE_SECS: u64 = 30 * 24 * 60 * 60; // Longest someone can be muted for, ban them for anything longer
@@ -0,0 +1,247 @@
+/// Framing for `Message`s, for use with `tokio_util::codec::Framed`
+///
+/// Each message is sent as a `Header` followed by the bincode encoded message.
+/// Partialy read frames are kept by the codec (and the buffer of the `Framed` it is in) between polls,
+/// so reading from a `Framed<_, FractureCodec>` is cancelation safe, unlike reading from the socket directly.
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::msg::{Header, Message, HEADER_LEN};
+use crate::stat::{ReadMessageError, SendError};
+
+#[derive(Debug)]
+pub struct FractureCodec {
+    /// Header of the frame currently being read, if it has already been parsed
+    current: Option<Header>,
+    /// Largest message that will be accepted, anything bigger is rejected as soon as its header is read.
+    /// messages bigger than this are not sent either, since the other side uses the same limit
+    max_size: usize,
+    /// Size of the last message decoded (not including the header)
+    last_read: usize,
+    /// Size of the last message encoded (not including the header)
+    last_written: usize,
+}
+
+impl Default for FractureCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FractureCodec {
+    /// Create a codec that accepts messages up to `conf::DEFAULT_MAX_MESSAGE_SIZE`
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_max_size(crate::conf::DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    /// Create a codec that accepts messages up to `max_size` bytes
+    #[must_use]
+    pub const fn with_max_size(max_size: usize) -> Self {
+        Self {
+            current: None,
+            max_size,
+            last_read: 0,
+            last_written: 0,
+        }
+    }
+
+    /// Change the largest message that will be accepted (or sent)
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+    }
+
+    /// Size of the last message that was decoded, not including the header
+    #[must_use]
+    pub const fn last_read(&self) -> usize {
+        self.last_read
+    }
+
+    /// Size of the last message that was encoded, not including the header
+    #[must_use]
+    pub const fn last_written(&self) -> usize {
+        self.last_written
+    }
+}
+
+impl Decoder for FractureCodec {
+    type Item = Message;
+    type Error = ReadMessageError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let header = match self.current.take() {
+            Some(header) => header,
+            None => {
+                if src.len() < HEADER_LEN {
+                    src.reserve(HEADER_LEN - src.len());
+                    return Ok(None);
+                }
+                Header::from_bytes(&src.split_to(HEADER_LEN).freeze(), self.max_size)?
+            }
+        };
+        let size = header.size();
+        if src.len() < size {
+            src.reserve(size - src.len());
+            self.current = Some(header);
+            return Ok(None);
+        }
+        let body = src.split_to(size);
+        let msg: Message = bincode::deserialize(&body[..])?;
+        self.last_read = size;
+        if crate::conf::SOCK_DBG {
+            println!("Receved:\n{:#?}", msg);
+        }
+        Ok(Some(msg))
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(buf)? {
+            Some(msg) => Ok(Some(msg)),
+            None => {
+                if buf.is_empty() && self.current.is_none() {
+                    Ok(None)
+                } else {
+                    // the other side left in the middle of sending a message
+                    Err(ReadMessageError::Disconnected)
+                }
+            }
+        }
+    }
+}
+
+impl Encoder<Message> for FractureCodec {
+    type Error = SendError;
+
+    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if crate::conf::SOCK_DBG {
+            println!("Sent:\n{:#?}", item);
+        }
+        let mut serialized = crate::seri::serialize(&item)?;
+        if serialized.size() > self.max_size {
+            return Err(SendError::TooLarge {
+                size: serialized.size(),
+                max: self.max_size,
+            });
+        }
+        self.last_written = serialized.size();
+        dst.extend_from_slice(&serialized.into_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::MessageVarient;
+
+    fn chat(content: &str) -> Message {
+        Message {
+            data: MessageVarient::ClientSendChat {
+                content: content.to_string(),
+                channel: String::from("general"),
+                nonce: 1,
+                parent: None,
+            },
+        }
+    }
+
+    fn content(msg: &Message) -> &str {
+        match &msg.data {
+            MessageVarient::ClientSendChat { content, .. } => content,
+            other => panic!("expected a chat, got {:?}", other),
+        }
+    }
+
+    fn encoded(msgs: &[Message]) -> BytesMut {
+        let mut codec = FractureCodec::new();
+        let mut buf = BytesMut::new();
+        for msg in msgs {
+            codec.encode(msg.clone(), &mut buf).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut buf = encoded(&[chat("hello")]);
+        let len = buf.len();
+        let mut codec = FractureCodec::new();
+        let msg = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(content(&msg), "hello");
+        assert_eq!(codec.last_read(), len - HEADER_LEN);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn partial_reads() {
+        let bytes = encoded(&[chat("one byte at a time")]);
+        let mut codec = FractureCodec::new();
+        let mut buf = BytesMut::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            buf.extend_from_slice(&[*byte]);
+            let res = codec.decode(&mut buf).unwrap();
+            if i + 1 < bytes.len() {
+                assert!(res.is_none(), "decoded a message after only {} bytes", i + 1);
+            } else {
+                assert_eq!(content(&res.unwrap()), "one byte at a time");
+            }
+        }
+    }
+
+    #[test]
+    fn split_header() {
+        let bytes = encoded(&[chat("split")]);
+        let mut codec = FractureCodec::new();
+        let mut buf = BytesMut::from(&bytes[..HEADER_LEN / 2]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(&bytes[HEADER_LEN / 2..HEADER_LEN + 1]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(&bytes[HEADER_LEN + 1..]);
+        assert_eq!(content(&codec.decode(&mut buf).unwrap().unwrap()), "split");
+    }
+
+    #[test]
+    fn several_frames_in_one_read() {
+        let mut buf = encoded(&[chat("first"), chat("second"), chat("third")]);
+        let mut codec = FractureCodec::new();
+        for expected in ["first", "second", "third"] {
+            assert_eq!(content(&codec.decode(&mut buf).unwrap().unwrap()), expected);
+        }
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn too_large_to_read() {
+        let mut buf = encoded(&[chat(&"x".repeat(1000))]);
+        let mut codec = FractureCodec::with_max_size(100);
+        // rejected from the header alone, before the body is waited for
+        buf.truncate(HEADER_LEN);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(ReadMessageError::HeaderParser(crate::msg::HeaderParserError::TooLarge { max: 100, .. }))
+        ));
+    }
+
+    #[test]
+    fn too_large_to_send() {
+        let mut codec = FractureCodec::with_max_size(100);
+        let mut buf = BytesMut::new();
+        assert!(matches!(
+            codec.encode(chat(&"x".repeat(1000)), &mut buf),
+            Err(SendError::TooLarge { max: 100, .. })
+        ));
+        assert!(buf.is_empty());
+        // it is still usable afterwards
+        codec.encode(chat("small"), &mut buf).unwrap();
+        assert_eq!(content(&codec.decode(&mut buf).unwrap().unwrap()), "small");
+    }
+
+    #[test]
+    fn eof_in_the_middle_of_a_frame() {
+        let bytes = encoded(&[chat("cut off")]);
+        let mut codec = FractureCodec::new();
+        let mut buf = BytesMut::from(&bytes[..bytes.len() - 1]);
+        assert!(matches!(codec.decode_eof(&mut buf), Err(ReadMessageError::Disconnected)));
+        assert!(FractureCodec::new().decode_eof(&mut BytesMut::new()).unwrap().is_none());
+    }
+}
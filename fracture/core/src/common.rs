@@ -1,6 +1,6 @@
 /// This is for functions to generate common messages
 /// All functions should be inlined, but you do not need #[inline] for this, if lto is on in cargo.toml
-use crate::msg::{Message, MessageVarient};
+use crate::msg::{capabilities, Message, MessageVarient, PROTOCOL_VERSION};
 
 #[must_use]
 pub const fn ping() -> Message {
@@ -10,8 +10,24 @@ pub const fn ping() -> Message {
 }
 
 #[must_use]
-pub const fn gen_connect(name: String) -> Message {
+pub fn gen_connect(name: String) -> Message {
     Message {
-        data: MessageVarient::ConnectMessage { name },
+        data: MessageVarient::ConnectMessage {
+            protocol: PROTOCOL_VERSION,
+            capabilities: capabilities::supported(),
+            name,
+        },
+    }
+}
+
+#[must_use]
+pub fn gen_resume(name: String, token: String) -> Message {
+    Message {
+        data: MessageVarient::ResumeSession {
+            protocol: PROTOCOL_VERSION,
+            capabilities: capabilities::supported(),
+            name,
+            token,
+        },
     }
 }
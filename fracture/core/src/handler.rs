@@ -4,6 +4,27 @@ pub enum GlobalHandlerOperation {
         msg: crate::msg::Message,
     },
 
+    /// Send a message to one client
+    MsgOne {
+        uuid: uuid::Uuid,
+        msg: crate::msg::Message,
+    },
+
+    /// Send a message to everyone in a channel
+    MsgChannel {
+        channel: String,
+        msg: crate::msg::Message,
+    },
+
+    /// Tell everyone in a channel (or the other person in a direct conversation) that someone started or stopped typing.
+    /// it is not sent back to whoever is typing
+    Typing {
+        from: uuid::Uuid,
+        name: String,
+        target: crate::msg::types::TypingTarget,
+        typing: bool,
+    },
+
     // no handler should produce these, they are produced by the clients wrapper (server only)
     ClientDisconnect {
         uuid: uuid::Uuid,
@@ -16,6 +37,28 @@ pub enum GlobalHandlerOperation {
         uuid: uuid::Uuid,
         name: String,
     },
+    ClientPresence {
+        uuid: uuid::Uuid,
+        presence: crate::msg::types::Presence,
+    },
+
+    /// Disconnect clients the admin `by` kicked or banned, and tell them `why`.
+    /// if `ban_address` is set, the addresses they connected from are banned too (server only)
+    Kick {
+        target: KickTarget,
+        reason: crate::msg::types::ServerDisconnectReason,
+        by: String,
+        why: Option<String>,
+        ban_address: bool,
+    },
+}
+
+/// Who a `GlobalHandlerOperation::Kick` is for
+#[derive(Clone, Debug)]
+pub enum KickTarget {
+    User(uuid::Uuid),
+    /// everyone connected from this address
+    Address(std::net::IpAddr),
 }
 
 /// Generic trait for createing a message handler.
@@ -0,0 +1,87 @@
+/// Keeping track of whether the other end of a connection is still there.
+///
+/// Both sides send a `Ping` every `interval`, and anything received from the peer counts as a sign of life.
+/// A peer that has not sent anything in `max_missed` intervals is considered dead, which catches half open connections
+/// long before the OS would notice.
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeartbeatSettings {
+    /// seconds between pings
+    pub interval_secs: u64,
+    /// pings the peer can go without answering (or sending anything else) before it is considered dead
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: crate::conf::HEARTBEAT_INTERVAL_SECS,
+            max_missed: crate::conf::HEARTBEAT_MAX_MISSED,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Heartbeat {
+    interval: Duration,
+    max_missed: u32,
+    last_received: Instant,
+    /// when the last ping was sent, and if it has been answered yet
+    last_ping: Option<(Instant, bool)>,
+    rtt: Option<Duration>,
+}
+
+impl Heartbeat {
+    pub fn new(settings: &HeartbeatSettings) -> Self {
+        Self {
+            interval: Duration::from_secs(settings.interval_secs.max(1)),
+            max_missed: settings.max_missed.max(1),
+            last_received: Instant::now(),
+            last_ping: None,
+            rtt: None,
+        }
+    }
+
+    /// Something was received from the peer
+    pub fn received(&mut self) {
+        self.last_received = Instant::now();
+    }
+
+    /// The peer answered a ping, returning how long it took if it was the answer to the last one
+    pub fn pong(&mut self) -> Option<Duration> {
+        match &mut self.last_ping {
+            Some((sent, answered @ false)) => {
+                *answered = true;
+                let rtt = sent.elapsed();
+                self.rtt = Some(rtt);
+                Some(rtt)
+            }
+            _ => None,
+        }
+    }
+
+    /// If it is time to send another ping. returns true once for every ping, so it should be sent when this does
+    pub fn ping_due(&mut self) -> bool {
+        let due = self
+            .last_ping
+            .is_none_or(|(sent, _)| sent.elapsed() >= self.interval);
+        if due {
+            self.last_ping = Some((Instant::now(), false));
+        }
+        due
+    }
+
+    /// If the peer has not sent anything in `max_missed` pings
+    pub fn is_dead(&self) -> bool {
+        self.last_received.elapsed() > self.interval * self.max_missed
+    }
+
+    /// The round trip time measured with the last ping that was answered
+    pub const fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+}
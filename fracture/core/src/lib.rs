@@ -1,10 +1,13 @@
 pub use fracture_config::core as conf;
 
 pub mod msg;
+pub mod codec;
 mod serializeation;
 pub use serializeation::*;
 mod sock_readable;
 pub use sock_readable::*;
 pub mod common;
 pub mod handler;
+pub mod heartbeat;
+pub mod transport;
 pub mod utils;
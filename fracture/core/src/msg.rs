@@ -10,6 +10,14 @@ pub mod types {
         Closed,
         ClientRequestedDisconnect,
         InvalidConnectionSequence,
+        /// The client sent a message larger than the server allows
+        MessageTooLarge,
+        /// The client kept sending messages faster than the server allows, after being warned with `RateLimited`
+        RateLimited,
+        /// A admin kicked the client, the close message says who and why
+        Kicked,
+        /// The clients account or address is banned, the close message says who by and why
+        Banned,
     }
 
     //TODO this
@@ -22,6 +30,95 @@ pub mod types {
         UserNamed { uuid: u128, name: String },
 
         UserLeft { uuid: u128, name: Option<String> },
+
+        /// the user changed their status, or went idle. also sent for everyone already connected when joining
+        PresenceChanged { uuid: u128, presence: Presence },
+    }
+
+    /// What someone is doing, as they (or their client, when they go idle) said
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PresenceStatus {
+        Online,
+        Away,
+        Busy,
+    }
+
+    /// Someones status, as sent in `UserNameUpdate::PresenceChanged`
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+    pub struct Presence {
+        pub status: PresenceStatus,
+        /// what they said they are up to, like "in a meeting"
+        pub text: Option<String>,
+        /// when the server last saw them do something, in milliseconds since the unix epoch (UTC)
+        pub last_seen: i64,
+    }
+
+    /// What a admin does to someone with `Moderate`
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+    pub enum ModerationAction {
+        /// disconnect them, they can come back
+        Kick,
+        /// stop them from saying anything for `secs` seconds
+        Mute { secs: u64 },
+        Unmute,
+        /// ban their account (and their address too if `address` is true), and disconnect them.
+        /// with `address`, the target can also be a IP address
+        Ban { address: bool },
+        /// take back the bans of a account (and the addresses it was banned with), or of a IP address
+        Unban,
+    }
+
+    /// A chat channel, as listed in `ChannelList`
+    #[derive(Deserialize, Serialize, Debug, Clone)]
+    pub struct ChannelInfo {
+        pub name: String,
+        pub topic: Option<String>,
+        /// how many people are in the channel
+        pub members: usize,
+    }
+
+    /// A chat message that was said in a channel before, as sent in `History`
+    #[derive(Deserialize, Serialize, Debug, Clone)]
+    pub struct HistoryMessage {
+        /// position in the servers history, older messages have smaller ids
+        pub id: u64,
+        /// milliseconds since the unix epoch (UTC)
+        pub timestamp: i64,
+        pub content: String,
+        pub author: String,
+        pub author_uuid: u128,
+        /// when it was last edited, in milliseconds since the unix epoch (UTC)
+        pub edited_at: Option<i64>,
+        /// deleted messages are still listed (with no content), so clients can show that something was there
+        pub deleted: bool,
+        /// the message this is a reply to
+        pub parent: Option<ReplyParent>,
+        pub reactions: Vec<Reaction>,
+    }
+
+    /// Where someone is typing
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+    pub enum TypingTarget {
+        Channel(String),
+        /// a direct conversation. from the client this is who it is with, from the server it is who is typing
+        Direct(u128),
+    }
+
+    /// Everyone who reacted to a message with the same emoji
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+    pub struct Reaction {
+        pub emoji: String,
+        /// uuids of who reacted, in the order they did
+        pub users: Vec<u128>,
+    }
+
+    /// The message a chat is a reply to, with enough of it to quote above the reply
+    #[derive(Deserialize, Serialize, Debug, Clone)]
+    pub struct ReplyParent {
+        pub id: u64,
+        pub author: String,
+        /// the start of what it says, empty if it was deleted
+        pub snippet: String,
     }
 
     //TODO this
@@ -31,6 +128,73 @@ pub mod types {
         Connected,
         Refused { reason: String },
     }
+
+    /// Version of the wire protocol spoken by a client or server
+    ///
+    /// peers with different `major` versions cannot understand each other,
+    /// differing `minor` versions only mean that one side may know about messages the other does not
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ProtocolVersion {
+        pub major: u16,
+        pub minor: u16,
+    }
+
+    impl ProtocolVersion {
+        /// If a peer speaking `other` can talk to a peer speaking `self`
+        #[must_use]
+        pub const fn is_compatible(&self, other: &Self) -> bool {
+            self.major == other.major
+        }
+    }
+
+    impl std::fmt::Display for ProtocolVersion {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}.{}", self.major, self.minor)
+        }
+    }
+}
+
+/// The protocol version this build speaks.
+///
+/// Bump `major` whenever the layout of an existing message changes (including adding variants anywhere but the end of `MessageVarient`),
+/// and `minor` when only new messages are appended.
+pub const PROTOCOL_VERSION: types::ProtocolVersion = types::ProtocolVersion { major: 11, minor: 0 };
+
+/// Optional features that are negotiated during the handshake.
+///
+/// The client lists what it supports in `ConnectMessage`, and the server replies with the ones both sides support in `ServerInfo`.
+/// These are plain strings so that peers can advertise capabilities the other side has never heard of.
+pub mod capabilities {
+    /// Client can answer `AuthRequest`, servers that need a password refuse clients without it
+    pub const AUTH: &str = "auth";
+
+    /// Client can answer `LoginRequest`, servers with accounts refuse clients without it
+    pub const ACCOUNTS: &str = "accounts";
+
+    /// Client understands `NameAssigned`, and will use the name the server gives it
+    pub const NAMES: &str = "names";
+
+    /// Client understands `SessionStarted`, and can come back after losing its connection with `ResumeSession`
+    pub const RESUME: &str = "resume";
+
+    /// Every capability this build supports
+    pub const SUPPORTED: &[&str] = &[AUTH, ACCOUNTS, NAMES, RESUME];
+
+    /// Get the capabilities from `offered` that this build also supports
+    #[must_use]
+    pub fn negotiate(offered: &[String]) -> Vec<String> {
+        offered
+            .iter()
+            .filter(|cap| SUPPORTED.contains(&cap.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// `SUPPORTED` as owned strings, for sending
+    #[must_use]
+    pub fn supported() -> Vec<String> {
+        SUPPORTED.iter().map(|cap| (*cap).to_string()).collect()
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -40,18 +204,30 @@ pub enum MessageVarient {
     DisconnectMessage {},
 
     /// Client sends this as its first message
+    ///
+    /// this must stay the second variant, with `protocol` as its first field,
+    /// so that peers running other protocol versions can still read the version and refuse cleanly
     ConnectMessage {
+        protocol: types::ProtocolVersion,
+        capabilities: Vec<String>,
         name: String,
     },
 
     //TODO this
     /// Server sends this after client sends `ConnectMessage`, with info about the server
     /// should not be sent any other time
+    ///
+    /// like `ConnectMessage`, this must stay in place with `protocol` and `conn_status` first
     ServerInfo {
-        server_name: String,
+        protocol: types::ProtocolVersion,
         conn_status: types::ConnectionStatus,
+        server_name: String,
         connected_users: Vec<types::UserNameUpdate>,
         your_uuid: u128,
+        /// the capabilities both the client and server support
+        capabilities: Vec<String>,
+        /// largest message (not including the header) the server will send or accept
+        max_message_size: u64,
     },
 
     //TODO this
@@ -77,17 +253,274 @@ pub enum MessageVarient {
     /// Client sends this to server
     ClientSendChat {
         content: String,
+        /// the channel to send it in, the client must have joined it
+        channel: String,
+        /// picked by the client, and sent back with the servers copy of the message (or `ChatFailed`), so it can tell which message it is
+        nonce: u64,
+        /// id of the message this replies to, it has to be in the same channel
+        parent: Option<u64>,
     },
 
     /// Server sends this to the client
     ServerSendChat {
+        /// unique on this server, and bigger for newer messages
+        id: u64,
+        /// when the server received it, in milliseconds since the unix epoch (UTC)
+        timestamp: i64,
         content: String,
         author: String,
         author_uuid: u128,
+        channel: String,
+        /// the nonce from `ClientSendChat`. only meaningful to the author
+        nonce: Option<u64>,
+        parent: Option<types::ReplyParent>,
     },
 
     TestMessage {},
     TestMessageResponse {},
+
+    /// Server sends this after `ConnectMessage` when the client has to authenticate before it can join
+    AuthRequest {},
+
+    /// Client sends this in response to `AuthRequest`
+    Authenticate {
+        password: String,
+    },
+
+    /// Server sends this when the client has to log in to a account before it can join
+    /// (after `AuthRequest`, if the server also has a password)
+    LoginRequest {},
+
+    /// Client sends this in response to `LoginRequest`, to log in to a existing account
+    Login {
+        username: String,
+        password: String,
+    },
+
+    /// Client sends this in response to `LoginRequest`, to create a new account and log in to it
+    Register {
+        username: String,
+        password: String,
+    },
+
+    /// Server sends this before `ServerInfo` when the client was given a different name than it asked for
+    /// (because someone else is already using it)
+    NameAssigned {
+        name: String,
+    },
+
+    /// Client sends this to start receiving chat from a channel
+    JoinChannel {
+        channel: String,
+    },
+
+    /// Client sends this to stop receiving chat from a channel
+    LeaveChannel {
+        channel: String,
+    },
+
+    /// Client sends this to make a new channel, and join it
+    CreateChannel {
+        channel: String,
+        topic: Option<String>,
+    },
+
+    /// Client sends this to get a `ChannelList`
+    ListChannels {},
+
+    /// Server sends this when the client is now in a channel (including the default channel, right after `ServerInfo`)
+    ChannelJoined {
+        channel: String,
+        topic: Option<String>,
+    },
+
+    /// Server sends this when the client is no longer in a channel
+    ChannelLeft {
+        channel: String,
+    },
+
+    /// Server sends this in response to `ListChannels`
+    ChannelList {
+        channels: Vec<types::ChannelInfo>,
+    },
+
+    /// Server sends this when a channel message from the client could not be done
+    ChannelError {
+        channel: String,
+        reason: String,
+    },
+
+    /// Client sends this to send a private message to one user
+    ClientSendDirect {
+        to: u128,
+        content: String,
+        /// the same as in `ClientSendChat`
+        nonce: u64,
+    },
+
+    /// Server sends this to the recipient of a `ClientSendDirect`, and back to its author
+    ServerSendDirect {
+        /// from the same ids as `ServerSendChat`
+        id: u64,
+        /// milliseconds since the unix epoch (UTC)
+        timestamp: i64,
+        content: String,
+        author: String,
+        author_uuid: u128,
+        /// the recipient
+        to: u128,
+        /// the nonce from `ClientSendDirect`, only sent to the author
+        nonce: Option<u64>,
+    },
+
+    /// Server sends this when a `ClientSendChat` or `ClientSendDirect` could not be delivered
+    /// (for example, the client is not in the channel, or the recipient is offline)
+    ChatFailed {
+        nonce: u64,
+        reason: String,
+    },
+
+    /// Client sends this to get up to `limit` messages said in a channel before the message with the id `before` (or the newest ones, if it is None)
+    RequestHistory {
+        channel: String,
+        before: Option<u64>,
+        limit: u32,
+    },
+
+    /// Server sends this in response to `RequestHistory`, and after `ChannelJoined` with the most recent messages.
+    /// messages are oldest first, and there are no more older ones if this is empty
+    History {
+        channel: String,
+        messages: Vec<types::HistoryMessage>,
+    },
+
+    /// Client sends this to change what one of its channel messages says (moderators can change anyones)
+    EditChat {
+        id: u64,
+        content: String,
+    },
+
+    /// Client sends this to delete one of its channel messages (moderators can delete anyones)
+    DeleteChat {
+        id: u64,
+    },
+
+    /// Server sends this to everyone in the channel when a message was edited
+    ChatEdited {
+        id: u64,
+        channel: String,
+        content: String,
+        /// milliseconds since the unix epoch (UTC)
+        edited_at: i64,
+    },
+
+    /// Server sends this to everyone in the channel when a message was deleted
+    ChatDeleted {
+        id: u64,
+        channel: String,
+    },
+
+    /// Server sends this when a `EditChat`, `DeleteChat`, `AddReaction` or `RemoveReaction` was not allowed
+    ChatChangeFailed {
+        id: u64,
+        reason: String,
+    },
+
+    /// Client sends this to get a message, and every reply to it
+    RequestThread {
+        id: u64,
+    },
+
+    /// Server sends this in response to `RequestThread`. replies are oldest first
+    Thread {
+        channel: String,
+        parent: types::HistoryMessage,
+        replies: Vec<types::HistoryMessage>,
+    },
+
+    /// Server sends this when a `RequestThread` could not be answered
+    ThreadFailed {
+        id: u64,
+        reason: String,
+    },
+
+    /// Client sends this to react to a channel message
+    AddReaction {
+        message_id: u64,
+        emoji: String,
+    },
+
+    /// Client sends this to take back its reaction
+    RemoveReaction {
+        message_id: u64,
+        emoji: String,
+    },
+
+    /// Server sends this to everyone in the channel when the reactions to a message change, with all of them
+    ReactionsChanged {
+        message_id: u64,
+        channel: String,
+        reactions: Vec<types::Reaction>,
+    },
+
+    /// Client sends this when the user starts typing (and again every so often while they are), and when they stop.
+    /// it is not saved, so clients should forget about it after a while if they are not told it stopped
+    Typing {
+        target: types::TypingTarget,
+        typing: bool,
+    },
+
+    /// Server sends this to everyone else in the channel (or the other person in a direct conversation) for a `Typing`
+    UserTyping {
+        user: u128,
+        name: String,
+        target: types::TypingTarget,
+        typing: bool,
+    },
+
+    /// Client sends this to change its status, and what it says about it.
+    /// going `Away` does not count as doing something, so clients send it when the user goes idle
+    SetPresence {
+        status: types::PresenceStatus,
+        text: Option<String>,
+    },
+
+    /// Server sends this when the client sends messages faster than it allows. messages are dropped until `retry_after_ms` has passed.
+    /// it is only sent once for every flood, and the client is disconnected after being warned `warnings_left` more times
+    RateLimited {
+        retry_after_ms: u64,
+        warnings_left: u32,
+    },
+
+    /// Server sends this right before `ServerInfo`, to clients with the resume capability.
+    /// `token` can be sent in `ResumeSession` to come back as the same user after losing the connection,
+    /// and `resumed` is true if this connection did that
+    SessionStarted {
+        token: String,
+        resumed: bool,
+    },
+
+    /// Client sends this instead of `ConnectMessage` when reconnecting, to take back the session `token` is for.
+    /// if the session is gone (it was not resumed in time, or the server restarted), the server carries on like this was a `ConnectMessage`
+    ResumeSession {
+        protocol: types::ProtocolVersion,
+        capabilities: Vec<String>,
+        name: String,
+        token: String,
+    },
+
+    /// Client sends this to kick, mute or ban the user called `target`. only admins can
+    Moderate {
+        target: String,
+        action: types::ModerationAction,
+        reason: Option<String>,
+    },
+
+    /// Server sends this in response to `Moderate`, saying what happened (or why nothing did)
+    ModerationResult {
+        ok: bool,
+        message: String,
+    },
 }
 
 /// Hello, hello, can you hear me?
@@ -106,6 +539,8 @@ pub enum HeaderParserError {
     InvalidMsgSize,
     #[error("Invalid header suffix")]
     InvalidSuffix,
+    #[error("Message of {size} bytes is larger than the maximum of {max} bytes")]
+    TooLarge { size: u64, max: usize },
 }
 
 pub const HEADER_LEN: usize = b"ds-header".len() +
@@ -116,6 +551,7 @@ b"header-end".len();
 ///
 /// Designed to be send before each Message, and have a fixed length so the other program
 /// knows how many by bytes to read
+#[derive(Debug)]
 pub struct Header {
     msg_size: u64,
 }
@@ -159,9 +595,11 @@ impl Header {
 
     /// Takes `bytes::Bytes` and creates a `Header`
     ///
+    /// `max_size` is the largest message size that will be accepted, so that a bad header can not make the reader allocate huge buffers
+    ///
     /// # Errors
-    /// if the header is invalid, returning what part was invalid
-    pub fn from_bytes(header: &bytes::Bytes) -> Result<Self, HeaderParserError> {
+    /// if the header is invalid, returning what part was invalid, or if the message is larger than `max_size`
+    pub fn from_bytes(header: &bytes::Bytes, max_size: usize) -> Result<Self, HeaderParserError> {
         if header.len() == HEADER_LEN {
             let header_start = header.slice(..b"ds-header".len());
             let mut message_size = header.slice(b"ds-header".len()..b"ds-header".len() + 8);
@@ -172,9 +610,14 @@ impl Header {
             if &header_end[..] != b"header-end" {
                 return Err(HeaderParserError::InvalidSuffix);
             }
-            Ok(Self {
-                msg_size: message_size.get_u64(),
-            })
+            let msg_size = message_size.get_u64();
+            if usize::try_from(msg_size).map_or(true, |size| size > max_size) {
+                return Err(HeaderParserError::TooLarge {
+                    size: msg_size,
+                    max: max_size,
+                });
+            }
+            Ok(Self { msg_size })
         } else {
             Err(HeaderParserError::InvalidLength)
         }
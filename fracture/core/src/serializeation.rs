@@ -2,33 +2,9 @@
 pub mod seri {
     use crate::msg;
     use crate::msg::Message;
-    use crate::msg::{Header, HeaderParserError, HEADER_LEN};
+    use crate::msg::Header;
     use bytes::Bytes;
-    use tokio::io::AsyncWriteExt;
-    use tokio::net::TcpStream;
-
-    pub mod res {
-        use thiserror::Error;
-        use super::{HeaderParserError, Message};
-
-        #[derive(Debug)]
-        pub struct GetMessageResponse {
-            pub msg: Message,
-            pub bytes: usize,
-        }
-
-        #[derive(Error, Debug)]
-        pub enum GetMessageError {
-            #[error("Connection closed")]
-            Disconnected,
-            #[error("Failed to read from socket: {0}")]
-            ReadError(std::io::Error),
-            #[error("Failed to parse header: {0}")]
-            HeaderParser(HeaderParserError),
-            #[error("Failed to deserialize message: {0}")]
-            DeserializationError(Box<bincode::ErrorKind>),
-        }
-    }
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
 
     pub struct SerializedMessage {
         message_bytes: Vec<u8>,
@@ -68,106 +44,21 @@ pub mod seri {
     /// Sends a message all in one go, to socket
     ///
     /// equivilant to doing
+    /// ```ignore
+    /// let encoded = seri::serialize(&message).unwrap().into_bytes();
+    /// socket.write_all(&encoded).await.unwrap();
     /// ```
-    /// let encoded = api::seri::serialize(&message).unwrap().as_bytes();
-    /// stream.write_all(&encoded).await.unwrap();
-    /// ```
+    ///
+    /// to read messages, wrap the socket in a `Framed` with a `FractureCodec` instead,
+    /// as that will not lose partialy read messages
     ///
     /// # Panics
     /// if it could not serialize the message or write to the socket
-    pub async fn fullsend(msg: &Message, socket: &mut TcpStream) {
+    pub async fn fullsend<T: AsyncWrite + Unpin>(msg: &Message, socket: &mut T) {
         let encoded = self::serialize(msg).unwrap().into_bytes();
         socket.write_all(&encoded).await.unwrap();
     }
 
-    /// Recieve and deserialize a message from a `TcpStream`
-    /// asynchronus, and will not return untill is encounters a error, or reads one whole message
-    ///
-    /// # Cancelation Saftey
-    /// this is cancelation safe, however when canceled it will not store what it has read, so the next attempt to read and deserialize __will__ fail
-    ///
-    /// # Errors
-    /// if one of these has happened while decoding:
-    /// - the client has disconnected
-    /// - there was a error while reading from the socket
-    /// - the message could not be deserialized
-    pub async fn get_message_from_socket(
-        socket: &mut TcpStream,
-    ) -> Result<res::GetMessageResponse, res::GetMessageError> {
-        //TODO god fix this sin
-        drop(socket.readable().await); //heck u and ill see u never
-
-        let mut header_buffer = [0; HEADER_LEN];
-        let mut read = 0;
-        loop {
-            drop(socket.readable().await);
-            match socket.try_read(&mut header_buffer) {
-                Ok(0) => {
-                    return Err(res::GetMessageError::Disconnected);
-                }
-                Ok(n) => {
-                    read += n;
-                    if read >= HEADER_LEN {
-                        break;
-                    }
-                }
-                Err(err) => {
-                    if err.kind() == tokio::io::ErrorKind::WouldBlock {
-                        continue;
-                    }
-                    return Err(res::GetMessageError::ReadError(err));
-                }
-            }
-        }
-        let header_r = msg::Header::from_bytes(&vec2bytes(Vec::from(header_buffer)));
-        match header_r {
-            Ok(header) => {
-                let read_amnt = header.size();
-                let mut buffer: Vec<u8> = Vec::with_capacity(read_amnt);
-                let mut read = 0;
-                loop {
-                    //TODO goodbye
-                    drop(socket.readable().await);
-                    match socket.try_read_buf(&mut buffer) {
-                        Ok(0) => {
-                            return Err(res::GetMessageError::Disconnected);
-                        }
-                        Ok(n) => {
-                            read += n;
-                            if read >= read_amnt {
-                                //TODO implement parsing the message
-                                let deserialized: std::result::Result<
-                                    Message,
-                                    Box<bincode::ErrorKind>,
-                                > = bincode::deserialize(&buffer[..]);
-                                match deserialized {
-                                    Ok(msg) => {
-                                        return Ok(res::GetMessageResponse {
-                                            msg,
-                                            bytes: buffer.len(),
-                                        })
-                                    }
-                                    Err(err) => {
-                                        return Err(res::GetMessageError::DeserializationError(
-                                            err,
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            if err.kind() == tokio::io::ErrorKind::WouldBlock {
-                                continue;
-                            }
-                            return Err(res::GetMessageError::ReadError(err));
-                        }
-                    }
-                }
-            }
-            Err(err) => Err(res::GetMessageError::HeaderParser(err)),
-        }
-    }
-
     /// Creates `bytes::Bytes` from `Vec<u8>`
     #[must_use]
     pub fn vec2bytes(data: Vec<u8>) -> Bytes {
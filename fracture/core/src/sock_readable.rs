@@ -1,5 +1,8 @@
-use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use futures::{SinkExt, StreamExt};
+use tokio_util::codec::Framed;
+
+use crate::codec::FractureCodec;
+use crate::transport::Transport;
 
 pub mod stat {
     use thiserror::Error;
@@ -16,6 +19,9 @@ pub mod stat {
         Failure(#[from] std::io::Error),
         #[error("Failed to serialize message: {0}")]
         SeriError(#[from] Box<bincode::ErrorKind>),
+        /// the message was not sent, since the other side would refuse it. nothing was written, so the connection is still fine
+        #[error("Message is too large to send ({size} bytes, at most {max} are allowed)")]
+        TooLarge { size: usize, max: usize },
     }
 
     #[derive(Debug)]
@@ -29,7 +35,7 @@ pub mod stat {
         #[error("Disconnected while writing message!")]
         Disconnected,
         #[error("Failed to read from socket: {0}")]
-        ReadError(std::io::Error),
+        ReadError(#[from] std::io::Error),
         #[error("Failed to parse msg header: {0}")]
         HeaderParser(#[from] crate::msg::HeaderParserError),
         #[error("Failed to deserialize message: {0}")]
@@ -44,84 +50,35 @@ use self::stat::SendError;
 /// Some utils for dealing with sockets on a struct (reading and writing)
 #[async_trait::async_trait]
 pub trait SocketUtils {
-    /// the socket, since a trait cannot require a feild
-    fn get_sock(&mut self) -> &mut TcpStream;
+    /// what the messages are sent over
+    type Transport: Transport;
+
+    /// the framed socket, since a trait cannot require a feild
+    fn get_framed(&mut self) -> &mut Framed<Self::Transport, FractureCodec>;
+
     /// read one message from the socket
+    ///
+    /// # Cancelation Saftey
+    /// this is cancelation safe, partialy read messages are kept by the codec untill the next call
     async fn read_msg(&mut self) -> Result<ReadMessageStatus, ReadMessageError> {
-        let sock = self.get_sock();
-        //TODO god fix this sin
-        drop(sock.readable().await); //heck u and ill see u never
-
-        let mut header_buffer = [0; crate::msg::HEADER_LEN];
-        let mut read = 0;
-        loop {
-            drop(sock.readable().await);
-            match sock.try_read(&mut header_buffer) {
-                Ok(0) => {
-                    return Err(ReadMessageError::Disconnected);
-                }
-                Ok(n) => {
-                    read += n;
-                    if read >= crate::msg::HEADER_LEN {
-                        break;
-                    }
-                }
-                Err(err) => {
-                    if err.kind() == tokio::io::ErrorKind::WouldBlock {
-                        continue;
-                    }
-                    return Err(ReadMessageError::ReadError(err));
-                }
-            }
-        }
-        let header =
-            crate::msg::Header::from_bytes(&crate::seri::vec2bytes(Vec::from(header_buffer)))?;
-        let read_amnt = header.size();
-        let mut buffer: Vec<u8> = Vec::with_capacity(read_amnt);
-        let mut read = 0;
-        loop {
-            //TODO goodbye
-            drop(sock.readable().await);
-            match sock.try_read_buf(&mut buffer) {
-                Ok(0) => {
-                    return Err(ReadMessageError::Disconnected);
-                }
-                Ok(n) => {
-                    read += n;
-                    if read >= read_amnt {
-                        //TODO implement parsing the message
-                        let msg: crate::msg::Message = bincode::deserialize(&buffer[..])?;
-                        if crate::conf::SOCK_DBG {
-                            println!("Receved:\n{:#?}", msg);
-                        }
-                        return Ok(ReadMessageStatus {
-                            msg,
-                            bytes: buffer.len(),
-                        });
-                    }
-                }
-                Err(err) => {
-                    if err.kind() == tokio::io::ErrorKind::WouldBlock {
-                        continue;
-                    }
-                    return Err(ReadMessageError::ReadError(err));
-                }
+        let framed = self.get_framed();
+        match framed.next().await {
+            Some(Ok(msg)) => Ok(ReadMessageStatus {
+                msg,
+                bytes: framed.codec().last_read(),
+            }),
+            // TLS reports the other side closing without saying goodbye as an error, but it is just a disconnect
+            Some(Err(ReadMessageError::ReadError(err))) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                Err(ReadMessageError::Disconnected)
             }
+            Some(Err(err)) => Err(err),
+            None => Err(ReadMessageError::Disconnected),
         }
     }
 
     async fn send_message(&mut self, message: crate::msg::Message) -> Result<SendStatus, SendError> {
-        if crate::conf::SOCK_DBG {
-            println!("Sent:\n{:#?}", message);
-        }
-        let socket = self.get_sock();
-        let mut serialized_msg = crate::seri::serialize(&message)?;
-        let wrote_size = serialized_msg.size();
-        let b_data = serialized_msg.into_bytes();
-        let write_status = socket.write_all(&b_data).await;
-        match write_status {
-            Ok(_) => Ok(SendStatus::Sent(wrote_size)),
-            Err(err) => Err(SendError::Failure(err)),
-        }
+        let framed = self.get_framed();
+        framed.send(message).await?;
+        Ok(SendStatus::Sent(framed.codec().last_written()))
     }
 }
@@ -0,0 +1,130 @@
+/// Abstraction over the byte streams messages can be sent over
+///
+/// Anything that implements `AsyncRead + AsyncWrite` (tcp, unix sockets, tls streams, `tokio::io::duplex` pipes, ...)
+/// can be used as a `Transport`, and boxed into a `BoxedTransport` when the kind of transport is only known at runtime.
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T> Transport for T where T: AsyncRead + AsyncWrite + Unpin + Send {}
+
+/// A type erased `Transport`
+pub type BoxedTransport = Box<dyn Transport>;
+
+/// Where a connection came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerAddr {
+    Tcp(SocketAddr),
+    /// Unix domain socket, with the path of the peer if it has one (it usualy does not)
+    Unix(Option<PathBuf>),
+    /// Some other kind of transport (for example a in memory pipe)
+    Other(String),
+}
+
+impl PeerAddr {
+    /// The IP address of the peer, if it connected over the network
+    #[must_use]
+    pub const fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Self::Tcp(addr) => Some(addr.ip()),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PeerAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            Self::Unix(Some(path)) => write!(f, "unix:{}", path.display()),
+            Self::Unix(None) => write!(f, "unix:<unnamed>"),
+            Self::Other(desc) => write!(f, "{}", desc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::Framed;
+
+    use super::*;
+    use crate::codec::FractureCodec;
+    use crate::msg::{Message, MessageVarient};
+
+    fn chat(content: String) -> Message {
+        Message {
+            data: MessageVarient::ClientSendChat {
+                content,
+                channel: String::from("general"),
+                nonce: 1,
+                parent: None,
+            },
+        }
+    }
+
+    fn content(msg: Message) -> String {
+        match msg.data {
+            MessageVarient::ClientSendChat { content, .. } => content,
+            other => panic!("expected a chat, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_over_a_duplex() {
+        // a tiny buffer, so the bigger messages are written and read in lots of pieces
+        let (client, server) = tokio::io::duplex(64);
+        let client: BoxedTransport = Box::new(client);
+        let server: BoxedTransport = Box::new(server);
+        let mut client = Framed::new(client, FractureCodec::new());
+        let mut server = Framed::new(server, FractureCodec::new());
+        let sent: Vec<String> = vec![String::from("hi"), "x".repeat(10_000), String::new(), "y".repeat(300)];
+        let to_send = sent.clone();
+        let writer = tokio::spawn(async move {
+            for content in to_send {
+                client.send(chat(content)).await.unwrap();
+            }
+            client
+        });
+        for expected in &sent {
+            assert_eq!(&content(server.next().await.unwrap().unwrap()), expected);
+        }
+        // and the other way
+        let mut client = writer.await.unwrap();
+        server.send(Message { data: MessageVarient::Ping }).await.unwrap();
+        assert!(matches!(client.next().await.unwrap().unwrap().data, MessageVarient::Ping));
+        // the other side going away ends the stream
+        drop(server);
+        assert!(client.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn cancelled_reads_do_not_lose_data() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut server = Framed::new(Box::new(server) as BoxedTransport, FractureCodec::new());
+        let mut bytes = bytes::BytesMut::new();
+        tokio_util::codec::Encoder::encode(&mut FractureCodec::new(), chat(String::from("slow")), &mut bytes).unwrap();
+        let (first, rest) = bytes.split_at(bytes.len() / 2);
+        client.write_all(first).await.unwrap();
+        // gives up half way through the message, like a `select!` with a timer would
+        assert!(tokio::time::timeout(Duration::from_millis(20), server.next()).await.is_err());
+        client.write_all(rest).await.unwrap();
+        assert_eq!(content(server.next().await.unwrap().unwrap()), "slow");
+    }
+
+    #[test]
+    fn peer_addrs() {
+        let tcp = PeerAddr::Tcp("127.0.0.1:1234".parse().unwrap());
+        assert_eq!(tcp.ip(), Some("127.0.0.1".parse().unwrap()));
+        assert_eq!(tcp.to_string(), "127.0.0.1:1234");
+        assert_eq!(PeerAddr::Unix(None).ip(), None);
+        assert_eq!(PeerAddr::Unix(Some(PathBuf::from("/tmp/sock"))).to_string(), "unix:/tmp/sock");
+        assert_eq!(PeerAddr::Other(String::from("memory")).to_string(), "memory");
+    }
+}
@@ -0,0 +1,12 @@
+/// Fingerprints for identifying a servers TLS certificate
+use sha2::{Digest, Sha256};
+
+/// SHA-256 fingerprint of a DER encoded certificate, formatted like `AB:CD:...`
+#[must_use]
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<String>>()
+        .join(":")
+}
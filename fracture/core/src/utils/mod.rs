@@ -1,6 +1,7 @@
 use tokio::time::sleep;
 use tokio::time::Duration;
 
+pub mod fingerprint;
 pub mod ipencoding;
 
 #[inline]
@@ -0,0 +1,166 @@
+/// Persistent user accounts
+///
+/// Accounts are kept in a JSON file next to the config (`<base>.accounts.json`),
+/// with passwords hashed using argon2. Each account has a UUID that stays the same across connections.
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use parking_lot::Mutex;
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::names::same_name;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AccountError {
+    #[error("Incorrect username or password")]
+    InvalidCredentials,
+    #[error("The username {0:?} is already taken")]
+    UsernameTaken(String),
+    #[error("Could not read or write the account store {path}: {err}")]
+    Io { path: PathBuf, err: io::Error },
+    #[error("The account store is corrupted: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("Failed to hash password: {0}")]
+    Hash(argon2::password_hash::Error),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Account {
+    uuid: uuid::Uuid,
+    /// PHC formatted argon2 hash
+    password_hash: String,
+}
+
+/// The account store. clones share the same accounts
+#[derive(Clone, Debug)]
+pub struct Accounts {
+    path: PathBuf,
+    /// accounts by username
+    accounts: Arc<Mutex<HashMap<String, Account>>>,
+    /// held from checking a new username is free until it is saved, so new accounts are saved one at a time,
+    /// without holding up logins while the file is written
+    saving: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Accounts {
+    /// path of the account store for the server data at `base`
+    pub fn path_for(base: &Path) -> PathBuf {
+        base.with_extension("accounts.json")
+    }
+
+    /// Load the account store at `path`, starting a empty one if it does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self, AccountError> {
+        let accounts = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                info!("No account store found, a new one will be created at {}", path.display());
+                HashMap::new()
+            }
+            Err(err) => return Err(AccountError::Io { path, err }),
+        };
+        Ok(Self {
+            path,
+            accounts: Arc::new(Mutex::new(accounts)),
+            saving: Arc::new(tokio::sync::Mutex::new(())),
+        })
+    }
+
+    /// The stored hash for `username`, so it can be checked without holding on to the store
+    pub fn credentials(&self, username: &str) -> Option<(uuid::Uuid, String)> {
+        self.accounts
+            .lock()
+            .get(username)
+            .map(|account| (account.uuid, account.password_hash.clone()))
+    }
+
+    /// Add a account with a already hashed password, returning its new UUID.
+    /// names that only differ in case from a existing account are taken, so nobody can pass for someone else.
+    /// the account is only added once it has been saved
+    pub async fn insert(&self, username: String, password_hash: String) -> Result<uuid::Uuid, AccountError> {
+        let _saving = self.saving.lock().await;
+        let account = Account {
+            uuid: uuid::Uuid::new_v4(),
+            password_hash,
+        };
+        let json = {
+            let accounts = self.accounts.lock();
+            if accounts.keys().any(|name| same_name(name, &username)) {
+                return Err(AccountError::UsernameTaken(username));
+            }
+            let mut updated: HashMap<&String, &Account> = accounts.iter().collect();
+            updated.insert(&username, &account);
+            serde_json::to_string_pretty(&updated)?
+        };
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || std::fs::write(&path, json).map_err(|err| AccountError::Io { path, err }))
+            .await
+            .expect("account saving task panicked")?;
+        let uuid = account.uuid;
+        self.accounts.lock().insert(username, account);
+        Ok(uuid)
+    }
+}
+
+/// Hash a password for storage.
+/// this is slow on purpose, so do not call it while holding a lock
+pub fn hash_password(password: &str) -> Result<String, AccountError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(AccountError::Hash)
+}
+
+/// Check a password against a stored hash.
+/// this is slow on purpose, so do not call it while holding a lock
+pub fn verify_password(password: &str, password_hash: &str) -> Result<(), AccountError> {
+    let parsed = PasswordHash::new(password_hash).map_err(AccountError::Hash)?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .map_err(|_| AccountError::InvalidCredentials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> Accounts {
+        let path = std::env::temp_dir().join(format!("fracture-test-{}-{}.accounts.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Accounts::load(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn names_that_differ_in_case_are_taken() {
+        let accounts = store("case");
+        let uuid = accounts.insert(String::from("alice"), String::from("hash")).await.unwrap();
+        assert!(matches!(
+            accounts.insert(String::from("ALICE"), String::from("other")).await,
+            Err(AccountError::UsernameTaken(_))
+        ));
+        assert_eq!(accounts.credentials("alice"), Some((uuid, String::from("hash"))));
+        assert_eq!(accounts.credentials("ALICE"), None);
+        // and it was saved
+        let reloaded = Accounts::load(accounts.path.clone()).unwrap();
+        assert_eq!(reloaded.credentials("alice"), Some((uuid, String::from("hash"))));
+        std::fs::remove_file(&accounts.path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn accounts_that_could_not_be_saved_are_not_added() {
+        let accounts = Accounts::load(std::env::temp_dir().join("fracture-test-missing-dir/x.accounts.json")).unwrap();
+        assert!(matches!(
+            accounts.insert(String::from("bob"), String::from("hash")).await,
+            Err(AccountError::Io { .. })
+        ));
+        assert_eq!(accounts.credentials("bob"), None);
+    }
+}
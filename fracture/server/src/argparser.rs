@@ -10,6 +10,12 @@ use std::path::PathBuf;
 use clap::{Parser, ArgSettings, Subcommand};
 use serde::{Serialize, Deserialize};
 
+use fracture_core::heartbeat::HeartbeatSettings;
+
+use crate::names::NameRules;
+use crate::connections::ConnectionLimits;
+use crate::ratelimit::RateLimits;
+
 
 /// this should always be a valid u16
 const DEFAULT_PORT: &str = "56282";
@@ -18,6 +24,48 @@ const DEFAULT_LOGGER_LEVEL: &str = "info";
 
 const DEFAULT_LOGGER_COLORMODE: &str = "auto";//auto, never, or always
 
+/// this should always be the same as `fracture_config::core::DEFAULT_MAX_MESSAGE_SIZE`
+const DEFAULT_MAX_MESSAGE_SIZE: &str = "65536";
+
+const DEFAULT_MODERATORS: &str = "";
+const DEFAULT_ADMINS: &str = "";
+
+const DEFAULT_MIN_NAME_LENGTH: &str = "1";
+
+const DEFAULT_MAX_NAME_LENGTH: &str = "32";
+
+const DEFAULT_NAME_CHARSET: &str = "any";//any or simple
+
+const DEFAULT_RESERVED_NAMES: &str = "server";
+
+const DEFAULT_NAME_COLLISION: &str = "refuse";//refuse or suffix
+
+/// these should always be the same as `RateLimits::default()`
+const DEFAULT_MESSAGES_PER_SECOND: &str = "5";
+
+const DEFAULT_MESSAGE_BURST: &str = "10";
+
+const DEFAULT_BYTES_PER_SECOND: &str = "32768";
+
+const DEFAULT_BYTE_BURST: &str = "131072";
+
+const DEFAULT_MAX_RATE_VIOLATIONS: &str = "5";
+
+/// these should always be the same as `ConnectionLimits::default()`
+const DEFAULT_MAX_CLIENTS: &str = "256";
+
+const DEFAULT_MAX_CLIENTS_PER_IP: &str = "8";
+
+const DEFAULT_HANDSHAKE_TIMEOUT: &str = "60";
+
+/// these should always be the same as `fracture_config::core::HEARTBEAT_INTERVAL_SECS` and `HEARTBEAT_MAX_MISSED`
+const DEFAULT_HEARTBEAT_INTERVAL: &str = "10";
+
+const DEFAULT_HEARTBEAT_MAX_MISSED: &str = "3";
+
+/// where the server keeps its files (TLS identity, accounts, history, bans) when not launching from a config file, relative to the current directory
+const DEFAULT_DATA_PATH: &str = "fracture-server";
+
 const ABOUT: &str = "A disruptively terrible chat app that cracks bad jokes";
 
 /// Shown when you do -h
@@ -37,6 +85,7 @@ pub struct CLI {
 }
 
 #[derive(Subcommand, Debug, Clone)]
+#[allow(clippy::large_enum_variant)] // there is only ever one of these, so the size does not matter
 pub enum Subcommands {
     Launch {
         #[clap(short, long)]
@@ -66,6 +115,117 @@ pub enum Subcommands {
         #[clap(setting(ArgSettings::TakesValue))]
         logger_colormode: String,
 
+        #[clap(short, long, default_value = DEFAULT_MAX_MESSAGE_SIZE)]
+        #[clap(help = "the largest message (in bytes) a client may send. clients sending larger messages are disconnected")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        max_message_size: String,
+
+        #[clap(short, long, parse(from_os_str))]
+        #[clap(help = "also accept clients on a unix domain socket at this path (unix only)")]
+        unix_socket: Option<PathBuf>,
+
+        #[clap(short, long)]
+        #[clap(help = "encrypt connections with TLS. a self signed certificate is generated on first launch, and saved next to the config file (or in the current directory)")]
+        tls: bool,
+
+        #[clap(long)]
+        #[clap(help = "require clients to enter this password to join")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        password: Option<String>,
+
+        #[clap(long)]
+        #[clap(help = "require clients to log in to (or register) a account. accounts are saved next to the config file (or in the current directory)")]
+        accounts: bool,
+
+        #[clap(long)]
+        #[clap(help = "save the history of what is said in channels, so it is kept when the server restarts. history is saved next to the config file (or in the current directory)")]
+        history: bool,
+
+        #[clap(long, default_value = DEFAULT_MODERATORS)]
+        #[clap(help = "comma separated names of users who can edit and delete anyones messages. only used with --accounts")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        moderators: String,
+
+        #[clap(long, default_value = DEFAULT_ADMINS)]
+        #[clap(help = "comma separated names of users who can kick, mute and ban people (and do anything moderators can). only used with --accounts")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        admins: String,
+
+        #[clap(long, default_value = DEFAULT_MIN_NAME_LENGTH)]
+        #[clap(help = "the shortest name a client may use")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        min_name_length: String,
+
+        #[clap(long, default_value = DEFAULT_MAX_NAME_LENGTH)]
+        #[clap(help = "the longest name a client may use")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        max_name_length: String,
+
+        #[clap(long, default_value = DEFAULT_NAME_CHARSET)]
+        #[clap(help = "what characters names may contain. can be any (anything but control characters and surrounding spaces) or simple (letters, numbers, _, - and .)")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        name_charset: String,
+
+        #[clap(long, default_value = DEFAULT_RESERVED_NAMES)]
+        #[clap(help = "comma separated names that nobody may use (ignoring case)")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        reserved_names: String,
+
+        #[clap(long, default_value = DEFAULT_NAME_COLLISION)]
+        #[clap(help = "what to do when a client uses a name that is already taken. can be refuse (refuse the connection) or suffix (name them something like name#2)")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        name_collision: String,
+
+        #[clap(long, default_value = DEFAULT_MESSAGES_PER_SECOND)]
+        #[clap(help = "how many messages a client may send per second, on average. 0 for no limit")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        messages_per_second: String,
+
+        #[clap(long, default_value = DEFAULT_MESSAGE_BURST)]
+        #[clap(help = "how many messages a client may send at once, after not sending anything for a while")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        message_burst: String,
+
+        #[clap(long, default_value = DEFAULT_BYTES_PER_SECOND)]
+        #[clap(help = "how many bytes a client may send per second, on average. 0 for no limit")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        bytes_per_second: String,
+
+        #[clap(long, default_value = DEFAULT_BYTE_BURST)]
+        #[clap(help = "how many bytes a client may send at once, after not sending anything for a while")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        byte_burst: String,
+
+        #[clap(long, default_value = DEFAULT_MAX_RATE_VIOLATIONS)]
+        #[clap(help = "how many times a client is warned for sending too fast before it is disconnected")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        max_rate_violations: String,
+
+        #[clap(long, default_value = DEFAULT_MAX_CLIENTS)]
+        #[clap(help = "the most clients that can be connected at once. 0 for no limit")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        max_clients: String,
+
+        #[clap(long, default_value = DEFAULT_MAX_CLIENTS_PER_IP)]
+        #[clap(help = "the most clients that can be connected at once from one IP address. 0 for no limit")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        max_clients_per_ip: String,
+
+        #[clap(long, default_value = DEFAULT_HANDSHAKE_TIMEOUT)]
+        #[clap(help = "seconds a client has to finish connecting (and logging in) before it is disconnected")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        handshake_timeout: String,
+
+        #[clap(long, default_value = DEFAULT_HEARTBEAT_INTERVAL)]
+        #[clap(help = "seconds between pings sent to clients to check they are still there")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        heartbeat_interval: String,
+
+        #[clap(long, default_value = DEFAULT_HEARTBEAT_MAX_MISSED)]
+        #[clap(help = "how many pings a client can miss before it is disconnected")]
+        #[clap(setting(ArgSettings::TakesValue))]
+        heartbeat_max_missed: String,
+
         #[clap(short, long, help = "save the current args to a config file and exit.", parse(from_os_str))]
         save: Option<PathBuf>,
     },
@@ -81,6 +241,11 @@ pub enum Subcommands {
 pub enum ParserErr {
     BadPort(ParseIntError),
     BadAddr(AddrParseError),
+    InvalidMaxMessageSize(ParseIntError),
+    InvalidNameRules(String),
+    InvalidRateLimits(String),
+    InvalidConnectionLimits(String),
+    InvalidHeartbeat(String),
 }
 
 impl From<AddrParseError> for ParserErr {
@@ -101,6 +266,20 @@ pub struct ParsedArgs {
     pub full_addr: SocketAddrV4,
     pub log_style: String,
     pub log_level: String,
+    pub max_message_size: usize,
+    pub unix_socket: Option<PathBuf>,
+    pub tls: bool,
+    /// base path for the files the server keeps (TLS identity, accounts, history, bans), without an extension
+    pub data_path: PathBuf,
+    pub password: Option<String>,
+    pub accounts: bool,
+    pub history: bool,
+    pub moderators: Vec<String>,
+    pub admins: Vec<String>,
+    pub name_rules: NameRules,
+    pub rate_limits: RateLimits,
+    pub connection_limits: ConnectionLimits,
+    pub heartbeat: HeartbeatSettings,
 }
 
 impl TryFrom<SemiParsedArgs> for ParsedArgs {
@@ -109,13 +288,26 @@ impl TryFrom<SemiParsedArgs> for ParsedArgs {
     /// Converts to ParsedArgs, failing if if the variant is `SemiParsedArgs::LoadLaunch`
     fn try_from(value: SemiParsedArgs) -> Result<Self, Self::Error> {
         match value {
-            SemiParsedArgs::Launch {name, addr, log_level, log_style, save: _} => {
+            SemiParsedArgs::Launch {name, addr, log_level, log_style, max_message_size, unix_socket, tls, password, accounts, history, moderators, admins, name_rules, rate_limits, connection_limits, heartbeat, save: _} => {
                 Ok(
                     ParsedArgs {
                         name,
                         full_addr: addr,
                         log_style,
-                        log_level
+                        log_level,
+                        max_message_size,
+                        unix_socket,
+                        tls,
+                        data_path: PathBuf::from(DEFAULT_DATA_PATH),
+                        password,
+                        accounts,
+                        history,
+                        moderators,
+                        admins,
+                        name_rules,
+                        rate_limits,
+                        connection_limits,
+                        heartbeat,
                     }
                 )
             }
@@ -127,12 +319,25 @@ impl TryFrom<SemiParsedArgs> for ParsedArgs {
 }
 
 #[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)] // there is only ever one of these, so the size does not matter
 pub enum SemiParsedArgs {
     Launch {
         name: String,
         addr: SocketAddrV4,
         log_level: String,//parsed later
         log_style: String,//also parsed later
+        max_message_size: usize,
+        unix_socket: Option<PathBuf>,
+        tls: bool,
+        password: Option<String>,
+        accounts: bool,
+        history: bool,
+        moderators: Vec<String>,
+        admins: Vec<String>,
+        name_rules: NameRules,
+        rate_limits: RateLimits,
+        connection_limits: ConnectionLimits,
+        heartbeat: HeartbeatSettings,
         save: Option<PathBuf>,
     },
     LoadLaunch {
@@ -145,9 +350,35 @@ impl TryFrom<CLI> for SemiParsedArgs {
 
     fn try_from(args: CLI) -> Result<Self, Self::Error> {
         match args.command {
-            Subcommands::Launch {name, addr, port, logger_colormode, logger_level, save} => {
+            Subcommands::Launch {name, addr, port, logger_colormode, logger_level, max_message_size, unix_socket, tls, password, accounts, history, moderators, admins, min_name_length, max_name_length, name_charset, reserved_names, name_collision, messages_per_second, message_burst, bytes_per_second, byte_burst, max_rate_violations, max_clients, max_clients_per_ip, handshake_timeout, heartbeat_interval, heartbeat_max_missed, save} => {
                 let parsed_addr = addr.parse::<Ipv4Addr>()?;
                 let parsed_port = port.parse::<u16>()?;
+                let parsed_max_message_size = max_message_size.parse::<usize>().map_err(ParserErr::InvalidMaxMessageSize)?;
+                let moderators = moderators.split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect();
+                let admins = admins.split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect();
+                let name_rules = NameRules {
+                    min_length: min_name_length.parse().map_err(|err| ParserErr::InvalidNameRules(format!("bad minimum name length: {}", err)))?,
+                    max_length: max_name_length.parse().map_err(|err| ParserErr::InvalidNameRules(format!("bad maximum name length: {}", err)))?,
+                    charset: name_charset.parse().map_err(ParserErr::InvalidNameRules)?,
+                    reserved: reserved_names.split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect(),
+                    on_collision: name_collision.parse().map_err(ParserErr::InvalidNameRules)?,
+                };
+                let rate_limits = RateLimits {
+                    messages_per_second: messages_per_second.parse().map_err(|err| ParserErr::InvalidRateLimits(format!("bad messages per second: {}", err)))?,
+                    message_burst: message_burst.parse().map_err(|err| ParserErr::InvalidRateLimits(format!("bad message burst: {}", err)))?,
+                    bytes_per_second: bytes_per_second.parse().map_err(|err| ParserErr::InvalidRateLimits(format!("bad bytes per second: {}", err)))?,
+                    byte_burst: byte_burst.parse().map_err(|err| ParserErr::InvalidRateLimits(format!("bad byte burst: {}", err)))?,
+                    max_violations: max_rate_violations.parse().map_err(|err| ParserErr::InvalidRateLimits(format!("bad maximum rate violations: {}", err)))?,
+                };
+                let connection_limits = ConnectionLimits {
+                    max_clients: max_clients.parse().map_err(|err| ParserErr::InvalidConnectionLimits(format!("bad maximum clients: {}", err)))?,
+                    max_per_ip: max_clients_per_ip.parse().map_err(|err| ParserErr::InvalidConnectionLimits(format!("bad maximum clients per IP: {}", err)))?,
+                    handshake_timeout_secs: handshake_timeout.parse().map_err(|err| ParserErr::InvalidConnectionLimits(format!("bad handshake timeout: {}", err)))?,
+                };
+                let heartbeat = HeartbeatSettings {
+                    interval_secs: heartbeat_interval.parse().map_err(|err| ParserErr::InvalidHeartbeat(format!("bad interval: {}", err)))?,
+                    max_missed: heartbeat_max_missed.parse().map_err(|err| ParserErr::InvalidHeartbeat(format!("bad maximum missed pings: {}", err)))?,
+                };
                 Ok(
                     SemiParsedArgs::Launch {
                         name,
@@ -155,6 +386,18 @@ impl TryFrom<CLI> for SemiParsedArgs {
                         save,
                         log_level: logger_level,
                         log_style: logger_colormode,
+                        max_message_size: parsed_max_message_size,
+                        unix_socket,
+                        tls,
+                        password,
+                        accounts,
+                        history,
+                        moderators,
+                        admins,
+                        name_rules,
+                        rate_limits,
+                        connection_limits,
+                        heartbeat,
                     }
                 )
             }
@@ -177,6 +420,35 @@ pub struct Configuration {
     pub address: SocketAddrV4,
     pub log_level: String,//parsed later
     pub log_style: String,//also parsed later
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+    #[serde(default)]
+    pub unix_socket: Option<PathBuf>,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub accounts: bool,
+    #[serde(default)]
+    pub history: bool,
+    #[serde(default)]
+    pub moderators: Vec<String>,
+    #[serde(default)]
+    pub admins: Vec<String>,
+    #[serde(default)]
+    pub name_rules: NameRules,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    #[serde(default)]
+    pub connection_limits: ConnectionLimits,
+    #[serde(default)]
+    pub heartbeat: HeartbeatSettings,
+}
+
+/// for loading configurations saved before `max_message_size` existed
+const fn default_max_message_size() -> usize {
+    fracture_config::core::DEFAULT_MAX_MESSAGE_SIZE
 }
 
 impl From<ParsedArgs> for Configuration {
@@ -186,6 +458,18 @@ impl From<ParsedArgs> for Configuration {
             address: args.full_addr,
             log_level: args.log_level,
             log_style: args.log_style,
+            max_message_size: args.max_message_size,
+            unix_socket: args.unix_socket,
+            tls: args.tls,
+            password: args.password,
+            accounts: args.accounts,
+            history: args.history,
+            moderators: args.moderators,
+            admins: args.admins,
+            name_rules: args.name_rules,
+            rate_limits: args.rate_limits,
+            connection_limits: args.connection_limits,
+            heartbeat: args.heartbeat,
         }
     }
 }
@@ -197,6 +481,19 @@ impl From<Configuration> for ParsedArgs {
             full_addr: conf.address,
             log_level: conf.log_level,
             log_style: conf.log_style,
+            max_message_size: conf.max_message_size,
+            unix_socket: conf.unix_socket,
+            tls: conf.tls,
+            data_path: PathBuf::from(DEFAULT_DATA_PATH),
+            password: conf.password,
+            accounts: conf.accounts,
+            history: conf.history,
+            moderators: conf.moderators,
+            admins: conf.admins,
+            name_rules: conf.name_rules,
+            rate_limits: conf.rate_limits,
+            connection_limits: conf.connection_limits,
+            heartbeat: conf.heartbeat,
         }
     }
 }
@@ -234,12 +531,28 @@ impl TryFrom<Configuration> for String {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
 pub enum GetArgsError {
+    #[error("Could not read or write the configuration file: {0}")]
     FileError(std::io::Error),
+    #[error("Invalid configuration: {0}")]
     Serialization(serde_json::Error),
+    #[error("Invalid address: {0}")]
     InvalidAddr(AddrParseError),
+    #[error("Invalid port: {0}")]
     InvalidPort(ParseIntError),
+    #[error("Invalid max message size: {0}")]
+    InvalidMaxMessageSize(ParseIntError),
+    #[error("Invalid name rules: {0}")]
+    InvalidNameRules(String),
+    #[error("Invalid rate limits: {0}")]
+    InvalidRateLimits(String),
+    #[error("Invalid connection limits: {0}")]
+    InvalidConnectionLimits(String),
+    #[error("Invalid heartbeat settings: {0}")]
+    InvalidHeartbeat(String),
+    /// nothing went wrong, but the server should not be launched (like after saving a configuration)
+    #[error("Exiting")]
     Exit,
 }
 
@@ -256,10 +569,30 @@ pub fn get_args() -> Result<ParsedArgs, GetArgsError> {
                 eprintln!("Invalid port!");
                 return Err(GetArgsError::InvalidPort(port_err));
             }
+            ParserErr::InvalidMaxMessageSize(size_err) => {
+                eprintln!("Invalid max message size!");
+                return Err(GetArgsError::InvalidMaxMessageSize(size_err));
+            }
+            ParserErr::InvalidNameRules(rules_err) => {
+                eprintln!("Invalid name rules: {}", rules_err);
+                return Err(GetArgsError::InvalidNameRules(rules_err));
+            }
+            ParserErr::InvalidRateLimits(limits_err) => {
+                eprintln!("Invalid rate limits: {}", limits_err);
+                return Err(GetArgsError::InvalidRateLimits(limits_err));
+            }
+            ParserErr::InvalidConnectionLimits(limits_err) => {
+                eprintln!("Invalid connection limits: {}", limits_err);
+                return Err(GetArgsError::InvalidConnectionLimits(limits_err));
+            }
+            ParserErr::InvalidHeartbeat(heartbeat_err) => {
+                eprintln!("Invalid heartbeat settings: {}", heartbeat_err);
+                return Err(GetArgsError::InvalidHeartbeat(heartbeat_err));
+            }
         }
     };
     let args = match semi_parsed {
-        SemiParsedArgs::Launch {name, addr, log_level, log_style, save} => {
+        SemiParsedArgs::Launch {name, addr, log_level, log_style, max_message_size, unix_socket, tls, password, accounts, history, moderators, admins, name_rules, rate_limits, connection_limits, heartbeat, save} => {
             match save {
                 None => {
                     ParsedArgs {
@@ -267,6 +600,19 @@ pub fn get_args() -> Result<ParsedArgs, GetArgsError> {
                         full_addr: addr,
                         log_level,
                         log_style,
+                        max_message_size,
+                        unix_socket,
+                        tls,
+                        data_path: PathBuf::from(DEFAULT_DATA_PATH),
+                        password,
+                        accounts,
+                        history,
+                        moderators,
+                        admins,
+                        name_rules,
+                        rate_limits,
+                        connection_limits,
+                        heartbeat,
                     }
                 }
                 Some(path) => {
@@ -286,6 +632,19 @@ pub fn get_args() -> Result<ParsedArgs, GetArgsError> {
                         full_addr: addr,
                         log_level,
                         log_style,
+                        max_message_size,
+                        unix_socket,
+                        tls,
+                        data_path: PathBuf::from(DEFAULT_DATA_PATH),
+                        password,
+                        accounts,
+                        history,
+                        moderators,
+                        admins,
+                        name_rules,
+                        rate_limits,
+                        connection_limits,
+                        heartbeat,
                     }.into();
                     let serialize_res: Result<String, serde_json::Error> = config.try_into();
                     let json_config = match serialize_res {
@@ -336,7 +695,11 @@ pub fn get_args() -> Result<ParsedArgs, GetArgsError> {
                     return Err(GetArgsError::Serialization(error));
                 }
             };
-            conf.into()
+            ParsedArgs {
+                // keep the servers files next to the config they belong to
+                data_path: path.with_extension(""),
+                ..conf.into()
+            }
         }
     };
     Ok(args)
@@ -0,0 +1,183 @@
+/// Chat channels, and who is in them
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use fracture_core::msg::types::ChannelInfo;
+
+/// longest name a channel can have
+const MAX_CHANNEL_NAME_LENGTH: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChannelError {
+    #[error("There is no channel named {0:?}")]
+    NoSuchChannel(String),
+    #[error("There is already a channel named {0:?}")]
+    AlreadyExists(String),
+    #[error("Channel names must be 1 to 32 letters, numbers, - or _")]
+    InvalidName,
+    #[error("You are not in the channel {0:?}")]
+    NotAMember(String),
+    #[error("Channel topics can be at most {} characters", crate::conf::MAX_TOPIC_LENGTH)]
+    TopicTooLong,
+    #[error("There are too many channels already")]
+    TooManyChannels,
+}
+
+#[derive(Debug, Default)]
+struct Channel {
+    topic: Option<String>,
+    members: HashSet<Uuid>,
+}
+
+/// Every channel on the server. clones share the same channels
+#[derive(Clone, Debug)]
+pub struct ChannelRegistry {
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    /// everyone joins this channel when they connect
+    default_channel: String,
+}
+
+impl ChannelRegistry {
+    pub fn new(default_channel: &str) -> Self {
+        let mut channels = HashMap::new();
+        channels.insert(default_channel.to_string(), Channel::default());
+        Self {
+            channels: Arc::new(Mutex::new(channels)),
+            default_channel: default_channel.to_string(),
+        }
+    }
+
+    pub fn default_channel(&self) -> &str {
+        &self.default_channel
+    }
+
+    /// Make a new channel. this does not join it
+    pub fn create(&self, name: &str, topic: Option<String>) -> Result<(), ChannelError> {
+        if name.is_empty()
+            || name.len() > MAX_CHANNEL_NAME_LENGTH
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        {
+            return Err(ChannelError::InvalidName);
+        }
+        if topic
+            .as_ref()
+            .is_some_and(|topic| topic.chars().count() > crate::conf::MAX_TOPIC_LENGTH)
+        {
+            return Err(ChannelError::TopicTooLong);
+        }
+        let mut channels = self.channels.lock();
+        if channels.contains_key(name) {
+            return Err(ChannelError::AlreadyExists(name.to_string()));
+        }
+        if channels.len() >= crate::conf::MAX_CHANNELS {
+            return Err(ChannelError::TooManyChannels);
+        }
+        channels.insert(
+            name.to_string(),
+            Channel {
+                topic,
+                members: HashSet::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Add `uuid` to a channel, returning the channels topic
+    pub fn join(&self, name: &str, uuid: Uuid) -> Result<Option<String>, ChannelError> {
+        let mut channels = self.channels.lock();
+        let channel = channels
+            .get_mut(name)
+            .ok_or_else(|| ChannelError::NoSuchChannel(name.to_string()))?;
+        channel.members.insert(uuid);
+        Ok(channel.topic.clone())
+    }
+
+    pub fn leave(&self, name: &str, uuid: Uuid) -> Result<(), ChannelError> {
+        let mut channels = self.channels.lock();
+        let channel = channels
+            .get_mut(name)
+            .ok_or_else(|| ChannelError::NoSuchChannel(name.to_string()))?;
+        if channel.members.remove(&uuid) {
+            Ok(())
+        } else {
+            Err(ChannelError::NotAMember(name.to_string()))
+        }
+    }
+
+    /// Remove `uuid` from every channel, for when it disconnects
+    pub fn leave_all(&self, uuid: Uuid) {
+        for channel in self.channels.lock().values_mut() {
+            channel.members.remove(&uuid);
+        }
+    }
+
+    pub fn is_member(&self, name: &str, uuid: Uuid) -> bool {
+        self.channels
+            .lock()
+            .get(name)
+            .is_some_and(|channel| channel.members.contains(&uuid))
+    }
+
+    /// Every channel, in order of name, as many as take up at most `max_bytes` encoded
+    pub fn list(&self, max_bytes: usize) -> Vec<ChannelInfo> {
+        let mut list: Vec<ChannelInfo> = self
+            .channels
+            .lock()
+            .iter()
+            .map(|(name, channel)| ChannelInfo {
+                name: name.clone(),
+                topic: channel.topic.clone(),
+                members: channel.members.len(),
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut used: usize = 0;
+        let fitting = list
+            .iter()
+            .take_while(|channel| {
+                let size = bincode::serialized_size(channel).map_or(usize::MAX, |size| size as usize);
+                used = used.saturating_add(size);
+                used <= max_bytes
+            })
+            .count();
+        if fitting < list.len() {
+            warn!("Only {} of {} channels fit in a channel list", fitting, list.len());
+            list.truncate(fitting);
+        }
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_are_checked_when_created() {
+        let channels = ChannelRegistry::new("general");
+        assert!(matches!(channels.create("two words", None), Err(ChannelError::InvalidName)));
+        assert!(matches!(channels.create("general", None), Err(ChannelError::AlreadyExists(_))));
+        let topic = "x".repeat(crate::conf::MAX_TOPIC_LENGTH + 1);
+        assert!(matches!(channels.create("long", Some(topic)), Err(ChannelError::TopicTooLong)));
+        for number in 1..crate::conf::MAX_CHANNELS {
+            channels.create(&number.to_string(), None).unwrap();
+        }
+        assert!(matches!(channels.create("more", None), Err(ChannelError::TooManyChannels)));
+    }
+
+    #[test]
+    fn lists_only_have_the_channels_that_fit() {
+        let channels = ChannelRegistry::new("a");
+        channels.create("b", Some("x".repeat(100))).unwrap();
+        channels.create("c", None).unwrap();
+        let names = |list: Vec<ChannelInfo>| list.into_iter().map(|channel| channel.name).collect::<Vec<_>>();
+        assert_eq!(names(channels.list(usize::MAX)), vec!["a", "b", "c"]);
+        assert_eq!(names(channels.list(100)), vec!["a"]);
+        assert!(channels.list(0).is_empty());
+    }
+}
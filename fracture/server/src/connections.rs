@@ -0,0 +1,115 @@
+/// Limits on how many clients can be connected, so no one can use up all of the servers connections
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use fracture_core::transport::PeerAddr;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionLimits {
+    /// most clients connected at once, 0 for no limit
+    pub max_clients: usize,
+    /// most clients connected at once from one IP address, 0 for no limit. unix socket clients are not counted
+    pub max_per_ip: usize,
+    /// seconds a client has to get through connecting (and logging in), before it is disconnected
+    pub handshake_timeout_secs: u64,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_clients: 256,
+            max_per_ip: 8,
+            handshake_timeout_secs: 60,
+        }
+    }
+}
+
+impl ConnectionLimits {
+    pub const fn handshake_timeout(&self) -> Duration {
+        Duration::from_secs(self.handshake_timeout_secs)
+    }
+}
+
+/// Why a connection was turned away
+#[derive(Debug, thiserror::Error)]
+pub enum LimitError {
+    #[error("The server is full ({0} people are connected), please try again later")]
+    ServerFull(usize),
+    #[error("Too many connections from your address ({0} already), please close one and try again")]
+    TooManyFromAddress(usize),
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// Counts the open connections. clones share the same counts
+#[derive(Clone, Debug)]
+pub struct Connections {
+    limits: ConnectionLimits,
+    counts: Arc<Mutex<Counts>>,
+}
+
+impl Connections {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        Self {
+            limits,
+            counts: Arc::new(Mutex::new(Counts::default())),
+        }
+    }
+
+    pub const fn limits(&self) -> &ConnectionLimits {
+        &self.limits
+    }
+
+    /// Count a new connection from `addr`, if there is room for it. it stops being counted when the slot is dropped
+    pub fn open(&self, addr: &PeerAddr) -> Result<ConnectionSlot, LimitError> {
+        let mut counts = self.counts.lock();
+        if self.limits.max_clients != 0 && counts.total >= self.limits.max_clients {
+            return Err(LimitError::ServerFull(counts.total));
+        }
+        let ip = addr.ip();
+        if let Some(ip) = ip {
+            let from_ip = counts.per_ip.get(&ip).copied().unwrap_or(0);
+            if self.limits.max_per_ip != 0 && from_ip >= self.limits.max_per_ip {
+                return Err(LimitError::TooManyFromAddress(from_ip));
+            }
+            counts.per_ip.insert(ip, from_ip + 1);
+        }
+        counts.total += 1;
+        Ok(ConnectionSlot {
+            counts: self.counts.clone(),
+            ip,
+        })
+    }
+}
+
+/// A counted connection, which stops being counted when this is dropped
+#[derive(Debug)]
+pub struct ConnectionSlot {
+    counts: Arc<Mutex<Counts>>,
+    ip: Option<IpAddr>,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock();
+        counts.total -= 1;
+        if let Some(ip) = self.ip {
+            if let Some(from_ip) = counts.per_ip.get_mut(&ip) {
+                *from_ip -= 1;
+                if *from_ip == 0 {
+                    counts.per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
@@ -10,8 +10,13 @@ pub fn get_default(
 {
     vec![
         // modules::test_handler::TestHandler::new(),
+        // first, so it sees every message the client sends
+        modules::presence::PresenceHandler::new(),
         modules::impl_msg_all::MsgAllHandler::new(),
         modules::incoming_chat::IncomingChatHandler::new(),
         modules::user_info::UserInfoUpdateHandler::new(),
+        modules::channels::ChannelHandler::new(),
+        modules::typing::TypingHandler::new(),
+        modules::moderation::ModerationHandler::new(),
     ]
 }
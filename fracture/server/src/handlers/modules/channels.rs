@@ -0,0 +1,202 @@
+/// Joining, leaving, creating and listing channels, sending channel messages to the client if it is in the channel, and sending it channel history
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use crate::handlers::imports::{
+    ClientInfo, GlobalHandlerOperation, HandlerOperation, MessageHandler, ServerClientInfo,
+    ServerMessageHandler,
+};
+use crate::channels::ChannelError;
+use crate::history::MessageError;
+use fracture_core::msg::{Message, MessageVarient};
+
+pub struct ChannelHandler {
+    client_data: Option<ClientInfo>,
+    pending: Vec<HandlerOperation>,
+}
+
+impl ChannelHandler {
+    fn reply(&mut self, data: MessageVarient) {
+        self.pending.push(HandlerOperation::Client {
+            msg: Message { data },
+        });
+    }
+
+    /// Join a channel, telling the client if it worked or not
+    fn join(&mut self, dat: &ClientInfo, channel: String) {
+        match dat.shared.channels.join(&channel, dat.uuid) {
+            Ok(topic) => {
+                debug!("{} joined channel {:?}", dat.name, channel);
+                self.reply(MessageVarient::ChannelJoined {
+                    channel: channel.clone(),
+                    topic,
+                });
+                self.send_history(dat, channel, None, crate::conf::HISTORY_REPLAY_LENGTH);
+            }
+            Err(err) => self.reply(MessageVarient::ChannelError {
+                channel,
+                reason: err.to_string(),
+            }),
+        }
+    }
+
+    /// Send the client up to `limit` messages said in `channel` before `before`, as many as fit in one message
+    fn send_history(&mut self, dat: &ClientInfo, channel: String, before: Option<u64>, limit: usize) {
+        let room = Self::room_for_messages(
+            dat,
+            MessageVarient::History {
+                channel: channel.clone(),
+                messages: vec![],
+            },
+        )
+        .unwrap_or(0);
+        let messages = dat.shared.history.lock().page(&channel, before, limit, room);
+        self.reply(MessageVarient::History { channel, messages });
+    }
+
+    /// How many bytes of messages can be added to `data` before it is too large to send, None if it already is
+    fn room_for_messages(dat: &ClientInfo, data: MessageVarient) -> Option<usize> {
+        let empty = fracture_core::seri::serialize(&Message { data }).ok()?;
+        dat.shared.max_message_size.checked_sub(empty.size())
+    }
+}
+
+impl MessageHandler for ChannelHandler {
+    type Operation = HandlerOperation;
+
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            client_data: None,
+            pending: vec![],
+        })
+    }
+
+    fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
+        // not ready until it knows who the client is
+        let Some(dat) = self.client_data.clone() else {
+            return false;
+        };
+        match msg.data.clone() {
+            MessageVarient::JoinChannel { channel } => {
+                self.join(&dat, channel);
+            }
+            MessageVarient::LeaveChannel { channel } => {
+                match dat.shared.channels.leave(&channel, dat.uuid) {
+                    Ok(()) => self.reply(MessageVarient::ChannelLeft { channel }),
+                    Err(err) => self.reply(MessageVarient::ChannelError {
+                        channel,
+                        reason: err.to_string(),
+                    }),
+                }
+            }
+            MessageVarient::CreateChannel { channel, topic } => {
+                match dat.shared.channels.create(&channel, topic) {
+                    Ok(()) => {
+                        info!("{} created channel {:?}", dat.name, channel);
+                        self.join(&dat, channel);
+                    }
+                    Err(err) => self.reply(MessageVarient::ChannelError {
+                        channel,
+                        reason: err.to_string(),
+                    }),
+                }
+            }
+            MessageVarient::RequestHistory { channel, before, limit } => {
+                if dat.shared.channels.is_member(&channel, dat.uuid) {
+                    let limit = limit.min(crate::conf::MAX_HISTORY_PAGE) as usize;
+                    self.send_history(&dat, channel, before, limit);
+                } else {
+                    self.reply(MessageVarient::ChannelError {
+                        reason: ChannelError::NotAMember(channel.clone()).to_string(),
+                        channel,
+                    });
+                }
+            }
+            MessageVarient::RequestThread { id } => {
+                let thread = {
+                    let history = dat.shared.history.lock();
+                    history.thread(id).and_then(|(channel, parent)| {
+                        let empty = MessageVarient::Thread {
+                            channel: channel.clone(),
+                            parent: parent.clone(),
+                            replies: vec![],
+                        };
+                        // as many of the replies as fit in one message
+                        let room = Self::room_for_messages(&dat, empty).ok_or(MessageError::TooLarge)?;
+                        let replies = history.replies(id, crate::conf::MAX_THREAD_REPLIES, room);
+                        Ok((channel, parent, replies))
+                    })
+                };
+                match thread {
+                    // the thread is only for people who can see the channel it is in
+                    Ok((channel, _, _)) if !dat.shared.channels.is_member(&channel, dat.uuid) => {
+                        self.reply(MessageVarient::ThreadFailed {
+                            id,
+                            reason: ChannelError::NotAMember(channel).to_string(),
+                        });
+                    }
+                    Ok((channel, parent, replies)) => self.reply(MessageVarient::Thread {
+                        channel,
+                        parent,
+                        replies,
+                    }),
+                    Err(err) => self.reply(MessageVarient::ThreadFailed {
+                        id,
+                        reason: err.to_string(),
+                    }),
+                }
+            }
+            MessageVarient::ListChannels {} => {
+                let room = Self::room_for_messages(&dat, MessageVarient::ChannelList { channels: vec![] }).unwrap_or(0);
+                let channels = dat.shared.channels.list(room);
+                self.reply(MessageVarient::ChannelList { channels });
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_global_op(&mut self, op: &GlobalHandlerOperation) {
+        if let GlobalHandlerOperation::MsgChannel { channel, msg } = op {
+            let Some(dat) = self.client_data.as_ref() else {
+                return;
+            };
+            if dat.shared.channels.is_member(channel, dat.uuid) {
+                self.pending
+                    .push(HandlerOperation::Client { msg: msg.clone() });
+            }
+        }
+    }
+
+    fn get_global_operations(&mut self) -> Option<Vec<GlobalHandlerOperation>> {
+        None
+    }
+
+    fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.drain(0..).collect())
+        }
+    }
+
+    fn get_default_operations(&mut self) -> Vec<Self::Operation> {
+        vec![]
+    }
+}
+
+impl ServerClientInfo for ChannelHandler {
+    type ClientData = ClientInfo;
+
+    /// this is given once the client is connected, so it is put in the default channel here.
+    /// clients that resumed a session are left in the channels they were in, even if they left the default one
+    fn accept_client_data(&mut self, data: Self::ClientData) {
+        if !data.resumed {
+            let default_channel = data.shared.channels.default_channel().to_string();
+            self.join(&data, default_channel);
+        }
+        self.client_data = Some(data);
+    }
+}
+
+impl ServerMessageHandler for ChannelHandler {}
@@ -1,5 +1,5 @@
-/// Implement msg GlobalHandlerOperation::MsgAll
-/// when it recieves this, it will send that message to its associated client
+/// Implement msg GlobalHandlerOperation::MsgAll and GlobalHandlerOperation::MsgOne
+/// when it recieves these (and for MsgOne, the message is for this client), it will send that message to its associated client
 use crate::handlers::imports::{
     ClientInfo, GlobalHandlerOperation, HandlerOperation, MessageHandler, ServerClientInfo,
     ServerMessageHandler,
@@ -7,13 +7,17 @@ use crate::handlers::imports::{
 
 pub struct MsgAllHandler {
     pending: Vec<HandlerOperation>,
+    client_data: Option<ClientInfo>,
 }
 
 impl MessageHandler for MsgAllHandler {
     type Operation = HandlerOperation;
 
     fn new() -> Box<Self> {
-        Box::new(Self { pending: vec![] })
+        Box::new(Self {
+            pending: vec![],
+            client_data: None,
+        })
     }
 
     fn handle(&mut self, _: &fracture_core::msg::Message) -> bool {
@@ -21,10 +25,18 @@ impl MessageHandler for MsgAllHandler {
     }
 
     fn handle_global_op(&mut self, op: &GlobalHandlerOperation) {
-        #[allow(irrefutable_let_patterns)] //not a issue, will fix itself later
-        if let GlobalHandlerOperation::MsgAll { msg } = op {
-            self.pending
-                .push(HandlerOperation::Client { msg: msg.clone() });
+        match op {
+            GlobalHandlerOperation::MsgAll { msg } => {
+                self.pending
+                    .push(HandlerOperation::Client { msg: msg.clone() });
+            }
+            GlobalHandlerOperation::MsgOne { uuid, msg }
+                if self.client_data.as_ref().map(|dat| dat.uuid) == Some(*uuid) =>
+            {
+                self.pending
+                    .push(HandlerOperation::Client { msg: msg.clone() });
+            }
+            _ => {}
         }
     }
 
@@ -50,7 +62,9 @@ impl MessageHandler for MsgAllHandler {
 impl ServerClientInfo for MsgAllHandler {
     type ClientData = ClientInfo;
 
-    fn accept_client_data(&mut self, _data: Self::ClientData) {}
+    fn accept_client_data(&mut self, data: Self::ClientData) {
+        self.client_data = Some(data);
+    }
 }
 
 impl ServerMessageHandler for MsgAllHandler {}
@@ -5,37 +5,298 @@ use crate::handlers::imports::{
     ClientInfo, GlobalHandlerOperation, HandlerOperation, MessageHandler, ServerClientInfo,
     ServerMessageHandler,
 };
-use fracture_core::msg::MessageVarient::ClientSendChat;
+use crate::history::{timestamp_now, MessageError};
+use crate::sessions::Hold;
+use fracture_core::msg::MessageVarient::{
+    AddReaction, ChatChangeFailed, ChatDeleted, ChatEdited, ChatFailed, ClientSendChat,
+    ClientSendDirect, DeleteChat, EditChat, ReactionsChanged, RemoveReaction, ServerSendChat,
+    ServerSendDirect,
+};
 
 pub struct IncomingChatHandler {
+    pending: Vec<HandlerOperation>,
     pending_global: Vec<GlobalHandlerOperation>,
     client_data: Option<ClientInfo>,
 }
 
+impl IncomingChatHandler {
+    /// Tell everyone in the channel that the message `id` changed, or the client why it could not be changed
+    fn changed(
+        &mut self,
+        id: u64,
+        res: Result<String, MessageError>,
+        update: impl FnOnce(String) -> fracture_core::msg::MessageVarient,
+    ) {
+        match res {
+            Ok(channel) => self.pending_global.push(GlobalHandlerOperation::MsgChannel {
+                channel: channel.clone(),
+                msg: fracture_core::msg::Message {
+                    data: update(channel),
+                },
+            }),
+            Err(err) => self.pending.push(HandlerOperation::Client {
+                msg: fracture_core::msg::Message {
+                    data: ChatChangeFailed {
+                        id,
+                        reason: err.to_string(),
+                    },
+                },
+            }),
+        }
+    }
+
+    /// React to a message (or take the reaction back), telling everyone in its channel
+    fn react(&mut self, dat: &ClientInfo, message_id: u64, emoji: String, add: bool) {
+        let res = {
+            let mut history = dat.shared.history.lock();
+            // reactions are only for people who can see the message
+            match history.channel_of(message_id) {
+                Some(channel) if !dat.shared.channels.is_member(channel, dat.uuid) => {
+                    Err(crate::channels::ChannelError::NotAMember(channel.to_string()).to_string())
+                }
+                _ => history
+                    .react(message_id, emoji, dat.uuid.as_u128(), add)
+                    .map_err(|err| err.to_string()),
+            }
+        };
+        match res {
+            Ok(Some((channel, reactions))) => self.pending_global.push(GlobalHandlerOperation::MsgChannel {
+                channel: channel.clone(),
+                msg: fracture_core::msg::Message {
+                    data: ReactionsChanged {
+                        message_id,
+                        channel,
+                        reactions,
+                    },
+                },
+            }),
+            Ok(None) => {}
+            Err(reason) => self.pending.push(HandlerOperation::Client {
+                msg: fracture_core::msg::Message {
+                    data: ChatChangeFailed { id: message_id, reason },
+                },
+            }),
+        }
+    }
+
+    /// Why the client can not say anything, if it is muted
+    fn muted(dat: &ClientInfo) -> Option<String> {
+        dat.shared
+            .mutes
+            .remaining(dat.uuid)
+            .map(|left| format!("You are muted for {} more minutes", left.as_secs() / 60 + 1))
+    }
+
+    /// If `msg` is too large to send to anyone, which happens when a client sends something right at the size limit,
+    /// since more is added to it when it is passed on
+    fn too_large(dat: &ClientInfo, msg: &fracture_core::msg::Message) -> bool {
+        fracture_core::seri::serialize(msg).map_or(true, |serialized| serialized.size() > dat.shared.max_message_size)
+    }
+
+    /// Tell the client the message with `nonce` was not sent
+    fn fail(&mut self, nonce: u64, reason: String) {
+        self.pending.push(HandlerOperation::Client {
+            msg: fracture_core::msg::Message {
+                data: ChatFailed { nonce, reason },
+            },
+        });
+    }
+}
+
 impl MessageHandler for IncomingChatHandler {
     type Operation = HandlerOperation;
 
     fn new() -> Box<Self> {
         Box::new(Self {
+            pending: vec![],
             pending_global: vec![],
             client_data: None,
         })
     }
 
     fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
-        if let ClientSendChat { content } = msg.data.clone() {
-            trace!("Received message {}", content);
-            let dat = self.client_data.clone().unwrap();
-            self.pending_global.push(GlobalHandlerOperation::MsgAll {
+        // not ready until it knows who the client is
+        let Some(dat) = self.client_data.clone() else {
+            return false;
+        };
+        if let ClientSendChat { content, channel, nonce, parent } = msg.data.clone() {
+            trace!("Received message {} in {}", content, channel);
+            if let Some(reason) = Self::muted(&dat) {
+                self.fail(nonce, reason);
+                return true;
+            }
+            if !dat.shared.channels.is_member(&channel, dat.uuid) {
+                self.fail(nonce, crate::channels::ChannelError::NotAMember(channel).to_string());
+                return true;
+            }
+            let timestamp = timestamp_now();
+            let (id, parent) = {
+                // the id is taken while holding the history, so it is saved in order
+                let mut history = dat.shared.history.lock();
+                let parent = match parent.map(|parent| history.parent(parent, &channel)).transpose() {
+                    Ok(parent) => parent,
+                    Err(err) => {
+                        self.fail(nonce, format!("Can not reply to that message: {}", err));
+                        return true;
+                    }
+                };
+                let chat = fracture_core::msg::Message {
+                    data: ServerSendChat {
+                        id: 0,
+                        timestamp,
+                        content: content.clone(),
+                        author: dat.name.clone(),
+                        author_uuid: dat.uuid.as_u128(),
+                        channel: channel.clone(),
+                        nonce: Some(nonce),
+                        parent: parent.clone(),
+                    },
+                };
+                // ids are always encoded the same size, so it does not matter that this one is not real
+                if Self::too_large(&dat, &chat) {
+                    self.fail(nonce, String::from("Your message is too long"));
+                    return true;
+                }
+                let id = dat.shared.message_ids.next();
+                history.append(
+                    id,
+                    timestamp,
+                    channel.clone(),
+                    content.clone(),
+                    dat.name.clone(),
+                    dat.uuid.as_u128(),
+                    parent.as_ref().map(|parent| parent.id),
+                );
+                (id, parent)
+            };
+            self.pending_global.push(GlobalHandlerOperation::MsgChannel {
+                channel: channel.clone(),
                 msg: fracture_core::msg::Message {
-                    data: fracture_core::msg::MessageVarient::ServerSendChat {
+                    // the author is in the channel, so this is also its copy
+                    data: ServerSendChat {
+                        id,
+                        timestamp,
                         content,
                         author: dat.name,
                         author_uuid: dat.uuid.as_u128(),
+                        channel,
+                        nonce: Some(nonce),
+                        parent,
                     },
                 },
             });
             true
+        } else if let ClientSendDirect { to, content, nonce } = msg.data.clone() {
+            if let Some(reason) = Self::muted(&dat) {
+                self.fail(nonce, reason);
+                return true;
+            }
+            let recipient = uuid::Uuid::from_u128(to);
+            trace!("Received direct message {} for {}", content, recipient);
+            if !dat.shared.tracker.is_connected(recipient) {
+                self.fail(nonce, String::from("That user is not online"));
+                return true;
+            }
+            // direct messages are not kept, but their ids still have to be saved so they are not used again
+            let id = {
+                let mut history = dat.shared.history.lock();
+                let id = dat.shared.message_ids.next();
+                history.reserve(id);
+                id
+            };
+            let timestamp = timestamp_now();
+            let direct = |nonce| fracture_core::msg::Message {
+                data: ServerSendDirect {
+                    id,
+                    timestamp,
+                    content: content.clone(),
+                    author: dat.name.clone(),
+                    author_uuid: dat.uuid.as_u128(),
+                    to,
+                    nonce,
+                },
+            };
+            if Self::too_large(&dat, &direct(Some(nonce))) {
+                self.fail(nonce, String::from("Your message is too long"));
+                return true;
+            }
+            // a note to yourself only needs the authors copy
+            if recipient != dat.uuid {
+                match dat.shared.sessions.hold(recipient, direct(None)) {
+                    Hold::NotDetached => self.pending_global.push(GlobalHandlerOperation::MsgOne {
+                        uuid: recipient,
+                        msg: direct(None),
+                    }),
+                    Hold::Held => debug!("{} lost their connection, keeping a direct message for them", recipient),
+                    Hold::Full => {
+                        self.fail(nonce, String::from("That user lost their connection, try again once they are back"));
+                        return true;
+                    }
+                }
+            }
+            // the author does not get `MsgOne`s for other clients, so it gets its copy directly
+            self.pending.push(HandlerOperation::Client { msg: direct(Some(nonce)) });
+            true
+        } else if let EditChat { id, content } = msg.data.clone() {
+            // or they could keep talking by editing old messages
+            if let Some(reason) = Self::muted(&dat) {
+                self.pending.push(HandlerOperation::Client {
+                    msg: fracture_core::msg::Message {
+                        data: ChatChangeFailed { id, reason },
+                    },
+                });
+                return true;
+            }
+            let edited_at = timestamp_now();
+            let channel = dat.shared.history.lock().channel_of(id).map(str::to_string).unwrap_or_default();
+            let edited = fracture_core::msg::Message {
+                data: ChatEdited {
+                    id,
+                    channel,
+                    content: content.clone(),
+                    edited_at,
+                },
+            };
+            if Self::too_large(&dat, &edited) {
+                self.pending.push(HandlerOperation::Client {
+                    msg: fracture_core::msg::Message {
+                        data: ChatChangeFailed {
+                            id,
+                            reason: String::from("Your message is too long"),
+                        },
+                    },
+                });
+                return true;
+            }
+            let res = dat.shared.history.lock().edit(
+                id,
+                content.clone(),
+                edited_at,
+                dat.uuid.as_u128(),
+                dat.shared.is_moderator(dat.uuid),
+            );
+            self.changed(id, res, |channel| ChatEdited {
+                id,
+                channel,
+                content,
+                edited_at,
+            });
+            true
+        } else if let DeleteChat { id } = msg.data.clone() {
+            let res = dat.shared.history.lock().delete(
+                id,
+                timestamp_now(),
+                dat.uuid.as_u128(),
+                dat.shared.is_moderator(dat.uuid),
+            );
+            self.changed(id, res, |channel| ChatDeleted { id, channel });
+            true
+        } else if let AddReaction { message_id, emoji } = msg.data.clone() {
+            self.react(&dat, message_id, emoji, true);
+            true
+        } else if let RemoveReaction { message_id, emoji } = msg.data.clone() {
+            self.react(&dat, message_id, emoji, false);
+            true
         } else {
             false
         }
@@ -54,7 +315,11 @@ impl MessageHandler for IncomingChatHandler {
     }
 
     fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
-        None
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.drain(0..).collect())
+        }
     }
 
     fn get_default_operations(&mut self) -> Vec<Self::Operation> {
@@ -1,5 +1,9 @@
+pub mod channels;
 pub mod impl_msg_all;
 pub mod incoming_chat;
+pub mod moderation;
+pub mod presence;
 /// All handlers go here
 pub mod test_handler;
+pub mod typing;
 pub mod user_info;
@@ -0,0 +1,196 @@
+/// Lets admins kick, mute and ban people.
+/// kicks are passed to the other clients interfaces (which do the disconnecting) as `GlobalHandlerOperation::Kick`
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::handlers::imports::{
+    ClientInfo, GlobalHandlerOperation, HandlerOperation, MessageHandler, ServerClientInfo,
+    ServerMessageHandler,
+};
+use crate::moderation::Ban;
+use crate::names::same_name;
+use fracture_core::handler::KickTarget;
+use fracture_core::msg::types::{ModerationAction, ServerDisconnectReason};
+use fracture_core::msg::{Message, MessageVarient};
+
+pub struct ModerationHandler {
+    pending: Vec<HandlerOperation>,
+    pending_global: Vec<GlobalHandlerOperation>,
+    client_data: Option<ClientInfo>,
+}
+
+impl ModerationHandler {
+    /// Do `action` to `target`, returning what happened or why it could not be done
+    fn moderate(&mut self, dat: &ClientInfo, target: &str, action: ModerationAction, reason: Option<String>) -> Result<String, String> {
+        if action != ModerationAction::Unban && same_name(target, &dat.name) {
+            return Err(String::from("You can not do that to yourself"));
+        }
+        let online = || {
+            dat.shared
+                .tracker
+                .find(target)
+                .ok_or_else(|| format!("{} is not online", target))
+        };
+        match action {
+            ModerationAction::Kick => {
+                self.kick(dat, KickTarget::User(online()?), ServerDisconnectReason::Kicked, reason, false);
+                info!("{} kicked {}", dat.name, target);
+                Ok(format!("Kicked {}", target))
+            }
+            ModerationAction::Mute { secs } => {
+                let uuid = online()?;
+                if secs < 60 {
+                    return Err(String::from("Mute them for at least a minute"));
+                }
+                if secs > crate::conf::MAX_MUTE_SECS {
+                    return Err(format!(
+                        "Mutes can be at most {} days, ban them instead",
+                        crate::conf::MAX_MUTE_SECS / (24 * 60 * 60)
+                    ));
+                }
+                dat.shared.mutes.mute(uuid, Duration::from_secs(secs));
+                info!("{} muted {} for {} seconds", dat.name, target, secs);
+                Ok(format!("Muted {} for {} minutes", target, secs.div_ceil(60)))
+            }
+            ModerationAction::Unmute => {
+                if dat.shared.mutes.unmute(online()?) {
+                    Ok(format!("Unmuted {}", target))
+                } else {
+                    Err(format!("{} is not muted", target))
+                }
+            }
+            ModerationAction::Ban { address: true } if target.parse::<IpAddr>().is_ok() => {
+                let ip = target.parse().unwrap();
+                let ban = Ban::new(None, dat.name.clone(), reason.clone());
+                dat.shared.bans.lock().ban_address(ip, ban).map_err(|err| {
+                    error!("Could not save the ban on {}: {}", ip, err);
+                    String::from("The ban could not be saved")
+                })?;
+                self.kick(dat, KickTarget::Address(ip), ServerDisconnectReason::Banned, reason, false);
+                info!("{} banned the address {}", dat.name, ip);
+                Ok(format!("Banned {}", ip))
+            }
+            ModerationAction::Ban { address } => {
+                // accounts keep their uuid, which is what makes banning them stick
+                let connected = dat.shared.tracker.find(target);
+                let uuid = match connected {
+                    Some(uuid) => uuid,
+                    None => dat
+                        .shared
+                        .accounts
+                        .as_ref()
+                        .and_then(|accounts| accounts.credentials(target))
+                        .map(|(uuid, _)| uuid)
+                        .ok_or_else(|| format!("There is no account called {}", target))?,
+                };
+                let ban = Ban::new(Some(target.to_string()), dat.name.clone(), reason.clone());
+                dat.shared.bans.lock().ban_account(uuid, ban).map_err(|err| {
+                    error!("Could not save the ban on {}: {}", target, err);
+                    String::from("The ban could not be saved")
+                })?;
+                self.kick(dat, KickTarget::User(uuid), ServerDisconnectReason::Banned, reason, address);
+                info!("{} banned {}", dat.name, target);
+                Ok(match (address, connected) {
+                    (false, _) => format!("Banned {}", target),
+                    (true, Some(_)) => format!("Banned {} and their address", target),
+                    (true, None) => format!("Banned {}, but they are not online so their address could not be banned", target),
+                })
+            }
+            ModerationAction::Unban => match dat.shared.bans.lock().unban(target) {
+                Ok(0) => Err(format!("{} is not banned", target)),
+                Ok(_) => {
+                    info!("{} unbanned {}", dat.name, target);
+                    Ok(format!("Unbanned {}", target))
+                }
+                Err(err) => {
+                    error!("Could not save the ban list: {}", err);
+                    Err(String::from("The ban list could not be saved"))
+                }
+            },
+        }
+    }
+
+    fn kick(&mut self, dat: &ClientInfo, target: KickTarget, reason: ServerDisconnectReason, why: Option<String>, ban_address: bool) {
+        self.pending_global.push(GlobalHandlerOperation::Kick {
+            target,
+            reason,
+            by: dat.name.clone(),
+            why,
+            ban_address,
+        });
+    }
+}
+
+impl MessageHandler for ModerationHandler {
+    type Operation = HandlerOperation;
+
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            pending: vec![],
+            pending_global: vec![],
+            client_data: None,
+        })
+    }
+
+    fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
+        if let MessageVarient::Moderate { target, action, reason } = msg.data.clone() {
+            // not ready until it knows who the client is
+            let Some(dat) = self.client_data.clone() else {
+                return false;
+            };
+            let res = if dat.shared.is_admin(dat.uuid) {
+                let reason = reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
+                self.moderate(&dat, target.trim(), action, reason)
+            } else {
+                Err(String::from("Only admins can do that"))
+            };
+            let (ok, message) = match res {
+                Ok(message) => (true, message),
+                Err(message) => (false, message),
+            };
+            self.pending.push(HandlerOperation::Client {
+                msg: Message {
+                    data: MessageVarient::ModerationResult { ok, message },
+                },
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    fn handle_global_op(&mut self, _op: &GlobalHandlerOperation) {}
+
+    fn get_global_operations(&mut self) -> Option<Vec<GlobalHandlerOperation>> {
+        if self.pending_global.is_empty() {
+            None
+        } else {
+            Some(self.pending_global.drain(0..).collect())
+        }
+    }
+
+    fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.drain(0..).collect())
+        }
+    }
+
+    fn get_default_operations(&mut self) -> Vec<Self::Operation> {
+        vec![]
+    }
+}
+
+impl ServerClientInfo for ModerationHandler {
+    type ClientData = ClientInfo;
+
+    fn accept_client_data(&mut self, data: Self::ClientData) {
+        self.client_data = Some(data);
+    }
+}
+
+impl ServerMessageHandler for ModerationHandler {}
@@ -0,0 +1,104 @@
+/// Keeps track of the clients status, and when it last did something.
+/// everyone is told about changes through the client tracker, as `ServerInfoUpdate`s, no more often than every `PRESENCE_CHANGE_SECS`
+use std::time::{Duration, Instant};
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use crate::conf::{MAX_STATUS_TEXT_LENGTH, PRESENCE_CHANGE_SECS};
+use crate::handlers::imports::{
+    ClientInfo, GlobalHandlerOperation, HandlerOperation, MessageHandler, ServerClientInfo,
+    ServerMessageHandler,
+};
+use fracture_core::msg::types::PresenceStatus;
+use fracture_core::msg::MessageVarient;
+
+pub struct PresenceHandler {
+    client_data: Option<ClientInfo>,
+    /// when the status was last changed
+    changed_at: Option<Instant>,
+    /// the newest change, if it came too soon after the last one to be passed on yet
+    held: Option<(PresenceStatus, Option<String>)>,
+}
+
+impl PresenceHandler {
+    /// Change the clients status now if it has not changed for `PRESENCE_CHANGE_SECS`, otherwise hold on to it until it has
+    fn change(&mut self, dat: &ClientInfo, status: PresenceStatus, text: Option<String>) {
+        if self
+            .changed_at
+            .is_some_and(|at| at.elapsed() < Duration::from_secs(PRESENCE_CHANGE_SECS))
+        {
+            self.held = Some((status, text));
+        } else {
+            self.held = None;
+            self.changed_at = Some(Instant::now());
+            dat.shared.tracker.set_presence(dat.uuid, status, text);
+        }
+    }
+}
+
+impl MessageHandler for PresenceHandler {
+    type Operation = HandlerOperation;
+
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            client_data: None,
+            changed_at: None,
+            held: None,
+        })
+    }
+
+    fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
+        // not ready until it knows who the client is
+        let Some(dat) = self.client_data.clone() else {
+            return false;
+        };
+        match &msg.data {
+            MessageVarient::SetPresence { status, text } => {
+                // clients go away on their own when the user is idle, which is not the user doing something
+                if *status != PresenceStatus::Away {
+                    dat.shared.tracker.seen(dat.uuid);
+                }
+                let text = text
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|text| !text.is_empty())
+                    .map(|text| text.chars().take(MAX_STATUS_TEXT_LENGTH).collect());
+                self.change(&dat, *status, text);
+                true
+            }
+            _ => {
+                dat.shared.tracker.seen(dat.uuid);
+                false
+            }
+        }
+    }
+
+    fn handle_global_op(&mut self, _op: &GlobalHandlerOperation) {}
+
+    fn get_global_operations(&mut self) -> Option<Vec<GlobalHandlerOperation>> {
+        None
+    }
+
+    fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
+        // this is asked for every update, so a held change is passed on soon after it can be
+        if let (Some(dat), Some((status, text))) = (self.client_data.clone(), self.held.clone()) {
+            self.change(&dat, status, text);
+        }
+        None
+    }
+
+    fn get_default_operations(&mut self) -> Vec<Self::Operation> {
+        vec![]
+    }
+}
+
+impl ServerClientInfo for PresenceHandler {
+    type ClientData = ClientInfo;
+
+    fn accept_client_data(&mut self, data: Self::ClientData) {
+        self.client_data = Some(data);
+    }
+}
+
+impl ServerMessageHandler for PresenceHandler {}
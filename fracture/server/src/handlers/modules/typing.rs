@@ -0,0 +1,147 @@
+/// Tells everyone in a channel (or the other person in a direct conversation) when the client is typing.
+/// nothing about this is kept, it is only passed on, and no more often than every `TYPING_FORWARD_SECS`
+use std::time::{Duration, Instant};
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use crate::handlers::imports::{
+    ClientInfo, GlobalHandlerOperation, HandlerOperation, MessageHandler, ServerClientInfo,
+    ServerMessageHandler,
+};
+use fracture_core::msg::types::TypingTarget;
+use fracture_core::msg::{Message, MessageVarient};
+
+pub struct TypingHandler {
+    pending: Vec<HandlerOperation>,
+    pending_global: Vec<GlobalHandlerOperation>,
+    client_data: Option<ClientInfo>,
+    /// where the client was last said to be typing, and when
+    typing_in: Vec<(TypingTarget, Instant)>,
+}
+
+impl TypingHandler {
+    /// If a change in where the client is typing should be passed on, remembering it if so.
+    /// starting again is only passed on every `TYPING_FORWARD_SECS`, and stopping only if starting was
+    fn should_forward(&mut self, target: &TypingTarget, typing: bool) -> bool {
+        let forward_every = Duration::from_secs(crate::conf::TYPING_FORWARD_SECS);
+        // everyone stops showing it by the time it would have been passed on twice
+        self.typing_in.retain(|(_, at)| at.elapsed() < forward_every * 2);
+        let index = self.typing_in.iter().position(|(typing_in, _)| typing_in == target);
+        match (index, typing) {
+            (Some(index), true) if self.typing_in[index].1.elapsed() < forward_every => false,
+            (Some(index), true) => {
+                self.typing_in[index].1 = Instant::now();
+                true
+            }
+            (None, true) => {
+                self.typing_in.push((target.clone(), Instant::now()));
+                true
+            }
+            (Some(index), false) => {
+                self.typing_in.remove(index);
+                true
+            }
+            (None, false) => false,
+        }
+    }
+}
+
+impl MessageHandler for TypingHandler {
+    type Operation = HandlerOperation;
+
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            pending: vec![],
+            pending_global: vec![],
+            client_data: None,
+            typing_in: vec![],
+        })
+    }
+
+    fn handle(&mut self, msg: &fracture_core::msg::Message) -> bool {
+        if let MessageVarient::Typing { target, typing } = msg.data.clone() {
+            // not ready until it knows who the client is
+            let Some(dat) = self.client_data.clone() else {
+                return false;
+            };
+            // there is no one to tell about a channel the client is not in
+            if let TypingTarget::Channel(channel) = &target {
+                if !dat.shared.channels.is_member(channel, dat.uuid) {
+                    return true;
+                }
+            }
+            if !self.should_forward(&target, typing) {
+                return true;
+            }
+            self.pending_global.push(GlobalHandlerOperation::Typing {
+                from: dat.uuid,
+                name: dat.name.clone(),
+                target,
+                typing,
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    fn handle_global_op(&mut self, op: &GlobalHandlerOperation) {
+        if let GlobalHandlerOperation::Typing { from, name, target, typing } = op {
+            let Some(dat) = self.client_data.as_ref() else {
+                return;
+            };
+            if *from == dat.uuid {
+                return;
+            }
+            let target = match target {
+                TypingTarget::Channel(channel) if dat.shared.channels.is_member(channel, dat.uuid) => {
+                    target.clone()
+                }
+                // this client sees the conversation as being with whoever is typing
+                TypingTarget::Direct(to) if *to == dat.uuid.as_u128() => TypingTarget::Direct(from.as_u128()),
+                _ => return,
+            };
+            self.pending.push(HandlerOperation::Client {
+                msg: Message {
+                    data: MessageVarient::UserTyping {
+                        user: from.as_u128(),
+                        name: name.clone(),
+                        target,
+                        typing: *typing,
+                    },
+                },
+            });
+        }
+    }
+
+    fn get_global_operations(&mut self) -> Option<Vec<GlobalHandlerOperation>> {
+        if self.pending_global.is_empty() {
+            None
+        } else {
+            Some(self.pending_global.drain(0..).collect())
+        }
+    }
+
+    fn get_operations(&mut self) -> Option<Vec<Self::Operation>> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.drain(0..).collect())
+        }
+    }
+
+    fn get_default_operations(&mut self) -> Vec<Self::Operation> {
+        vec![]
+    }
+}
+
+impl ServerClientInfo for TypingHandler {
+    type ClientData = ClientInfo;
+
+    fn accept_client_data(&mut self, data: Self::ClientData) {
+        self.client_data = Some(data);
+    }
+}
+
+impl ServerMessageHandler for TypingHandler {}
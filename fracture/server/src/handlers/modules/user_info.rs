@@ -46,6 +46,12 @@ impl MessageHandler for UserInfoUpdateHandler {
                                 name: (*name).clone(),
                             }
                         }
+                        GlobalHandlerOperation::ClientPresence { uuid, presence } => {
+                            msg::types::UserNameUpdate::PresenceChanged {
+                                uuid: uuid.as_u128(),
+                                presence: presence.clone(),
+                            }
+                        }
                         _ => return,
                     }],
                 },
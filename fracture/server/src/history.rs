@@ -0,0 +1,690 @@
+/// Chat history, so clients can see what was said before they joined, and messages can be changed after they are sent
+///
+/// Every channel message is kept in memory, so it can be paged through and looked up by id.
+/// if the server keeps history, messages (and changes to them, and the ids of direct messages) are also appended to a JSON lines file next to the config (`<base>.history.jsonl`),
+/// otherwise only the most recent messages are kept, and they are lost when the server stops.
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use serde::{Deserialize, Serialize};
+
+use fracture_core::msg::types::{HistoryMessage, Reaction, ReplyParent};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("Could not read or write the history {path}: {err}")]
+    Io { path: PathBuf, err: io::Error },
+    #[error("Failed to serialize history entry: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+/// Why a message could not be edited, deleted or replied to
+#[derive(Debug, thiserror::Error)]
+pub enum MessageError {
+    #[error("There is no message with that id")]
+    NoSuchMessage,
+    #[error("Only the author or a moderator can change that message")]
+    NotAllowed,
+    #[error("That message was deleted")]
+    Deleted,
+    #[error("That message is in another channel")]
+    OtherChannel,
+    #[error("That is not a reaction")]
+    NotAReaction,
+    #[error("That message has too many different reactions")]
+    TooManyReactions,
+    #[error("That message is too large to send")]
+    TooLarge,
+}
+
+/// Hands out the ids of chat messages, so every message on the server has a different one. clones share the same counter
+#[derive(Clone, Debug)]
+pub struct MessageIds {
+    next: Arc<AtomicU64>,
+}
+
+impl MessageIds {
+    /// start handing out ids from `next`, so they do not clash with ones already in the history
+    pub fn starting_at(next: u64) -> Self {
+        Self {
+            next: Arc::new(AtomicU64::new(next)),
+        }
+    }
+
+    pub fn next(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// The current time, in milliseconds since the unix epoch (UTC), for timestamping messages
+pub fn timestamp_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as i64)
+}
+
+/// A message, as it is saved in the history file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Entry {
+    id: u64,
+    /// history saved before messages had timestamps does not have one
+    #[serde(default)]
+    timestamp: i64,
+    channel: String,
+    content: String,
+    author: String,
+    author_uuid: u128,
+    /// id of the message this is a reply to
+    #[serde(default)]
+    parent: Option<u64>,
+    /// what the message said before each edit, oldest first. these come from `Record::Edit`s, not the message itself
+    #[serde(skip)]
+    previous: Vec<String>,
+    #[serde(skip)]
+    edited_at: Option<i64>,
+    #[serde(skip)]
+    deleted: bool,
+    /// these come from `Record::React`s too
+    #[serde(skip)]
+    reactions: Vec<Reaction>,
+}
+
+impl Entry {
+    /// What the message says, deleted messages are still kept but nobody gets to see them
+    fn visible_content(&self) -> &str {
+        if self.deleted {
+            ""
+        } else {
+            &self.content
+        }
+    }
+
+    /// Enough of the message to quote above a reply to it
+    fn as_parent(&self) -> ReplyParent {
+        let content = self.visible_content();
+        let mut snippet: String = content.chars().take(crate::conf::REPLY_SNIPPET_LENGTH).collect();
+        if snippet.len() < content.len() {
+            snippet.push('…');
+        }
+        ReplyParent {
+            id: self.id,
+            author: self.author.clone(),
+            snippet,
+        }
+    }
+}
+
+/// One line of the history file
+#[derive(Debug)]
+enum Record {
+    Message(Entry),
+    Change(Change),
+    Reserved(Reserved),
+}
+
+/// An id taken by something not kept in the history (like a direct message), so it is not handed out again after a restart
+#[derive(Debug, Serialize, Deserialize)]
+struct Reserved {
+    reserved: u64,
+}
+
+impl Record {
+    // not an untagged enum, as those can not hold the u128 uuids
+    fn parse(line: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(line)
+            .map(Record::Message)
+            .or_else(|_| serde_json::from_str(line).map(Record::Change))
+            .or_else(|_| serde_json::from_str(line).map(Record::Reserved))
+    }
+
+    fn to_line(&self) -> Result<String, serde_json::Error> {
+        match self {
+            Record::Message(entry) => serde_json::to_string(entry),
+            Record::Change(change) => serde_json::to_string(change),
+            Record::Reserved(reserved) => serde_json::to_string(reserved),
+        }
+    }
+}
+
+/// A change to a message that is already in the history
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Change {
+    Edit(Edit),
+    Delete(Delete),
+    React(React),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Edit {
+    id: u64,
+    content: String,
+    timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Delete {
+    id: u64,
+    timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct React {
+    id: u64,
+    emoji: String,
+    user: u128,
+    /// false if the reaction was taken back
+    add: bool,
+}
+
+/// The file the history is saved to
+#[derive(Debug)]
+struct HistoryFile {
+    path: PathBuf,
+    /// opened for appending
+    file: File,
+}
+
+impl HistoryFile {
+    fn write(&mut self, record: &Record) -> Result<(), HistoryError> {
+        let mut line = record.to_line()?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|err| HistoryError::Io {
+                path: self.path.clone(),
+                err,
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct HistoryStore {
+    /// None if the history is only kept in memory
+    file: Option<HistoryFile>,
+    /// most messages to keep
+    memory_limit: usize,
+    /// every message, oldest first
+    entries: Vec<Entry>,
+    /// the biggest id taken by something that is not in `entries`
+    reserved: Option<u64>,
+}
+
+impl HistoryStore {
+    /// path of the history for the server data at `base`
+    pub fn path_for(base: &Path) -> PathBuf {
+        base.with_extension("history.jsonl")
+    }
+
+    /// Keep only the last `memory_limit` messages, and do not save them anywhere
+    pub fn in_memory(memory_limit: usize) -> Self {
+        Self {
+            file: None,
+            memory_limit,
+            entries: vec![],
+            reserved: None,
+        }
+    }
+
+    /// Load the history at `path`, starting a empty one if it does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self, HistoryError> {
+        let io_err = |err| HistoryError::Io { path: path.clone(), err };
+        let mut store = Self::in_memory(usize::MAX);
+        match File::open(&path) {
+            Ok(file) => {
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line.map_err(io_err)?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    // a line can be cut off if the server was killed while writing it, that should not lose the rest
+                    match Record::parse(&line) {
+                        Ok(record) => store.apply(record),
+                        Err(err) => warn!("Skipping unreadable line {} of {}: {}", number + 1, path.display(), err),
+                    }
+                }
+                info!("Loaded {} messages of history from {}", store.entries.len(), path.display());
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                info!("No history found, a new one will be created at {}", path.display());
+            }
+            Err(err) => return Err(io_err(err)),
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(io_err)?;
+        store.file = Some(HistoryFile { path, file });
+        Ok(store)
+    }
+
+    /// The id after the newest message in the history, or anything else that was given an id
+    pub fn next_id(&self) -> u64 {
+        let newest = self.entries.last().map(|entry| entry.id).max(self.reserved);
+        newest.map_or(0, |id| id + 1)
+    }
+
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Message(entry) => {
+                self.entries.push(entry);
+                if self.entries.len() > self.memory_limit {
+                    self.entries.remove(0);
+                }
+            }
+            Record::Reserved(Reserved { reserved }) => {
+                self.reserved = self.reserved.max(Some(reserved));
+            }
+            Record::Change(Change::Edit(edit)) => {
+                if let Some(entry) = self.entry_mut(edit.id) {
+                    let original = std::mem::replace(&mut entry.content, edit.content);
+                    entry.previous.push(original);
+                    entry.edited_at = Some(edit.timestamp);
+                }
+            }
+            Record::Change(Change::Delete(delete)) => {
+                if let Some(entry) = self.entry_mut(delete.id) {
+                    entry.deleted = true;
+                }
+            }
+            Record::Change(Change::React(react)) => {
+                if let Some(entry) = self.entry_mut(react.id) {
+                    let index = entry.reactions.iter().position(|reaction| reaction.emoji == react.emoji);
+                    match (index, react.add) {
+                        (Some(index), true) => {
+                            let users = &mut entry.reactions[index].users;
+                            if !users.contains(&react.user) {
+                                users.push(react.user);
+                            }
+                        }
+                        (None, true) => entry.reactions.push(Reaction {
+                            emoji: react.emoji,
+                            users: vec![react.user],
+                        }),
+                        (Some(index), false) => {
+                            entry.reactions[index].users.retain(|user| *user != react.user);
+                            if entry.reactions[index].users.is_empty() {
+                                entry.reactions.remove(index);
+                            }
+                        }
+                        (None, false) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Save a record to the file (if there is one), and then apply it.
+    /// it is applied even if it could not be saved, so what clients see does not depend on the disk working
+    fn record(&mut self, record: Record) {
+        if let Some(file) = &mut self.file {
+            if let Err(err) = file.write(&record) {
+                error!("Failed to save to history: {}", err);
+            }
+        }
+        self.apply(record);
+    }
+
+    fn entry(&self, id: u64) -> Option<&Entry> {
+        self.entries
+            .binary_search_by_key(&id, |entry| entry.id)
+            .ok()
+            .map(|index| &self.entries[index])
+    }
+
+    fn entry_mut(&mut self, id: u64) -> Option<&mut Entry> {
+        self.entries
+            .binary_search_by_key(&id, |entry| entry.id)
+            .ok()
+            .map(|index| &mut self.entries[index])
+    }
+
+    /// Add a message to the end of the history.
+    /// `id` has to be bigger than every id already in it, so take it from `MessageIds` while holding on to the store
+    #[allow(clippy::too_many_arguments)] // it is every field of the message
+    pub fn append(
+        &mut self,
+        id: u64,
+        timestamp: i64,
+        channel: String,
+        content: String,
+        author: String,
+        author_uuid: u128,
+        parent: Option<u64>,
+    ) {
+        self.record(Record::Message(Entry {
+            id,
+            timestamp,
+            channel,
+            content,
+            author,
+            author_uuid,
+            parent,
+            previous: vec![],
+            edited_at: None,
+            deleted: false,
+            reactions: vec![],
+        }));
+    }
+
+    /// Remember that `id` was given to something that is not kept in the history, so it is not given out again.
+    /// like `append`, take it from `MessageIds` while holding on to the store
+    pub fn reserve(&mut self, id: u64) {
+        self.record(Record::Reserved(Reserved { reserved: id }));
+    }
+
+    /// Check that the message `id` can be changed by `editor` (a moderator can change anyones messages), returning its channel
+    fn check_change(&self, id: u64, editor: u128, moderator: bool) -> Result<String, MessageError> {
+        let entry = self.entry(id).ok_or(MessageError::NoSuchMessage)?;
+        if entry.deleted {
+            return Err(MessageError::Deleted);
+        }
+        if entry.author_uuid != editor && !moderator {
+            return Err(MessageError::NotAllowed);
+        }
+        Ok(entry.channel.clone())
+    }
+
+    /// Change what the message `id` says, keeping what it said before. returns the channel it is in
+    pub fn edit(
+        &mut self,
+        id: u64,
+        content: String,
+        timestamp: i64,
+        editor: u128,
+        moderator: bool,
+    ) -> Result<String, MessageError> {
+        let channel = self.check_change(id, editor, moderator)?;
+        self.record(Record::Change(Change::Edit(Edit { id, content, timestamp })));
+        // not what it said, it was edited so that is not seen anymore
+        debug!("Message {} was edited", id);
+        Ok(channel)
+    }
+
+    /// Hide the message `id` from everyone (it is still kept). returns the channel it is in
+    pub fn delete(&mut self, id: u64, timestamp: i64, editor: u128, moderator: bool) -> Result<String, MessageError> {
+        let channel = self.check_change(id, editor, moderator)?;
+        self.record(Record::Change(Change::Delete(Delete { id, timestamp })));
+        Ok(channel)
+    }
+
+    /// The channel the message `id` is in
+    pub fn channel_of(&self, id: u64) -> Option<&str> {
+        self.entry(id).map(|entry| entry.channel.as_str())
+    }
+
+    /// React to the message `id` as `user`, or take the reaction back if `add` is false.
+    /// returns the channel it is in and all of its reactions, or None if nothing changed
+    pub fn react(
+        &mut self,
+        id: u64,
+        emoji: String,
+        user: u128,
+        add: bool,
+    ) -> Result<Option<(String, Vec<Reaction>)>, MessageError> {
+        let entry = self.entry(id).ok_or(MessageError::NoSuchMessage)?;
+        if entry.deleted {
+            return Err(MessageError::Deleted);
+        }
+        let existing = entry.reactions.iter().find(|reaction| reaction.emoji == emoji);
+        if existing.is_some_and(|reaction| reaction.users.contains(&user)) == add {
+            return Ok(None);
+        }
+        if add && existing.is_none() {
+            let length = emoji.chars().count();
+            if length == 0 || length > crate::conf::MAX_REACTION_LENGTH || emoji.chars().any(char::is_whitespace) {
+                return Err(MessageError::NotAReaction);
+            }
+            if entry.reactions.len() >= crate::conf::MAX_REACTIONS {
+                return Err(MessageError::TooManyReactions);
+            }
+        }
+        self.record(Record::Change(Change::React(React { id, emoji, user, add })));
+        Ok(self
+            .entry(id)
+            .map(|entry| (entry.channel.clone(), entry.reactions.clone())))
+    }
+
+    /// Up to `limit` of the messages said in `channel` before the message `before` (or the newest ones), oldest first.
+    /// the page ends early if it would take up more than `max_bytes` encoded
+    pub fn page(&self, channel: &str, before: Option<u64>, limit: usize, max_bytes: usize) -> Vec<HistoryMessage> {
+        // ids only go up, so everything before `before` is before its position
+        let end = match before {
+            Some(before) => self.entries.partition_point(|entry| entry.id < before),
+            None => self.entries.len(),
+        };
+        let newest_first = self.entries[..end]
+            .iter()
+            .rev()
+            .filter(|entry| entry.channel == channel)
+            .map(|entry| self.to_message(entry));
+        let mut page = take_fitting(newest_first, limit, max_bytes);
+        page.reverse();
+        page
+    }
+
+    /// The message `id`, to quote above a reply to it in `channel`
+    pub fn parent(&self, id: u64, channel: &str) -> Result<ReplyParent, MessageError> {
+        let entry = self.entry(id).ok_or(MessageError::NoSuchMessage)?;
+        if entry.channel != channel {
+            return Err(MessageError::OtherChannel);
+        }
+        if entry.deleted {
+            return Err(MessageError::Deleted);
+        }
+        Ok(entry.as_parent())
+    }
+
+    /// The message `id` that a thread replies to, and the channel it is in
+    pub fn thread(&self, id: u64) -> Result<(String, HistoryMessage), MessageError> {
+        let parent = self.entry(id).ok_or(MessageError::NoSuchMessage)?;
+        Ok((parent.channel.clone(), self.to_message(parent)))
+    }
+
+    /// The newest replies to the message `id` (oldest first), up to `limit` of them and `max_bytes` encoded
+    pub fn replies(&self, id: u64, limit: usize, max_bytes: usize) -> Vec<HistoryMessage> {
+        let newest_first = self
+            .entries
+            .iter()
+            .rev()
+            // replies are always newer than what they reply to
+            .take_while(|entry| entry.id > id)
+            .filter(|entry| entry.parent == Some(id))
+            .map(|entry| self.to_message(entry));
+        let mut replies = take_fitting(newest_first, limit, max_bytes);
+        replies.reverse();
+        replies
+    }
+
+    fn to_message(&self, entry: &Entry) -> HistoryMessage {
+        HistoryMessage {
+            id: entry.id,
+            timestamp: entry.timestamp,
+            content: entry.visible_content().to_string(),
+            author: entry.author.clone(),
+            author_uuid: entry.author_uuid,
+            edited_at: entry.edited_at,
+            deleted: entry.deleted,
+            // the parent may have been trimmed from the history
+            parent: entry
+                .parent
+                .and_then(|parent| self.entry(parent))
+                .map(Entry::as_parent),
+            reactions: entry.reactions.clone(),
+        }
+    }
+}
+
+/// Take up to `limit` of `messages` until they would take up more than `max_bytes` encoded.
+/// a message too big to send even on its own is left out, so it can not stop clients paging past it
+fn take_fitting(messages: impl Iterator<Item = HistoryMessage>, limit: usize, max_bytes: usize) -> Vec<HistoryMessage> {
+    let mut taken = vec![];
+    let mut used = 0;
+    for message in messages {
+        if taken.len() >= limit {
+            break;
+        }
+        let size = bincode::serialized_size(&message).map_or(usize::MAX, |size| size as usize);
+        if size > max_bytes {
+            warn!("Message {} is too large to send as history, leaving it out", message.id);
+            continue;
+        }
+        if used + size > max_bytes {
+            break;
+        }
+        used += size;
+        taken.push(message);
+    }
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a store with `count` messages in "general" from one author, each saying its id padded to `size` characters
+    fn store(count: u64, size: usize) -> HistoryStore {
+        let mut store = HistoryStore::in_memory(usize::MAX);
+        for id in 0..count {
+            store.append(id, 0, String::from("general"), format!("{:0size$}", id), String::from("alice"), 1, None);
+        }
+        store
+    }
+
+    fn ids(messages: &[HistoryMessage]) -> Vec<u64> {
+        messages.iter().map(|message| message.id).collect()
+    }
+
+    #[test]
+    fn pages_are_the_newest_messages_before_oldest_first() {
+        let mut store = store(10, 10);
+        store.append(10, 0, String::from("other"), String::new(), String::from("alice"), 1, None);
+        assert_eq!(ids(&store.page("general", None, 3, usize::MAX)), vec![7, 8, 9]);
+        assert_eq!(ids(&store.page("general", Some(7), 3, usize::MAX)), vec![4, 5, 6]);
+        assert_eq!(ids(&store.page("general", Some(2), 3, usize::MAX)), vec![0, 1]);
+        assert!(store.page("general", Some(0), 3, usize::MAX).is_empty());
+        assert_eq!(ids(&store.page("other", None, 3, usize::MAX)), vec![10]);
+    }
+
+    #[test]
+    fn pages_stop_before_they_get_too_large() {
+        let store = store(10, 1000);
+        let one = bincode::serialized_size(&store.page("general", None, 1, usize::MAX)[0]).unwrap() as usize;
+        let page = store.page("general", None, 10, one * 3 + one / 2);
+        assert_eq!(ids(&page), vec![7, 8, 9]);
+        // the rest can still be paged through
+        let page = store.page("general", Some(7), 10, one * 3 + one / 2);
+        assert_eq!(ids(&page), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn messages_too_large_on_their_own_are_left_out() {
+        let mut store = store(3, 10);
+        store.append(3, 0, String::from("general"), "x".repeat(1000), String::from("alice"), 1, None);
+        store.append(4, 0, String::from("general"), String::from("after"), String::from("alice"), 1, None);
+        assert_eq!(ids(&store.page("general", None, 10, 500)), vec![0, 1, 2, 4]);
+    }
+
+    #[test]
+    fn threads_are_the_newest_replies_that_fit() {
+        let mut store = store(2, 10);
+        for id in 2..8 {
+            store.append(id, 0, String::from("general"), "x".repeat(1000), String::from("bob"), 2, Some(1));
+        }
+        let (channel, parent) = store.thread(1).unwrap();
+        assert_eq!((channel.as_str(), parent.id), ("general", 1));
+        assert_eq!(ids(&store.replies(1, 200, usize::MAX)), vec![2, 3, 4, 5, 6, 7]);
+        assert_eq!(ids(&store.replies(1, 2, usize::MAX)), vec![6, 7]);
+        assert_eq!(ids(&store.replies(1, 200, 3500)), vec![5, 6, 7]);
+        assert!(store.replies(0, 200, usize::MAX).is_empty());
+        assert!(matches!(store.thread(100), Err(MessageError::NoSuchMessage)));
+    }
+
+    #[test]
+    fn reactions_are_counted_per_user() {
+        let mut store = store(1, 10);
+        let (channel, reactions) = store.react(0, String::from("👍"), 1, true).unwrap().unwrap();
+        assert_eq!(channel, "general");
+        assert_eq!(reactions.len(), 1);
+        // reacting twice changes nothing
+        assert!(store.react(0, String::from("👍"), 1, true).unwrap().is_none());
+        let (_, reactions) = store.react(0, String::from("👍"), 2, true).unwrap().unwrap();
+        assert_eq!(reactions[0].users, vec![1, 2]);
+        let (_, reactions) = store.react(0, String::from("🎉"), 2, true).unwrap().unwrap();
+        assert_eq!(reactions.len(), 2);
+        // taking back the last one removes the reaction
+        let (_, reactions) = store.react(0, String::from("🎉"), 2, false).unwrap().unwrap();
+        assert_eq!(reactions.len(), 1);
+        assert!(store.react(0, String::from("🎉"), 2, false).unwrap().is_none());
+        assert_eq!(store.page("general", None, 1, usize::MAX)[0].reactions[0].users, vec![1, 2]);
+    }
+
+    #[test]
+    fn reactions_are_checked() {
+        let mut store = store(2, 10);
+        for reaction in ["", "two words", &"x".repeat(crate::conf::MAX_REACTION_LENGTH + 1)] {
+            assert!(matches!(
+                store.react(0, reaction.to_string(), 1, true),
+                Err(MessageError::NotAReaction)
+            ));
+        }
+        for number in 0..crate::conf::MAX_REACTIONS {
+            store.react(0, number.to_string(), 1, true).unwrap();
+        }
+        assert!(matches!(
+            store.react(0, String::from("more"), 1, true),
+            Err(MessageError::TooManyReactions)
+        ));
+        // existing reactions can still be added to
+        assert!(store.react(0, String::from("0"), 2, true).unwrap().is_some());
+        assert!(matches!(store.react(9, String::from("👍"), 1, true), Err(MessageError::NoSuchMessage)));
+        store.delete(1, 0, 1, false).unwrap();
+        assert!(matches!(store.react(1, String::from("👍"), 1, true), Err(MessageError::Deleted)));
+    }
+
+    #[test]
+    fn only_authors_and_moderators_change_messages() {
+        let mut store = store(1, 10);
+        assert!(matches!(store.edit(0, String::from("mine"), 0, 2, false), Err(MessageError::NotAllowed)));
+        assert_eq!(store.edit(0, String::from("edited"), 5, 1, false).unwrap(), "general");
+        assert_eq!(store.edit(0, String::from("moderated"), 6, 2, true).unwrap(), "general");
+        let message = &store.page("general", None, 1, usize::MAX)[0];
+        assert_eq!((message.content.as_str(), message.edited_at), ("moderated", Some(6)));
+        assert!(matches!(store.delete(0, 7, 2, false), Err(MessageError::NotAllowed)));
+        store.delete(0, 7, 1, false).unwrap();
+        assert!(store.page("general", None, 1, usize::MAX)[0].content.is_empty());
+        assert!(matches!(store.edit(0, String::from("again"), 8, 1, false), Err(MessageError::Deleted)));
+    }
+
+    #[test]
+    fn saved_history_is_loaded_again() {
+        let path = std::env::temp_dir().join(format!("fracture-test-{}.history.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut store = HistoryStore::load(path.clone()).unwrap();
+        store.append(0, 1, String::from("general"), String::from("hi"), String::from("alice"), 1, None);
+        store.append(1, 2, String::from("general"), String::from("reply"), String::from("bob"), 2, Some(0));
+        store.edit(0, String::from("hello"), 3, 1, false).unwrap();
+        store.react(1, String::from("👍"), 1, true).unwrap();
+        drop(store);
+        let mut store = HistoryStore::load(path.clone()).unwrap();
+        assert_eq!(store.next_id(), 2);
+        // ids given to direct messages are not given out again either
+        store.reserve(2);
+        drop(store);
+        let store = HistoryStore::load(path.clone()).unwrap();
+        assert_eq!(store.next_id(), 3);
+        let page = store.page("general", None, 10, usize::MAX);
+        assert_eq!(page[0].content, "hello");
+        assert_eq!(page[1].parent.as_ref().map(|parent| parent.id), Some(0));
+        assert_eq!(page[1].reactions[0].users, vec![1]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -7,19 +7,30 @@ use queues::IsQueue;
 use queues::Queue;
 
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use tokio_util::codec::Framed;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::error::TryRecvError;
 
 #[allow(unused_imports)]
 use log::{trace, debug, info, warn, error};
 
-use fracture_core::handler::GlobalHandlerOperation;
+use fracture_core::codec::FractureCodec;
+use fracture_core::handler::{GlobalHandlerOperation, KickTarget};
 use fracture_core::handler::ServerMessageHandler;
+use fracture_core::heartbeat::Heartbeat;
+use fracture_core::transport::{BoxedTransport, PeerAddr};
 use fracture_core::msg;
 use fracture_core::stat;
 use fracture_core::SocketUtils;
 
+use crate::accounts::{self, AccountError};
+use crate::interface::tracker::{Subscription, UserUpdate};
+use crate::moderation::Ban;
+use crate::names::NameError;
+use crate::ratelimit::{RateLimiter, Verdict};
+use crate::sessions::SessionHandle;
+use crate::state::SharedState;
+
 pub mod stati {
     use fracture_core::stat;
 
@@ -40,6 +51,10 @@ pub mod stati {
         Sucsess,
         Noop,
         ClientKicked(String),
+        /// The client could not be accepted (for example, it speaks a incompatible protocol version)
+        Refused(String),
+        /// The clients account is banned, and this is what it is told
+        Banned(String),
         #[allow(dead_code)]
         Unexpected(fracture_core::msg::Message),
         #[allow(dead_code)]
@@ -51,6 +66,8 @@ pub mod stati {
     pub enum UpdateReadStatus {
         Disconnected,
         GracefullDisconnect,
+        /// the client kept sending messages too fast after being warned
+        RateLimited,
         Sucsess,
         ReadError(stat::ReadMessageError),
     }
@@ -68,6 +85,9 @@ pub enum HandlerOperation {
 pub struct ClientInfo {
     pub name: String,
     pub uuid: uuid::Uuid,
+    pub shared: SharedState,
+    /// the client resumed a session, so it is still in the channels it was in
+    pub resumed: bool,
 }
 
 //TODO this
@@ -75,6 +95,11 @@ pub struct ClientInfo {
 pub enum InterfaceState {
     /// Begining state, in this state it is waiting for the client to send ConnectMessage
     Start,
+    /// Client has sent ConnectMessage, and the server has asked it to authenticate.
+    /// waiting for the client to send `Authenticate`
+    Authenticating,
+    /// The server has asked the client to log in, waiting for it to send `Login` or `Register`
+    LoggingIn,
     /// Client has sent ConnectMessage,
     /// Server sends the data back
     RecevedConnectMessage,
@@ -95,16 +120,32 @@ pub struct ClientInterface {
     global_handler_rx: broadcast::Receiver<GlobalHandlerOperation>,
     global_handler_tx: broadcast::Sender<GlobalHandlerOperation>,
     pending_global_ops: Queue<GlobalHandlerOperation>,
-    server_name: String,
+    shared: SharedState,
     client_name: Option<String>,
     uuid: uuid::Uuid,
-    socket: TcpStream,
+    /// capabilities both the client and server support, decided during the handshake
+    capabilities: Vec<String>,
+    /// who else is connected, once the client has claimed its name
+    roster: Option<Subscription>,
+    socket: Framed<BoxedTransport, FractureCodec>,
+    /// where the client connected from, since a generic transport cannot be asked
+    addr: PeerAddr,
+    /// how fast the client may send messages
+    limiter: RateLimiter,
+    /// if the client is still there
+    heartbeat: Heartbeat,
+    /// the session the client can resume if it loses its connection, once it has one
+    session: Option<SessionHandle>,
+    /// direct messages sent while the resumed session was detached, sent once the client is connected
+    held: Vec<msg::Message>,
+    /// a admin kicked or banned the client: why, and what to tell it
+    kicked: Option<(msg::types::ServerDisconnectReason, String)>,
 }
 
 impl ClientInterface {
     pub fn new(
-        socket: TcpStream,
-        name: String,
+        socket: BoxedTransport,
+        addr: PeerAddr,
         handlers: Vec<
             Box<
                 dyn ServerMessageHandler<ClientData = ClientInfo, Operation = HandlerOperation>
@@ -112,6 +153,7 @@ impl ClientInterface {
             >,
         >,
         global_handler_channel: broadcast::Sender<GlobalHandlerOperation>,
+        shared: SharedState,
     ) -> Self {
         let global_handler_rx = global_handler_channel.subscribe();
         let uuid = uuid::Uuid::new_v4();
@@ -120,14 +162,22 @@ impl ClientInterface {
             incoming: queue![],
             state: InterfaceState::Start,
             handlers,
-            socket,
+            socket: Framed::new(socket, FractureCodec::with_max_size(shared.max_message_size)),
             pending_op: queue![],
             pending_global_ops: queue![],
             global_handler_tx: global_handler_channel,
             global_handler_rx,
-            server_name: name,
             client_name: None,
             uuid,
+            capabilities: vec![],
+            roster: None,
+            limiter: RateLimiter::new(&shared.rate_limits),
+            heartbeat: Heartbeat::new(&shared.heartbeat),
+            session: None,
+            held: vec![],
+            kicked: None,
+            shared,
+            addr,
         }
     }
 
@@ -138,11 +188,215 @@ impl ClientInterface {
     }
 
     /// Get the uuid of the client.
-    /// this is not specified by the client, but generated randomly on initialization.
+    /// this is not specified by the client, it is generated randomly on initialization,
+    /// and replaced with the accounts uuid when the client logs in.
     pub const fn uuid(&self) -> uuid::Uuid {
         self.uuid
     }
 
+    /// If the client has not sent its `ConnectMessage` yet
+    pub const fn is_awaiting_connect(&self) -> bool {
+        matches!(self.state, InterfaceState::Start)
+    }
+
+    /// If the client has made it through authentication (and logging in), and everyone else knows about it
+    pub const fn is_authenticated(&self) -> bool {
+        matches!(
+            self.state,
+            InterfaceState::RecevedConnectMessage | InterfaceState::Ready
+        )
+    }
+
+    /// If a admin kicked or banned the client, why and what to tell it. the connection should be closed
+    pub fn take_kick(&mut self) -> Option<(msg::types::ServerDisconnectReason, String)> {
+        self.kicked.take()
+    }
+
+    /// The clients session, if it has one
+    pub const fn session(&self) -> Option<&SessionHandle> {
+        self.session.as_ref()
+    }
+
+    /// The client sent `ConnectMessage` (or `ResumeSession`, with the `token` of the session to resume).
+    /// checks that it can be accepted, and moves on to authenticating it
+    fn accept_connect(
+        &mut self,
+        protocol: msg::types::ProtocolVersion,
+        capabilities: &[String],
+        name: String,
+        token: Option<String>,
+    ) -> stati::UpdateStatus {
+        use fracture_core::msg::{Message, MessageVarient};
+        if !msg::PROTOCOL_VERSION.is_compatible(&protocol) {
+            info!(
+                "Refusing client {:?} with incompatible protocol version {} (server is {})",
+                self.get_client_addr(),
+                protocol,
+                msg::PROTOCOL_VERSION
+            );
+            let outdated = if protocol.major < msg::PROTOCOL_VERSION.major {
+                "your client"
+            } else {
+                "the server"
+            };
+            return stati::UpdateStatus::Refused(format!(
+                "Incompatible protocol version: your client speaks {}, but this server speaks {}. Please upgrade {}.",
+                protocol,
+                msg::PROTOCOL_VERSION,
+                outdated
+            ));
+        }
+        self.capabilities = msg::capabilities::negotiate(capabilities);
+        debug!("Negotiated capabilities {:?}", self.capabilities);
+        if let Some(token) = token {
+            // checked before taking the session over, so it is still released when its old connection gives up on it
+            let banned = self
+                .shared
+                .sessions
+                .user(&token)
+                .and_then(|uuid| self.shared.bans.lock().account(uuid).map(|ban| ban.message()));
+            if let Some(message) = banned {
+                info!("Turning away {:?}, its account is banned", self.get_client_addr());
+                return stati::UpdateStatus::Banned(message);
+            }
+            if let Some(resumed) = self.shared.sessions.resume(&token) {
+                // it is still in the roster and its channels, so no one sees it leave or join
+                info!("{:?} resumed the session of {:?}", self.get_client_addr(), resumed.name);
+                self.uuid = resumed.uuid;
+                self.client_name = Some(resumed.name);
+                self.session = Some(resumed.handle);
+                self.held = resumed.held;
+                self.roster = Some(self.shared.tracker.subscribe());
+                self.state = InterfaceState::RecevedConnectMessage;
+                return stati::UpdateStatus::Sucsess;
+            }
+            debug!(
+                "{:?} tried to resume a session that is gone, connecting it like a new client",
+                self.get_client_addr()
+            );
+        }
+        if let Err(err) = self.shared.tracker.rules().validate(&name) {
+            return stati::UpdateStatus::Refused(err.to_string());
+        }
+        self.client_name = Some(name);
+        if self.shared.password.is_some() {
+            if !self.capabilities.iter().any(|cap| cap == msg::capabilities::AUTH) {
+                return stati::UpdateStatus::Refused(String::from(
+                    "This server requires a password, but your client does not support entering one. Please upgrade your client.",
+                ));
+            }
+            self.queue_message(Message {
+                data: MessageVarient::AuthRequest {},
+            })
+            .unwrap();
+            self.state = InterfaceState::Authenticating;
+            return stati::UpdateStatus::Sucsess;
+        }
+        self.request_login()
+    }
+
+    /// Ask the client to log in if accounts are enabled, or finish authentication if they are not
+    fn request_login(&mut self) -> stati::UpdateStatus {
+        use fracture_core::msg::{Message, MessageVarient};
+        if self.shared.accounts.is_none() {
+            return self.finish_auth();
+        }
+        if !self.capabilities.iter().any(|cap| cap == msg::capabilities::ACCOUNTS) {
+            return stati::UpdateStatus::Refused(String::from(
+                "This server requires you to log in, but your client does not support accounts. Please upgrade your client.",
+            ));
+        }
+        self.queue_message(Message {
+            data: MessageVarient::LoginRequest {},
+        })
+        .unwrap();
+        self.state = InterfaceState::LoggingIn;
+        stati::UpdateStatus::Sucsess
+    }
+
+    /// Log in to (or create) a account, taking on its name and uuid
+    async fn log_in(&mut self, username: String, password: String, register: bool) -> Result<(), LoginError> {
+        let store = self
+            .shared
+            .accounts
+            .clone()
+            .expect("only logging in when accounts are enabled");
+        // hashing is slow on purpose, so it is done on the blocking pool, and without holding the lock
+        let uuid = if register {
+            self.shared.tracker.rules().validate(&username)?;
+            let hash = tokio::task::spawn_blocking(move || accounts::hash_password(&password))
+                .await
+                .expect("password hashing task panicked")?;
+            let uuid = store.insert(username.clone(), hash).await?;
+            info!("Registered new account {:?} ({})", username, uuid);
+            uuid
+        } else {
+            let (uuid, hash) = store
+                .credentials(&username)
+                .ok_or(AccountError::InvalidCredentials)?;
+            tokio::task::spawn_blocking(move || accounts::verify_password(&password, &hash))
+                .await
+                .expect("password hashing task panicked")?;
+            uuid
+        };
+        self.uuid = uuid;
+        self.client_name = Some(username);
+        Ok(())
+    }
+
+    /// The client is connected and authenticated, so claim its name (telling everyone about it), and move on to sending `ServerInfo`
+    fn finish_auth(&mut self) -> stati::UpdateStatus {
+        use fracture_core::msg::{Message, MessageVarient};
+        if let Some(ban) = self.shared.bans.lock().account(self.uuid()) {
+            info!("Turning away {:?}, its account is banned", self.get_client_addr());
+            return stati::UpdateStatus::Banned(ban.message());
+        }
+        // logging in to a account whose connection was lost takes over its session, instead of being refused for already being connected
+        if let Some(resumed) = self.shared.sessions.resume_user(self.uuid()) {
+            info!("{:?} logged in to a account that was waiting to be resumed", self.get_client_addr());
+            self.session = Some(resumed.handle);
+            self.held = resumed.held;
+            self.roster = Some(self.shared.tracker.subscribe());
+            self.state = InterfaceState::RecevedConnectMessage;
+            return stati::UpdateStatus::Sucsess;
+        }
+        let requested = self.name().expect("client named itself before authenticating");
+        let name = match self.shared.tracker.claim(self.uuid(), &requested) {
+            Ok(name) => name,
+            Err(err) => return stati::UpdateStatus::Refused(err.to_string()),
+        };
+        if name != requested {
+            info!("{:?} is already in use, naming client {:?} instead", requested, name);
+            if !self.capabilities.iter().any(|cap| cap == msg::capabilities::NAMES) {
+                self.shared.tracker.release(self.uuid());
+                return stati::UpdateStatus::Refused(NameError::Taken(requested).to_string());
+            }
+            self.queue_message(Message {
+                data: MessageVarient::NameAssigned { name: name.clone() },
+            })
+            .unwrap();
+            self.client_name = Some(name);
+        }
+        debug!(
+            "Client named itself and completed auth: {:#?} (uuid {})",
+            self.name().unwrap(),
+            self.uuid()
+        );
+        // this client is in the roster, not its updates, so it will not be told about itself joining
+        self.roster = Some(self.shared.tracker.subscribe());
+        self.state = InterfaceState::RecevedConnectMessage;
+        stati::UpdateStatus::Sucsess
+    }
+
+    /// Refuse the connection, telling the client why in a `ServerInfo` message, and then close the socket
+    pub async fn refuse(&mut self, reason: String) {
+        let refusal = refusal(&self.shared, self.uuid, reason);
+        if let Err(err) = self.send_message(refusal).await {
+            error!("Error whilst sending refusal!\n{:#?}", err);
+        }
+        self.close(String::new(), None).await;
+    }
+
     /// Close the socket, optionaly notifying the client why it is being disconnected
     /// does NOT send any queued messages, as that is kinda pointless since the client could not reply and may become confused
     pub async fn close(
@@ -179,11 +433,12 @@ impl ClientInterface {
                             };
                         }
                         stat::SendError::SeriError(_) => panic!("Could not serialize shutdown msg"),
+                        stat::SendError::TooLarge { .. } => unreachable!("shutdown msg is tiny"),
                     }
                 }
             };
         }
-        match self.socket.shutdown().await {
+        match self.socket.get_mut().shutdown().await {
             Ok(_) => {}
             Err(err) => {
                 //Socket is already shutdown
@@ -195,8 +450,8 @@ impl ClientInterface {
     }
 
     /// Get the address of the client connected
-    pub fn get_client_addr(&mut self) -> Result<std::net::SocketAddr, std::io::Error> {
-        self.socket.peer_addr()
+    pub const fn get_client_addr(&self) -> &PeerAddr {
+        &self.addr
     }
 
     /// Send one message from the queue
@@ -243,6 +498,10 @@ impl ClientInterface {
                         stat::SendError::SeriError(err) => {
                             return Err(stati::MultiSendError(stat::SendError::SeriError(err)));
                         }
+                        // the client would disconnect on getting it, so it is better that it just misses this one
+                        err @ stat::SendError::TooLarge { .. } => {
+                            warn!("Not sending a message to {:?}: {}", self.addr, err);
+                        }
                     }
                 }
             }
@@ -272,27 +531,24 @@ impl ClientInterface {
                 .unwrap();
                 return stati::UpdateStatus::Sucsess;
             }
+            if let MessageVarient::Pong = msg.data {
+                self.incoming.remove().unwrap();
+                if let Some(rtt) = self.heartbeat.pong() {
+                    trace!("Round trip time to {:?} is {:?}", self.addr, rtt);
+                }
+                return stati::UpdateStatus::Sucsess;
+            }
         }
         // handle everything else
         match self.state {
             InterfaceState::Start => {
                 if let Ok(msg) = self.incoming.remove() {
                     match msg.data {
-                        MessageVarient::ConnectMessage { name } => {
-                            //TODO make this actulay handle users (with the user state handler) (mabey later)
-                            self.client_name = Some(name);
-                            debug!(
-                                "Client named itself and completed auth: {:#?}",
-                                self.name().unwrap()
-                            );
-                            let _ =
-                                self.global_handler_tx
-                                    .send(GlobalHandlerOperation::ClientNamed {
-                                        uuid: self.uuid(),
-                                        name: self.name().unwrap(),
-                                    });
-                            self.state = InterfaceState::RecevedConnectMessage;
-                            return stati::UpdateStatus::Sucsess;
+                        MessageVarient::ConnectMessage { protocol, capabilities, name } => {
+                            return self.accept_connect(protocol, &capabilities, name, None);
+                        }
+                        MessageVarient::ResumeSession { protocol, capabilities, name, token } => {
+                            return self.accept_connect(protocol, &capabilities, name, Some(token));
                         }
                         other => {
                             error!(
@@ -308,22 +564,122 @@ impl ClientInterface {
                     };
                 }
             }
+            InterfaceState::Authenticating => {
+                if let Ok(msg) = self.incoming.remove() {
+                    match msg.data {
+                        MessageVarient::Authenticate { password } => {
+                            let expected = self.shared.password.as_deref().unwrap_or_default();
+                            if !constant_time_eq(password.as_bytes(), expected.as_bytes()) {
+                                return stati::UpdateStatus::Refused(String::from("Incorrect password"));
+                            }
+                            return self.request_login();
+                        }
+                        other => {
+                            error!(
+                                "Recieved {:#?} from client {:?} instead of authentication!",
+                                other,
+                                self.get_client_addr()
+                            );
+                            return stati::UpdateStatus::ClientKicked(format!(
+                                "Recieved {:#?} instead of authentication!",
+                                other
+                            ));
+                        }
+                    };
+                }
+            }
+            InterfaceState::LoggingIn => {
+                if let Ok(msg) = self.incoming.remove() {
+                    let res = match msg.data {
+                        MessageVarient::Login { username, password } => {
+                            self.log_in(username, password, false).await
+                        }
+                        MessageVarient::Register { username, password } => {
+                            self.log_in(username, password, true).await
+                        }
+                        other => {
+                            error!(
+                                "Recieved {:#?} from client {:?} instead of logging in!",
+                                other,
+                                self.get_client_addr()
+                            );
+                            return stati::UpdateStatus::ClientKicked(format!(
+                                "Recieved {:#?} instead of logging in!",
+                                other
+                            ));
+                        }
+                    };
+                    return match res {
+                        Ok(()) => self.finish_auth(),
+                        Err(LoginError::Name(err)) => stati::UpdateStatus::Refused(err.to_string()),
+                        Err(LoginError::Account(
+                            err @ (AccountError::InvalidCredentials | AccountError::UsernameTaken(_)),
+                        )) => stati::UpdateStatus::Refused(err.to_string()),
+                        Err(LoginError::Account(err)) => {
+                            error!("Error while logging in a client: {}", err);
+                            stati::UpdateStatus::Refused(String::from(
+                                "The server could not log you in, please try again later",
+                            ))
+                        }
+                    };
+                }
+            }
             InterfaceState::RecevedConnectMessage => {
+                // sessions are only started below, so one is only here if it was resumed
+                let resumed = self.session.is_some();
                 for handler in &mut self.handlers {
                     handler.accept_client_data(ClientInfo {
                         name: self.client_name.clone().expect("This should not happen"),
                         uuid: self.uuid,
+                        shared: self.shared.clone(),
+                        resumed,
                     });
                 }
+                let connected_users = self
+                    .roster
+                    .as_mut()
+                    .map(|roster| std::mem::take(&mut roster.users))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|user| {
+                        [
+                            types::UserNameUpdate::UserNamed {
+                                uuid: user.id.as_u128(),
+                                name: user.name,
+                            },
+                            types::UserNameUpdate::PresenceChanged {
+                                uuid: user.id.as_u128(),
+                                presence: user.presence,
+                            },
+                        ]
+                    })
+                    .collect();
+                if self.capabilities.iter().any(|cap| cap == msg::capabilities::RESUME) {
+                    let name = self.client_name.clone().expect("This should not happen");
+                    let session = self
+                        .session
+                        .get_or_insert_with(|| self.shared.sessions.start(self.uuid, name));
+                    let token = session.token.clone();
+                    self.queue_message(Message {
+                        data: MessageVarient::SessionStarted { token, resumed },
+                    })
+                    .unwrap();
+                }
                 self.queue_message(Message {
                     data: MessageVarient::ServerInfo {
-                        server_name: self.server_name.clone(),
+                        protocol: msg::PROTOCOL_VERSION,
                         conn_status: types::ConnectionStatus::Connected,
-                        connected_users: vec![], //TODO make this actulay send users instead of l y i n g (so many lies)
+                        server_name: self.shared.server_name.clone(),
+                        connected_users,
                         your_uuid: self.uuid.as_u128(),
+                        capabilities: self.capabilities.clone(),
+                        max_message_size: self.shared.max_message_size as u64,
                     },
                 })
                 .unwrap();
+                for held in std::mem::take(&mut self.held) {
+                    self.queue_message(held).unwrap();
+                }
                 self.state = InterfaceState::Ready;
                 return stati::UpdateStatus::Sucsess;
             }
@@ -406,7 +762,7 @@ impl ClientInterface {
         }
     }
 
-    /// Collect global operations from the channel and store them internaly
+    /// Collect global operations and roster updates from their channels and store them internaly
     pub fn collect_recv_global_actions(&mut self) {
         loop {
             match self.global_handler_rx.try_recv() {
@@ -417,24 +773,132 @@ impl ClientInterface {
                     TryRecvError::Closed => {
                         panic!("No senders left! this should not happen");
                     }
+                    // there is no way to get them back, but it is better than dropping the client without cleaning up after it
                     TryRecvError::Lagged(amnt) => {
-                        panic!("Missed receiving {} global operations! if this continues, increase the max allowed ammount!", amnt);
+                        warn!("{:?} fell behind and missed {} global operations", self.addr, amnt);
                     }
                     TryRecvError::Empty => break,
                 },
             }
         }
+        // roster updates are turned into the same operations for the handlers
+        if let Some(roster) = &mut self.roster {
+            for update in roster.try_recv(&self.shared.tracker) {
+                match update {
+                    UserUpdate::Joined(user) => {
+                        self.pending_global_ops
+                            .add(GlobalHandlerOperation::ClientConnect { uuid: user.id })
+                            .unwrap();
+                        self.pending_global_ops
+                            .add(GlobalHandlerOperation::ClientNamed {
+                                uuid: user.id,
+                                name: user.name,
+                            })
+                            .unwrap();
+                    }
+                    UserUpdate::Left(user) => {
+                        self.pending_global_ops
+                            .add(GlobalHandlerOperation::ClientDisconnect {
+                                uuid: user.id,
+                                name: Some(user.name),
+                            })
+                            .unwrap();
+                    }
+                    UserUpdate::Presence(user) => {
+                        self.pending_global_ops
+                            .add(GlobalHandlerOperation::ClientPresence {
+                                uuid: user.id,
+                                presence: user.presence,
+                            })
+                            .unwrap();
+                    }
+                }
+            }
+        }
     }
 
     /// Executes internaly stored global operations
     pub fn execute_global_actions(&mut self) {
+        // the handlers only get the client data when moving on to `Ready`, so leave everything queued until then
+        if matches!(self.state, InterfaceState::RecevedConnectMessage) {
+            return;
+        }
         while let Ok(oper) = self.pending_global_ops.remove() {
+            if let GlobalHandlerOperation::Kick { target, reason, by, why, ban_address } = &oper {
+                self.kick(target, reason, by, why, *ban_address);
+                continue;
+            }
+            // clients that have not gotten through authentication yet should not see what is going on
+            if !self.is_authenticated() {
+                continue;
+            }
             for h in &mut self.handlers {
                 h.handle_global_op(&oper);
             }
         }
     }
 
+    /// A admin kicked or banned someone, if it was this client get ready to disconnect it
+    fn kick(
+        &mut self,
+        target: &KickTarget,
+        reason: &msg::types::ServerDisconnectReason,
+        by: &str,
+        why: &Option<String>,
+        ban_address: bool,
+    ) {
+        let ip = self.addr.ip();
+        let is_target = match target {
+            KickTarget::User(uuid) => self.is_authenticated() && *uuid == self.uuid,
+            KickTarget::Address(address) => ip == Some(*address),
+        };
+        if !is_target {
+            return;
+        }
+        let ban = Ban::new(self.client_name.clone(), by.to_string(), why.clone());
+        if let (true, Some(ip)) = (ban_address, ip) {
+            info!("Banning the address of {:?}", self.addr);
+            if let Err(err) = self.shared.bans.lock().ban_address(ip, ban.clone()) {
+                error!("Could not save the ban on {}: {}", ip, err);
+            }
+        }
+        let message = match (reason, why) {
+            (msg::types::ServerDisconnectReason::Banned, _) => ban.message(),
+            (_, Some(why)) => format!("You were kicked by {}: {}", by, why),
+            (_, None) => format!("You were kicked by {}", by),
+        };
+        self.kicked = Some((reason.clone(), message));
+    }
+
+    /// Ping the client if it is time to, so it is known to still be there
+    pub fn send_heartbeat(&mut self) {
+        if self.heartbeat.ping_due() {
+            self.queue_message(msg::Message {
+                data: msg::MessageVarient::Ping,
+            })
+            .unwrap();
+        }
+    }
+
+    /// If the client has not sent anything (not even a answer to a ping) in too long
+    pub fn is_peer_dead(&self) -> bool {
+        self.heartbeat.is_dead()
+    }
+
+    /// Drop a message that went over the rate limits, telling the client if it was a chat message so it is not left waiting for it
+    fn drop_limited(&mut self, dropped: &msg::Message) {
+        use fracture_core::msg::{Message, MessageVarient};
+        if let MessageVarient::ClientSendChat { nonce, .. } | MessageVarient::ClientSendDirect { nonce, .. } = dropped.data {
+            self.queue_message(Message {
+                data: MessageVarient::ChatFailed {
+                    nonce,
+                    reason: String::from("You are sending messages too fast"),
+                },
+            })
+            .unwrap();
+        }
+    }
+
     /// Handles the reading message half of updating the client.
     /// for the most part it handles errors that occur in it, but it will return info for some situations,
     /// Like disconnects.
@@ -444,15 +908,33 @@ impl ClientInterface {
         let read = self.read_msg().await;
         match read {
             Ok(stat) => {
+                self.heartbeat.received();
                 if let msg::MessageVarient::DisconnectMessage {} = stat.msg.data {
                     stati::UpdateReadStatus::GracefullDisconnect
                 } else {
-                    let added = self.incoming.add(stat.msg);
-                    if added.is_err() {
-                        panic!("Could not queue message for sending!");
-                    } else {
-                        stati::UpdateReadStatus::Sucsess
+                    // checked here so messages over the limit never get to the handlers
+                    match self.limiter.check(stat.bytes) {
+                        Verdict::Allow => {
+                            let added = self.incoming.add(stat.msg);
+                            if added.is_err() {
+                                panic!("Could not queue message for sending!");
+                            }
+                        }
+                        Verdict::Warn { retry_after, warnings_left } => {
+                            info!("{:?} is sending messages too fast, {} warnings left", self.addr, warnings_left);
+                            self.drop_limited(&stat.msg);
+                            self.queue_message(msg::Message {
+                                data: msg::MessageVarient::RateLimited {
+                                    retry_after_ms: retry_after.as_millis() as u64,
+                                    warnings_left,
+                                },
+                            })
+                            .unwrap();
+                        }
+                        Verdict::Drop => self.drop_limited(&stat.msg),
+                        Verdict::Kick => return stati::UpdateReadStatus::RateLimited,
                     }
+                    stati::UpdateReadStatus::Sucsess
                 }
             }
             Err(err_or_disconnect) => match err_or_disconnect {
@@ -463,8 +945,40 @@ impl ClientInterface {
     }
 }
 
+/// Why logging in failed
+#[derive(Debug, thiserror::Error)]
+enum LoginError {
+    #[error("{0}")]
+    Account(#[from] AccountError),
+    #[error("{0}")]
+    Name(#[from] NameError),
+}
+
+/// The `ServerInfo` that tells the client `uuid` its connection was refused, and why
+pub fn refusal(shared: &SharedState, uuid: uuid::Uuid, reason: String) -> msg::Message {
+    use fracture_core::msg::{types, Message, MessageVarient};
+    Message {
+        data: MessageVarient::ServerInfo {
+            protocol: msg::PROTOCOL_VERSION,
+            conn_status: types::ConnectionStatus::Refused { reason },
+            server_name: shared.server_name.clone(),
+            connected_users: vec![],
+            your_uuid: uuid.as_u128(),
+            capabilities: vec![],
+            max_message_size: shared.max_message_size as u64,
+        },
+    }
+}
+
+/// Compare two byte strings without leaking where they differ through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
 impl SocketUtils for ClientInterface {
-    fn get_sock(&mut self) -> &mut TcpStream {
+    type Transport = BoxedTransport;
+
+    fn get_framed(&mut self) -> &mut Framed<BoxedTransport, FractureCodec> {
         &mut self.socket
     }
 }
@@ -1,53 +1,111 @@
 #[allow(unused_imports)]
 use log::{trace, debug, info, warn, error};
 
-use tokio::net::TcpStream;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::broadcast::Sender;
 use tokio::task;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tokio_rustls::TlsAcceptor;
 
 use fracture_core::handler::GlobalHandlerOperation;
+use fracture_core::msg::{HeaderParserError, Message};
 use fracture_core::stat;
+use fracture_core::transport::{BoxedTransport, PeerAddr};
 use fracture_core::utils::wait_update_time;
 
+use crate::conf::{RESUME_GRACE_SECS, TURN_AWAY_SECS};
+use crate::connections::ConnectionSlot;
 use crate::handlers::get_default;
 use crate::interface::core::{stati, ClientInterface};
-use crate::argparser;
+use crate::state::SharedState;
 
 #[derive(Clone, Debug)]
 pub struct ShutdownMessage {
     pub reason: String,
 }
 
+/// Turn away a connection before anything else is started for it, sending it `msg` to tell it why.
+/// it is not counted against the connection limits, so it only gets `TURN_AWAY_SECS` for that,
+/// and is dropped without being told if that would need a TLS handshake
+pub fn turn_away(mut socket: BoxedTransport, addr: PeerAddr, msg: Message, tls: bool) {
+    if tls {
+        return;
+    }
+    tokio::spawn(async move {
+        let tell = async {
+            let bytes = fracture_core::seri::serialize(&msg)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?
+                .into_bytes();
+            socket.write_all(&bytes).await?;
+            socket.shutdown().await
+        };
+        match tokio::time::timeout(Duration::from_secs(TURN_AWAY_SECS), tell).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => debug!("Could not tell {} why it was turned away: {}", addr, err),
+            Err(_) => debug!("{} took too long to be told why it was turned away", addr),
+        }
+    });
+}
+
 pub async fn handle_client(
-    socket: TcpStream,
-    addr: std::net::SocketAddr,
+    socket: BoxedTransport,
+    addr: PeerAddr,
+    slot: ConnectionSlot,
     shutdown_sender: &Sender<ShutdownMessage>,
     global_handler_channel: Sender<GlobalHandlerOperation>,
-    args: argparser::ParsedArgs,
+    shared: SharedState,
+    tls: Option<TlsAcceptor>,
 ) -> task::JoinHandle<()> {
     let mut client_shutdown_channel = shutdown_sender.subscribe(); //make shure to like and
     tokio::spawn(async move {
+        // held until the connection is closed, so it is counted untill then
+        let _slot = slot;
+        // clients that take too long connecting are using up a slot someone else could have
+        let handshake_deadline = Instant::now() + shared.connections.limits().handshake_timeout();
+        // the handshake is done here and not when accepting, so a slow client does not hold up everyone else
+        let socket: BoxedTransport = match tls {
+            Some(acceptor) => {
+                tokio::select! {
+                    res = acceptor.accept(socket) => match res {
+                        Ok(stream) => Box::new(stream),
+                        Err(err) => {
+                            info!("TLS handshake with {} failed: {}", addr, err);
+                            return;
+                        }
+                    },
+                    _ = sleep_until(handshake_deadline) => {
+                        info!("TLS handshake with {} timed out", addr);
+                        return;
+                    }
+                    _ = client_shutdown_channel.recv() => {
+                        return;
+                    }
+                }
+            }
+            None => socket,
+        };
+        let tracker = shared.tracker.clone();
+        let channels = shared.channels.clone();
+        let sessions = shared.sessions.clone();
         let mut interface = ClientInterface::new(
             socket,
-            String::from(args.name),
+            addr.clone(),
             get_default(),
-            global_handler_channel.clone(),
-        );
-        info!(
-            "Connected to {:?}, reported ip {:?}",
-            addr,
-            interface.get_client_addr().unwrap()
+            global_handler_channel,
+            shared,
         );
-        let _ = global_handler_channel.send(GlobalHandlerOperation::ClientConnect {
-            uuid: interface.uuid(),
-        });
+        info!("Connected to {}", addr);
         debug!("client ID for {:?} is {:?}", addr, interface.uuid());
+        // if the connection was lost (instead of being closed on purpose), so the client may come back and resume its session
+        let mut lost = false;
         loop {
+            let session = interface.session().cloned();
             tokio::select! {
                 stat = interface.update_read() => {
                     match stat {
                         stati::UpdateReadStatus::Disconnected => {
                             info!("{:?} disconnected", addr);
+                            lost = true;
                             interface.close(String::from(""), None).await;// do not notify the client of disconnecting, as it is already disconnected
                             break;
                         },
@@ -58,8 +116,22 @@ pub async fn handle_client(
                                 err => {
                                     match err {
                                         DeserializationError(bincode_err) => {
+                                            if interface.is_awaiting_connect() {
+                                                // most likely a client built for a different protocol version, that does not know to send its version first
+                                                info!("Could not understand the connect message from {:?}, refusing", addr);
+                                                interface.refuse(String::from("Could not understand your connect message, your client is probably out of date. Please upgrade your client.")).await;
+                                                break;
+                                            }
                                             error!("Recevied malformed or incomplete message from client! (could not deserialize) error folows:\n{:#?}", bincode_err);
                                         }
+                                        HeaderParser(HeaderParserError::TooLarge { size, max }) => {
+                                            warn!("{:?} tried to send a message of {} bytes (max is {}), disconnecting", addr, size, max);
+                                            interface.close(
+                                                format!("Message of {} bytes is larger than the maximum of {} bytes", size, max),
+                                                Some(fracture_core::msg::types::ServerDisconnectReason::MessageTooLarge)
+                                            ).await;
+                                            break;
+                                        }
                                         HeaderParser(parser_err) => {
                                             error!("Recevied malformed or incomplete message from client! (header parser error) error folows:\n{:#?}", parser_err);
                                         }
@@ -71,6 +143,14 @@ pub async fn handle_client(
                                 }
                             }
                         },
+                        stati::UpdateReadStatus::RateLimited => {
+                            warn!("{:?} kept sending messages too fast, disconnecting", addr);
+                            interface.close(
+                                String::from("You kept sending messages too fast after being warned"),
+                                Some(fracture_core::msg::types::ServerDisconnectReason::RateLimited)
+                            ).await;
+                            break;
+                        },
                         stati::UpdateReadStatus::GracefullDisconnect => {
                             info!("{:?} gracefully disconnected", addr);
                             interface.close(String::from(""), Some(fracture_core::msg::types::ServerDisconnectReason::ClientRequestedDisconnect)).await;
@@ -80,6 +160,13 @@ pub async fn handle_client(
                     };
                 }
                 _ = wait_update_time() => {//update loop
+                    if interface.is_peer_dead() {
+                        info!("{:?} stopped answering pings, disconnecting", addr);
+                        lost = true;
+                        interface.close(String::from(""), None).await;// it would not get the message anyway
+                        break;
+                    }
+                    interface.send_heartbeat();
                     match interface.update().await {
                         stati::UpdateStatus::ClientKicked (reason) => {
                             //TODO this should actualy never happen, so remove it or implement it
@@ -87,6 +174,15 @@ pub async fn handle_client(
                             interface.close(reason, Some(fracture_core::msg::types::ServerDisconnectReason::InvalidConnectionSequence)).await;
                             break;
                         }
+                        stati::UpdateStatus::Refused (reason) => {
+                            info!("Refused connection from {:?}: {}", addr, reason);
+                            interface.refuse(reason).await;
+                            break;
+                        }
+                        stati::UpdateStatus::Banned (message) => {
+                            interface.close(message, Some(fracture_core::msg::types::ServerDisconnectReason::Banned)).await;
+                            break;
+                        }
                         stati::UpdateStatus::Unexpected (msg) => {
                             //TODO make this a error
                             error!("Unexpected message {:#?}", msg);
@@ -97,6 +193,7 @@ pub async fn handle_client(
                         }
                         stati::UpdateStatus::SendError(err) => {
                             error!("Send error: {:#?}", err);
+                            lost = true;
                             interface.close(String::from(""), None).await;
                             break;
                         }
@@ -127,11 +224,17 @@ pub async fn handle_client(
                     interface.colloect_send_global_actions();
                     interface.collect_recv_global_actions();
                     interface.execute_global_actions();
+                    if let Some((reason, message)) = interface.take_kick() {
+                        info!("Disconnecting {:?}: {}", addr, message);
+                        interface.close(message, Some(reason)).await;
+                        break;
+                    }
                     if let Err(err) = interface.send_all_queued().await {
                         match err.0 {
                             stat::SendError::Failure (ioerr) => {
                                 if ioerr.kind() == std::io::ErrorKind::NotConnected {
                                     info!("{:?} disconnected", addr);
+                                    lost = true;
                                     interface.close(String::from(""), None).await;// do not notify the client of disconnecting, as it is already disconnected
                                 } else {
                                     panic!("Error while sending messages:\n{:#?}", ioerr);
@@ -141,9 +244,24 @@ pub async fn handle_client(
                             stat::SendError::SeriError (serr) => {
                                 panic!("Could not serialize message:\n{:#?}", serr);
                             }
+                            stat::SendError::TooLarge { .. } => unreachable!("messages that are too large are skipped"),
                         }
                     }
                 }
+                _ = sleep_until(handshake_deadline), if !interface.is_authenticated() => {
+                    info!("{:?} did not finish connecting in time, disconnecting", addr);
+                    interface.close(
+                        String::from("You took too long to connect"),
+                        Some(fracture_core::msg::types::ServerDisconnectReason::InvalidConnectionSequence)
+                    ).await;
+                    break;
+                }
+                _ = async { session.as_ref().unwrap().taken_over().await }, if session.is_some() => {
+                    // the client came back on another connection before this one was noticed to be gone
+                    info!("{:?} resumed its session on another connection, closing this one", addr);
+                    interface.close(String::from(""), None).await;
+                    break;
+                }
                 smsg = client_shutdown_channel.recv() => {
                     info!("Closing connection to {:?}", addr);
                     interface.close(smsg.unwrap().reason, None).await;
@@ -152,10 +270,32 @@ pub async fn handle_client(
             };
         }
 
-        let _ = global_handler_channel.send(GlobalHandlerOperation::ClientDisconnect {
-            uuid: interface.uuid(),
-            name: interface.name(),
-        });
+        // clients that never made it through authentication never claimed a name
+        if interface.is_authenticated() {
+            let uuid = interface.uuid();
+            match interface.session().cloned() {
+                // another connection has the session now, along with the name and channels
+                Some(session) if !sessions.owns(&session) => {}
+                Some(session) if lost => {
+                    debug!("Keeping the session of {:?} for {} seconds, so it can be resumed", addr, RESUME_GRACE_SECS);
+                    sessions.detach(&session);
+                    tokio::spawn(async move {
+                        sleep(Duration::from_secs(RESUME_GRACE_SECS)).await;
+                        if sessions.end(&session) {
+                            tracker.release(uuid);
+                            channels.leave_all(uuid);
+                        }
+                    });
+                }
+                session => {
+                    if let Some(session) = session {
+                        sessions.end(&session);
+                    }
+                    tracker.release(uuid);
+                    channels.leave_all(uuid);
+                }
+            }
+        }
 
         info!("Connection to {:?} closed", addr);
     })
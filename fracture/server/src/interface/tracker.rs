@@ -1,79 +1,297 @@
-/// User updates should not be used to maintain a accurate record of what users are connected by a client interface,
-/// and should instead be used (for example) to signal chat messages and similar things
-/// TODO make this work?
-
+/// Keeps track of who is connected, so no two clients can use the same name, and so clients can be told who is here.
+///
+/// Clients claim a name once they are authenticated, and release it when they disconnect.
+/// Client interfaces subscribe to the tracker to get the users that are connected and every change after that.
 use std::sync::Arc;
 use parking_lot::FairMutex;
 
 use uuid::Uuid;
 use tokio::sync::broadcast::*;
+use tokio::sync::broadcast::error::TryRecvError;
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use fracture_core::msg::types::{Presence, PresenceStatus};
+
+use crate::history::timestamp_now;
+use crate::names::{same_name, CollisionPolicy, NameError, NameRules};
 
-#[derive(Clone)]
-enum UserUpdate {
-    Connected ( User ),
-    Disconnected ( User ),
+#[derive(Clone, Debug)]
+pub struct User {
+    pub id: Uuid,
+    pub name: String,
+    pub presence: Presence,
 }
 
-#[derive(Clone)]
-struct User {
-    id: uuid::Uuid,
-    name: String,
+#[derive(Clone, Debug)]
+pub enum UserUpdate {
+    Joined(User),
+    Left(User),
+    /// the users status changed
+    Presence(User),
+}
+
+/// The users that were connected when subscribing, and every update after that
+pub struct Subscription {
+    pub users: Vec<User>,
+    updates: Receiver<UserUpdate>,
+    /// everyone the subscriber has been told about, so it can catch up if it falls behind
+    known: Vec<User>,
 }
 
-impl User {
-    fn new_unique(name: String) -> User {
-        User {
-            id: uuid::Uuid::new_v4(),
-            name: name,
+impl Subscription {
+    /// Get the updates that came in since the last call.
+    /// if the subscriber fell too far behind and some were missed, it catches up by comparing who it knows about with who is here now
+    pub fn try_recv(&mut self, tracker: &ClientTracker) -> Vec<UserUpdate> {
+        let mut received = vec![];
+        loop {
+            match self.updates.try_recv() {
+                Ok(update) => {
+                    self.apply(&update);
+                    received.push(update);
+                }
+                Err(TryRecvError::Lagged(missed)) => {
+                    warn!("Missed {} user updates, catching up", missed);
+                    // anything received so far is from before the missed ones, so it is out of date too
+                    received = self.catch_up(tracker);
+                }
+                // the tracker is still around, so it is never closed
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return received,
+            }
         }
     }
 
-    fn new(name: String, id: uuid::Uuid) -> User {
-        User {
-            id: id,
-            name: name,
+    fn apply(&mut self, update: &UserUpdate) {
+        match update {
+            UserUpdate::Joined(user) => self.known.push(user.clone()),
+            UserUpdate::Left(user) => self.known.retain(|known| known.id != user.id),
+            UserUpdate::Presence(user) => {
+                if let Some(known) = self.known.iter_mut().find(|known| known.id == user.id) {
+                    *known = user.clone();
+                }
+            }
         }
     }
 
-    fn id(&self) -> &uuid::Uuid {
-        return &self.id;
+    /// Subscribe again, and work out what changed since the last update that was received
+    fn catch_up(&mut self, tracker: &ClientTracker) -> Vec<UserUpdate> {
+        let Subscription { users, updates, .. } = tracker.subscribe();
+        let mut changes = vec![];
+        for known in &self.known {
+            match users.iter().find(|user| user.id == known.id) {
+                // they left and came back under another name
+                Some(user) if user.name != known.name => {
+                    changes.push(UserUpdate::Left(known.clone()));
+                    changes.push(UserUpdate::Joined(user.clone()));
+                }
+                Some(user) if user.presence != known.presence => changes.push(UserUpdate::Presence(user.clone())),
+                Some(_) => {}
+                None => changes.push(UserUpdate::Left(known.clone())),
+            }
+        }
+        for user in &users {
+            if !self.known.iter().any(|known| known.id == user.id) {
+                changes.push(UserUpdate::Joined(user.clone()));
+            }
+        }
+        self.known = users;
+        self.updates = updates;
+        changes
     }
+}
+
+/// Tracks all connected clients, and provides a way of subscribing to it. clones share the same users
+#[derive(Clone, Debug)]
+pub struct ClientTracker {
+    rules: Arc<NameRules>,
+    // updates are sent while holding the lock, so subscribers never miss or double count one
+    update_tx: Sender<UserUpdate>,
+    users: Arc<FairMutex<Vec<User>>>,
+}
 
-    fn id_cpy(&self) -> Uuid {
-        return self.id.clone();
+impl ClientTracker {
+    pub fn new(rules: NameRules, update_channel_limit: usize) -> ClientTracker {
+        let (update_tx, _): (Sender<UserUpdate>, Receiver<UserUpdate>) = channel(update_channel_limit);
+        ClientTracker {
+            rules: Arc::new(rules),
+            update_tx,
+            users: Arc::new(FairMutex::new(Vec::new())),
+        }
     }
 
-    fn name(&self) -> &String {
-        return &self.name;
+    /// Get everyone who is connected, and subscribe to updates about who joins and leaves
+    pub fn subscribe(&self) -> Subscription {
+        let users = self.users.lock();
+        Subscription {
+            users: users.clone(),
+            updates: self.update_tx.subscribe(),
+            known: users.clone(),
+        }
     }
 
-    fn name_cpy(&self) -> String {
-        return self.name.clone();
+    /// The rules names have to follow
+    pub fn rules(&self) -> &NameRules {
+        &self.rules
     }
-}
 
-pub struct Subscription {
-    users: Arc<FairMutex<Vec<User>>>,
-    update_channel: Receiver<UserUpdate>,
-}
+    /// If the client `id` is connected (and has claimed its name)
+    pub fn is_connected(&self, id: Uuid) -> bool {
+        self.users.lock().iter().any(|user| user.id == id)
+    }
 
-/// Tracks all connected clients, and provides a way of subscribing to it
-struct ClientTracker {
-    core_update_tx: Sender<UserUpdate>,
-    users: Arc<FairMutex<Vec<User>>>
-}
+    /// Claim `name` for the client `id`, returning the name it ended up with.
+    /// this can be different from `name` if it was taken and the collision policy is to add a suffix
+    pub fn claim(&self, id: Uuid, name: &str) -> Result<String, NameError> {
+        let mut users = self.users.lock();
+        // uuids come from accounts when logging in, so the same one can try to connect twice
+        if users.iter().any(|user| user.id == id) {
+            return Err(NameError::AlreadyConnected);
+        }
+        let taken = |candidate: &str| users.iter().any(|user| same_name(&user.name, candidate));
+        let name = if !taken(name) {
+            name.to_string()
+        } else {
+            match self.rules.on_collision {
+                CollisionPolicy::Refuse => return Err(NameError::Taken(name.to_string())),
+                CollisionPolicy::Suffix => (2..)
+                    .map(|discriminator| format!("{}#{}", name, discriminator))
+                    .find(|candidate| !taken(candidate))
+                    .expect("there are not infinitely many users"),
+            }
+        };
+        let user = User {
+            id,
+            name: name.clone(),
+            presence: Presence {
+                status: PresenceStatus::Online,
+                text: None,
+                last_seen: timestamp_now(),
+            },
+        };
+        users.push(user.clone());
+        let _ = self.update_tx.send(UserUpdate::Joined(user));//we dont care about the result bc it could only tell us that no one is listening
+        Ok(name)
+    }
 
-impl ClientTracker {
-    fn new(update_channel_limit: usize) -> ClientTracker {
-        let (update_tx, _): (Sender<UserUpdate>, Receiver<UserUpdate>) = channel(update_channel_limit);
-        ClientTracker {
-            core_update_tx: update_tx,
-            users: Arc::new(FairMutex::new(Vec::new()))//let the chain b e g i n
+    /// The client `id` did something, so it was last seen now.
+    /// everyone is not told about this, it is sent along with the next change to their status
+    pub fn seen(&self, id: Uuid) {
+        if let Some(user) = self.users.lock().iter_mut().find(|user| user.id == id) {
+            user.presence.last_seen = timestamp_now();
+        }
+    }
+
+    /// Change the status of the client `id`, and tell everyone if it is different
+    pub fn set_presence(&self, id: Uuid, status: PresenceStatus, text: Option<String>) {
+        let mut users = self.users.lock();
+        if let Some(user) = users.iter_mut().find(|user| user.id == id) {
+            if user.presence.status != status || user.presence.text != text {
+                user.presence.status = status;
+                user.presence.text = text;
+                let _ = self.update_tx.send(UserUpdate::Presence(user.clone()));
+            }
         }
     }
 
-    fn user_connects(&mut self, user: User) {
-        let _ = self.core_update_tx.send(UserUpdate::Connected(user.clone()));//we dont care about the result bc it could only tell us that no one is listening
+    /// The id of the connected client called `name`
+    pub fn find(&self, name: &str) -> Option<Uuid> {
+        self.users
+            .lock()
+            .iter()
+            .find(|user| same_name(&user.name, name))
+            .map(|user| user.id)
+    }
+
+    /// Release the name of the client `id`, so others can use it
+    pub fn release(&self, id: Uuid) {
+        let mut users = self.users.lock();
+        if let Some(index) = users.iter().position(|user| user.id == id) {
+            let user = users.remove(index);
+            let _ = self.update_tx.send(UserUpdate::Left(user));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn tracker(on_collision: CollisionPolicy) -> ClientTracker {
+        ClientTracker::new(
+            NameRules {
+                on_collision,
+                ..NameRules::default()
+            },
+            16,
+        )
+    }
+
+    #[test]
+    fn taken_names_are_refused() {
+        let tracker = tracker(CollisionPolicy::Refuse);
+        assert_eq!(tracker.claim(Uuid::new_v4(), "alice").unwrap(), "alice");
+        assert!(matches!(tracker.claim(Uuid::new_v4(), "ALICE"), Err(NameError::Taken(_))));
+    }
+
+    #[test]
+    fn taken_names_get_a_suffix() {
+        let tracker = tracker(CollisionPolicy::Suffix);
+        assert_eq!(tracker.claim(Uuid::new_v4(), "alice").unwrap(), "alice");
+        assert_eq!(tracker.claim(Uuid::new_v4(), "Alice").unwrap(), "Alice#2");
+        assert_eq!(tracker.claim(Uuid::new_v4(), "alice").unwrap(), "alice#3");
+    }
+
+    #[test]
+    fn released_names_can_be_claimed_again() {
+        let tracker = tracker(CollisionPolicy::Refuse);
+        let alice = Uuid::new_v4();
+        tracker.claim(alice, "alice").unwrap();
+        assert!(matches!(tracker.claim(alice, "alice2"), Err(NameError::AlreadyConnected)));
+        assert_eq!(tracker.find("ALICE"), Some(alice));
+        tracker.release(alice);
+        assert_eq!(tracker.find("alice"), None);
+        assert!(tracker.claim(Uuid::new_v4(), "alice").is_ok());
+    }
+
+    #[test]
+    fn subscribers_see_who_is_here_and_what_changes() {
+        let tracker = tracker(CollisionPolicy::Refuse);
+        let alice = Uuid::new_v4();
+        tracker.claim(alice, "alice").unwrap();
+        let mut subscription = tracker.subscribe();
+        assert_eq!(subscription.users.len(), 1);
+        let bob = Uuid::new_v4();
+        tracker.claim(bob, "bob").unwrap();
+        tracker.release(alice);
+        let updates = subscription.try_recv(&tracker);
+        assert!(matches!(&updates[..], [UserUpdate::Joined(joined), UserUpdate::Left(left)] if joined.id == bob && left.id == alice));
+        assert!(subscription.try_recv(&tracker).is_empty());
+    }
+
+    #[test]
+    fn subscribers_that_fall_behind_catch_up() {
+        let tracker = ClientTracker::new(NameRules::default(), 2);
+        let (stays, leaves, changes) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        tracker.claim(stays, "stays").unwrap();
+        tracker.claim(leaves, "leaves").unwrap();
+        tracker.claim(changes, "changes").unwrap();
+        let mut subscription = tracker.subscribe();
+        let joins: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in joins.iter().enumerate() {
+            tracker.claim(*id, &format!("new{}", i)).unwrap();
+        }
+        tracker.release(leaves);
+        tracker.set_presence(changes, PresenceStatus::Away, None);
+        let updates = subscription.try_recv(&tracker);
+        assert!(updates.iter().any(|update| matches!(update, UserUpdate::Left(user) if user.id == leaves)));
+        assert!(updates.iter().any(|update| matches!(update, UserUpdate::Presence(user) if user.id == changes)));
+        for id in &joins {
+            assert!(updates.iter().any(|update| matches!(update, UserUpdate::Joined(user) if user.id == *id)));
+        }
+        assert!(!updates.iter().any(|update| matches!(update, UserUpdate::Joined(user) | UserUpdate::Left(user) if user.id == stays)));
+        // and it keeps getting updates afterwards
+        tracker.release(stays);
+        assert!(matches!(&subscription.try_recv(&tracker)[..], [UserUpdate::Left(user)] if user.id == stays));
     }
 }
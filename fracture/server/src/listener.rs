@@ -0,0 +1,85 @@
+/// Accepting clients over the different kinds of transport the server supports
+use std::path::PathBuf;
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use futures::future::{select_all, FutureExt};
+use tokio::io;
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+use fracture_core::transport::{BoxedTransport, PeerAddr};
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        /// removed when the listener is dropped
+        path: PathBuf,
+    },
+}
+
+impl Listener {
+    /// Listen on a unix domain socket at `path`.
+    /// If there is a stale socket left over from a previous run at `path`, it is replaced.
+    #[cfg(unix)]
+    pub fn bind_unix(path: PathBuf) -> io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if let Ok(meta) = std::fs::metadata(&path) {
+            if meta.file_type().is_socket() {
+                debug!("Removing stale unix socket at {}", path.display());
+                std::fs::remove_file(&path)?;
+            }
+        }
+        let listener = UnixListener::bind(&path)?;
+        Ok(Self::Unix { listener, path })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind_unix(_path: PathBuf) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unix domain sockets are not supported on this platform",
+        ))
+    }
+
+    /// Accept one client.
+    /// this is cancelation safe, so it can be used in `tokio::select!`
+    pub async fn accept(&self) -> io::Result<(BoxedTransport, PeerAddr)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (socket, addr) = listener.accept().await?;
+                Ok((Box::new(socket), PeerAddr::Tcp(addr)))
+            }
+            #[cfg(unix)]
+            Self::Unix { listener, .. } => {
+                let (socket, addr) = listener.accept().await?;
+                Ok((
+                    Box::new(socket),
+                    PeerAddr::Unix(addr.as_pathname().map(std::path::Path::to_path_buf)),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Self::Unix { path, .. } = self {
+            if let Err(err) = std::fs::remove_file(&path) {
+                warn!("Failed to remove unix socket {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// Accept a client from whichever listener gets one first
+pub async fn accept_any(listeners: &[Listener]) -> io::Result<(BoxedTransport, PeerAddr)> {
+    let (res, _, _) = select_all(listeners.iter().map(|l| l.accept().boxed())).await;
+    res
+}
@@ -1,22 +1,55 @@
+mod accounts;
 mod argparser;
+mod channels;
+mod connections;
 mod handlers;
+mod history;
 mod interface;
+mod listener;
+mod moderation;
+mod names;
+mod ratelimit;
+mod sessions;
+mod state;
+mod tls;
 use fracture_config::server as conf;
 
 #[allow(unused_imports)]
 use log::{trace, debug, info, warn, error};
 
+use std::sync::Arc;
+
+use parking_lot::Mutex;
 use tokio::{io, net::TcpListener, sync::broadcast, task};
+use tokio_rustls::TlsAcceptor;
 
 use fracture_core::handler::GlobalHandlerOperation;
 use fracture_core::utils::ipencoding;
 
-use interface::{handler::handle_client, handler::ShutdownMessage};
+use interface::{handler::handle_client, handler::turn_away, handler::ShutdownMessage, tracker::ClientTracker};
+use state::SharedState;
+use listener::{accept_any, Listener};
 
 
-#[derive(Debug)]
+#[derive(thiserror::Error)]
 enum MainErr {
+    #[error("{0}")]
     ArgsError(argparser::GetArgsError),
+    #[error("Could not set up TLS: {0}")]
+    Tls(tls::TlsSetupError),
+    #[error("Could not load the accounts: {0}")]
+    Accounts(accounts::AccountError),
+    #[error("Could not load the chat history: {0}")]
+    History(history::HistoryError),
+    #[error("Could not load the bans: {0}")]
+    Bans(moderation::BanError),
+}
+
+// `main` prints the error it returns with `Debug`, so this makes it print the message instead of the variant
+impl std::fmt::Debug for MainErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
 }
 
 impl From<argparser::GetArgsError> for MainErr {
@@ -53,7 +86,72 @@ async fn main() -> Result<(), MainErr> {
 
     let ctrlc_transmitter = shutdown_tx.clone();
 
-    let accepter_task = get_client_listener(shutdown_tx, args.clone());
+    let tls = if args.tls {
+        match tls::load_or_generate(&args.data_path) {
+            Ok(acceptor) => Some(acceptor),
+            Err(err) => {
+                error!("{}", err);
+                return Err(MainErr::Tls(err));
+            }
+        }
+    } else {
+        warn!("TLS is disabled, all messages will be sent unencrypted");
+        None
+    };
+
+    let accounts = if args.accounts {
+        match accounts::Accounts::load(accounts::Accounts::path_for(&args.data_path)) {
+            Ok(store) => Some(store),
+            Err(err) => {
+                error!("{}", err);
+                return Err(MainErr::Accounts(err));
+            }
+        }
+    } else {
+        None
+    };
+    let history = if args.history {
+        match history::HistoryStore::load(history::HistoryStore::path_for(&args.data_path)) {
+            Ok(store) => store,
+            Err(err) => {
+                error!("{}", err);
+                return Err(MainErr::History(err));
+            }
+        }
+    } else {
+        history::HistoryStore::in_memory(conf::MEMORY_HISTORY_LENGTH)
+    };
+    let message_ids = history::MessageIds::starting_at(history.next_id());
+    if (!args.moderators.is_empty() || !args.admins.is_empty()) && !args.accounts {
+        warn!("Moderators and admins are ignored without accounts, since anyone could use their names");
+    }
+    let bans = match moderation::BanList::load(moderation::BanList::path_for(&args.data_path)) {
+        Ok(bans) => bans,
+        Err(err) => {
+            error!("{}", err);
+            return Err(MainErr::Bans(err));
+        }
+    };
+    let shared = SharedState {
+        server_name: args.name.clone(),
+        max_message_size: args.max_message_size,
+        password: args.password.clone(),
+        accounts,
+        tracker: ClientTracker::new(args.name_rules.clone(), conf::USER_UPDATE_CHANNEL_LIMIT),
+        channels: channels::ChannelRegistry::new(conf::DEFAULT_CHANNEL),
+        history: Arc::new(Mutex::new(history)),
+        message_ids,
+        moderators: args.moderators.clone(),
+        admins: args.admins.clone(),
+        bans: Arc::new(Mutex::new(bans)),
+        mutes: moderation::Mutes::default(),
+        rate_limits: args.rate_limits.clone(),
+        connections: connections::Connections::new(args.connection_limits.clone()),
+        heartbeat: args.heartbeat.clone(),
+        sessions: sessions::Sessions::default(),
+    };
+
+    let accepter_task = get_client_listener(shutdown_tx, args.clone(), shared, tls);
     let wait_for_ctrlc = get_ctrlc_listener(ctrlc_transmitter);
 
     // wait for ctrl+c, and then send the shutdown message, then wait for the other task to finish
@@ -64,6 +162,8 @@ async fn main() -> Result<(), MainErr> {
 fn get_client_listener(
     shutdown_tx: broadcast::Sender<ShutdownMessage>,
     args: argparser::ParsedArgs,
+    shared: SharedState,
+    tls: Option<TlsAcceptor>,
 ) -> task::JoinHandle<io::Result<()>> {
     tokio::spawn(async move {
         let (global_oper_tx, _): (
@@ -72,11 +172,11 @@ fn get_client_listener(
         ) = broadcast::channel(conf::GLOBAL_HANDLER_OP_LIMIT);
         let mut accepter_shutdown_rx = shutdown_tx.subscribe();
         // TODO make address configurable
-        let listener = TcpListener::bind(args.full_addr.clone()).await?;
+        let tcp_listener = TcpListener::bind(args.full_addr.clone()).await?;
         info!(
             "Started listening on {:?}, join this server with code {:?}",
-            listener.local_addr().unwrap().to_string(),
-            ipencoding::ip_to_code(match listener.local_addr().unwrap() {
+            tcp_listener.local_addr().unwrap().to_string(),
+            ipencoding::ip_to_code(match tcp_listener.local_addr().unwrap() {
                 std::net::SocketAddr::V4(addr) => {
                     addr
                 }
@@ -84,16 +184,45 @@ fn get_client_listener(
             })
             .unwrap()
         );
+        let mut listeners = vec![Listener::Tcp(tcp_listener)];
+        if let Some(path) = args.unix_socket.clone() {
+            let unix_listener = Listener::bind_unix(path.clone())?;
+            info!("Started listening on unix socket {}", path.display());
+            listeners.push(unix_listener);
+        }
         let mut tasks: Vec<task::JoinHandle<()>> = vec![];
 
         loop {
             tokio::select! {
-                accepted_sock = listener.accept() => {
+                accepted_sock = accept_any(&listeners) => {
                     match accepted_sock {
                         Ok(socket_addr) => {
                             let (socket, addr) = socket_addr;
-
-                            tasks.push(handle_client(socket, addr, &shutdown_tx, global_oper_tx.clone(), args.clone()).await);
+                            // before the TLS handshake, so connections that are not counted can not hold on to anything for long
+                            let banned = addr.ip().and_then(|ip| shared.bans.lock().address(ip).map(|ban| ban.message()));
+                            if let Some(close_message) = banned {
+                                info!("Turning away {}, its address is banned", addr);
+                                let ban = fracture_core::msg::Message {
+                                    data: fracture_core::msg::MessageVarient::ServerForceDisconnect {
+                                        reason: fracture_core::msg::types::ServerDisconnectReason::Banned,
+                                        close_message,
+                                    },
+                                };
+                                turn_away(socket, addr, ban, tls.is_some());
+                                continue;
+                            }
+                            let slot = match shared.connections.open(&addr) {
+                                Ok(slot) => slot,
+                                Err(err) => {
+                                    info!("Turning away {}: {}", addr, err);
+                                    let refusal = interface::core::refusal(&shared, uuid::Uuid::nil(), err.to_string());
+                                    turn_away(socket, addr, refusal, tls.is_some());
+                                    continue;
+                                }
+                            };
+                            // forget about clients that are gone, or this would grow forever
+                            tasks.retain(|task| !task.is_finished());
+                            tasks.push(handle_client(socket, addr, slot, &shutdown_tx, global_oper_tx.clone(), shared.clone(), tls.clone()).await);
                         },
                         Err(err) => {
                             error!("Error while accepting a client {:?}", err);
@@ -0,0 +1,181 @@
+/// Bans and mutes handed out by admins
+///
+/// Bans are kept in a JSON file next to the config (`<base>.bans.json`), so they last when the server restarts.
+/// Mutes are only for a while, so they are only kept in memory.
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::history::timestamp_now;
+use crate::names::same_name;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BanError {
+    #[error("Could not read or write the ban list {path}: {err}")]
+    Io { path: PathBuf, err: io::Error },
+    #[error("The ban list is corrupted: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ban {
+    /// name of who was banned, if it was a user (and not just a address)
+    pub name: Option<String>,
+    /// name of the admin who banned them
+    pub by: String,
+    pub reason: Option<String>,
+    /// when, in milliseconds since the unix epoch (UTC)
+    pub at: i64,
+}
+
+impl Ban {
+    pub fn new(name: Option<String>, by: String, reason: Option<String>) -> Self {
+        Self {
+            name,
+            by,
+            reason,
+            at: timestamp_now(),
+        }
+    }
+
+    /// What a banned client is told when it is turned away
+    pub fn message(&self) -> String {
+        match &self.reason {
+            Some(reason) => format!("You are banned from this server by {}: {}", self.by, reason),
+            None => format!("You are banned from this server by {}", self.by),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Bans {
+    /// banned accounts, by uuid
+    accounts: HashMap<Uuid, Ban>,
+    addresses: HashMap<IpAddr, Ban>,
+}
+
+#[derive(Debug)]
+pub struct BanList {
+    path: PathBuf,
+    bans: Bans,
+}
+
+impl BanList {
+    /// path of the ban list for the server data at `base`
+    pub fn path_for(base: &Path) -> PathBuf {
+        base.with_extension("bans.json")
+    }
+
+    /// Load the ban list at `path`, starting a empty one if it does not exist yet
+    pub fn load(path: PathBuf) -> Result<Self, BanError> {
+        let bans = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Bans::default(),
+            Err(err) => return Err(BanError::Io { path, err }),
+        };
+        Ok(Self { path, bans })
+    }
+
+    fn save(&self) -> Result<(), BanError> {
+        let json = serde_json::to_string_pretty(&self.bans)?;
+        std::fs::write(&self.path, json).map_err(|err| BanError::Io {
+            path: self.path.clone(),
+            err,
+        })
+    }
+
+    /// The ban on the account `uuid`, if it is banned
+    pub fn account(&self, uuid: Uuid) -> Option<&Ban> {
+        self.bans.accounts.get(&uuid)
+    }
+
+    /// The ban on `ip`, if it is banned
+    pub fn address(&self, ip: IpAddr) -> Option<&Ban> {
+        self.bans.addresses.get(&ip)
+    }
+
+    pub fn ban_account(&mut self, uuid: Uuid, ban: Ban) -> Result<(), BanError> {
+        self.bans.accounts.insert(uuid, ban);
+        self.save()
+    }
+
+    pub fn ban_address(&mut self, ip: IpAddr, ban: Ban) -> Result<(), BanError> {
+        self.bans.addresses.insert(ip, ban);
+        self.save()
+    }
+
+    /// Take back the bans on `target`, which is a IP address or the name of a banned user (along with the addresses they were banned with).
+    /// returns how many bans there were
+    pub fn unban(&mut self, target: &str) -> Result<usize, BanError> {
+        let before = self.bans.accounts.len() + self.bans.addresses.len();
+        if let Ok(ip) = target.parse::<IpAddr>() {
+            self.bans.addresses.remove(&ip);
+        } else {
+            let banned = |ban: &Ban| ban.name.as_deref().is_some_and(|name| same_name(name, target));
+            self.bans.accounts.retain(|_, ban| !banned(ban));
+            self.bans.addresses.retain(|_, ban| !banned(ban));
+        }
+        let removed = before - (self.bans.accounts.len() + self.bans.addresses.len());
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Who is muted, and until when. clones share the same mutes
+#[derive(Clone, Debug, Default)]
+pub struct Mutes {
+    until: Arc<Mutex<HashMap<Uuid, Instant>>>,
+}
+
+impl Mutes {
+    /// Mute `uuid` for `time`, or `MAX_MUTE_SECS` if that is longer
+    pub fn mute(&self, uuid: Uuid, time: Duration) {
+        let time = time.min(Duration::from_secs(crate::conf::MAX_MUTE_SECS));
+        self.until.lock().insert(uuid, Instant::now() + time);
+    }
+
+    /// Unmute `uuid`, returning if it was muted
+    pub fn unmute(&self, uuid: Uuid) -> bool {
+        self.until.lock().remove(&uuid).is_some()
+    }
+
+    /// How much longer `uuid` is muted for, if it is
+    pub fn remaining(&self, uuid: Uuid) -> Option<Duration> {
+        let mut until = self.until.lock();
+        let remaining = until.get(&uuid)?.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            until.remove(&uuid);
+            None
+        } else {
+            Some(remaining)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutes_can_not_be_too_long() {
+        let mutes = Mutes::default();
+        let uuid = Uuid::new_v4();
+        mutes.mute(uuid, Duration::from_secs(u64::MAX));
+        let remaining = mutes.remaining(uuid).unwrap();
+        assert!(remaining <= Duration::from_secs(crate::conf::MAX_MUTE_SECS));
+        assert!(mutes.unmute(uuid));
+        assert!(mutes.remaining(uuid).is_none());
+    }
+}
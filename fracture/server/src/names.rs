@@ -0,0 +1,195 @@
+/// Rules for what clients may call themselves
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum NameError {
+    #[error("Your name must be at least {0} characters long")]
+    TooShort(usize),
+    #[error("Your name must be at most {0} characters long")]
+    TooLong(usize),
+    #[error("Your name contains characters that are not allowed here ({0})")]
+    InvalidCharacters(NameCharset),
+    #[error("The name {0:?} is reserved")]
+    Reserved(String),
+    #[error("Someone else is already using the name {0:?}")]
+    Taken(String),
+    #[error("This account is already connected")]
+    AlreadyConnected,
+}
+
+/// What characters are allowed in names
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameCharset {
+    /// anything except control characters, and whitespace at the start or end
+    Any,
+    /// ASCII letters and numbers, `_`, `-` and `.`
+    Simple,
+}
+
+impl NameCharset {
+    fn allows(self, name: &str) -> bool {
+        match self {
+            Self::Any => name.trim() == name && !name.chars().any(char::is_control),
+            Self::Simple => name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')),
+        }
+    }
+}
+
+impl FromStr for NameCharset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Self::Any),
+            "simple" => Ok(Self::Simple),
+            other => Err(format!("unknown name charset {:?}, expected any or simple", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for NameCharset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "no control characters or surrounding spaces"),
+            Self::Simple => write!(f, "only letters, numbers, _, - and ."),
+        }
+    }
+}
+
+/// What to do when a client asks for a name someone else is using
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// refuse the connection
+    Refuse,
+    /// give the client the name with a number after it, like `alice#2`
+    Suffix,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "refuse" => Ok(Self::Refuse),
+            "suffix" => Ok(Self::Suffix),
+            other => Err(format!("unknown name collision policy {:?}, expected refuse or suffix", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NameRules {
+    pub min_length: usize,
+    /// does not include the suffix added to names that collide
+    pub max_length: usize,
+    pub charset: NameCharset,
+    /// names nobody may use, compared ignoring case
+    pub reserved: Vec<String>,
+    pub on_collision: CollisionPolicy,
+}
+
+impl Default for NameRules {
+    fn default() -> Self {
+        Self {
+            min_length: 1,
+            max_length: 32,
+            charset: NameCharset::Any,
+            reserved: vec![String::from("server")],
+            on_collision: CollisionPolicy::Refuse,
+        }
+    }
+}
+
+impl NameRules {
+    /// Check that `name` follows the rules, not counting whether someone else is using it
+    pub fn validate(&self, name: &str) -> Result<(), NameError> {
+        let length = name.chars().count();
+        if length < self.min_length {
+            return Err(NameError::TooShort(self.min_length));
+        }
+        if length > self.max_length {
+            return Err(NameError::TooLong(self.max_length));
+        }
+        if !self.charset.allows(name) {
+            return Err(NameError::InvalidCharacters(self.charset));
+        }
+        if self.reserved.iter().any(|reserved| same_name(reserved, name)) {
+            return Err(NameError::Reserved(name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Names that only differ in case are the same name, so nobody can pretend to be someone else by capitalizing it differently
+pub fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(charset: NameCharset) -> NameRules {
+        NameRules {
+            min_length: 2,
+            max_length: 5,
+            charset,
+            ..NameRules::default()
+        }
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        let rules = rules(NameCharset::Any);
+        assert!(matches!(rules.validate("a"), Err(NameError::TooShort(2))));
+        assert!(matches!(rules.validate("abcdef"), Err(NameError::TooLong(5))));
+        assert!(rules.validate("ab").is_ok());
+        // five characters, but more than five bytes
+        assert!(rules.validate("ééééé").is_ok());
+    }
+
+    #[test]
+    fn charsets() {
+        let any = rules(NameCharset::Any);
+        assert!(any.validate("a b").is_ok());
+        assert!(any.validate("ünï").is_ok());
+        assert!(matches!(any.validate(" ab"), Err(NameError::InvalidCharacters(NameCharset::Any))));
+        assert!(matches!(any.validate("a\nb"), Err(NameError::InvalidCharacters(NameCharset::Any))));
+        let simple = rules(NameCharset::Simple);
+        assert!(simple.validate("a_b-c").is_ok());
+        assert!(simple.validate("a.1").is_ok());
+        assert!(matches!(simple.validate("a b"), Err(NameError::InvalidCharacters(NameCharset::Simple))));
+        assert!(matches!(simple.validate("ünï"), Err(NameError::InvalidCharacters(NameCharset::Simple))));
+    }
+
+    #[test]
+    fn reserved_names_ignore_case() {
+        let rules = NameRules::default();
+        assert!(matches!(rules.validate("server"), Err(NameError::Reserved(_))));
+        assert!(matches!(rules.validate("SeRvEr"), Err(NameError::Reserved(_))));
+        assert!(rules.validate("servers").is_ok());
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("any".parse::<NameCharset>(), Ok(NameCharset::Any));
+        assert_eq!("simple".parse::<NameCharset>(), Ok(NameCharset::Simple));
+        assert!("ascii".parse::<NameCharset>().is_err());
+        assert_eq!("refuse".parse::<CollisionPolicy>(), Ok(CollisionPolicy::Refuse));
+        assert_eq!("suffix".parse::<CollisionPolicy>(), Ok(CollisionPolicy::Suffix));
+        assert!("rename".parse::<CollisionPolicy>().is_err());
+    }
+
+    #[test]
+    fn same_name_ignores_case() {
+        assert!(same_name("Alice", "aLICE"));
+        assert!(!same_name("alice", "alice2"));
+    }
+}
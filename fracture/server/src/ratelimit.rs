@@ -0,0 +1,237 @@
+/// Limits on how fast a client may send messages, so one client can not flood everyone else
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::conf::FORGIVE_VIOLATIONS_SECS;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimits {
+    /// messages a client may send per second, on average. 0 for no limit
+    pub messages_per_second: u32,
+    /// messages a client may send at once, after not sending anything for a while
+    pub message_burst: u32,
+    /// bytes a client may send per second, on average. 0 for no limit
+    pub bytes_per_second: u32,
+    /// bytes a client may send at once, after not sending anything for a while
+    pub byte_burst: u32,
+    /// how many times a client is warned about going over the limits before it is disconnected
+    pub max_violations: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            messages_per_second: 5,
+            message_burst: 10,
+            bytes_per_second: 32768,
+            byte_burst: 131072,
+            max_violations: 5,
+        }
+    }
+}
+
+/// A bucket that fills up with `rate` tokens per second, up to `capacity`
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_fill: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket. a `rate` of 0 means there is no limit
+    pub fn new(rate: u32, capacity: u32) -> Self {
+        // a bucket smaller than its rate could never let a full second through
+        let capacity = f64::from(capacity.max(rate).max(1));
+        Self {
+            rate: f64::from(rate),
+            capacity,
+            tokens: capacity,
+            last_fill: Instant::now(),
+        }
+    }
+
+    fn fill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_fill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_fill = now;
+    }
+
+    /// Take `amount` tokens if there are enough, returning how long to wait before trying again if there are not.
+    /// anything bigger than the bucket only needs it to be full, or it could never get through
+    pub fn take(&mut self, amount: usize) -> Result<(), Duration> {
+        if self.rate == 0.0 {
+            return Ok(());
+        }
+        self.fill();
+        let amount = (amount as f64).min(self.capacity);
+        if self.tokens >= amount {
+            self.tokens -= amount;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((amount - self.tokens) / self.rate))
+        }
+    }
+}
+
+/// What to do with a message a client sent
+pub enum Verdict {
+    Allow,
+    /// drop it, and warn the client (this is the first message over the limit in a while)
+    Warn { retry_after: Duration, warnings_left: u32 },
+    /// drop it, the client was already warned
+    Drop,
+    /// the client has been warned too many times, disconnect it
+    Kick,
+}
+
+/// Rate limits for one connection
+#[derive(Debug)]
+pub struct RateLimiter {
+    messages: TokenBucket,
+    bytes: TokenBucket,
+    max_violations: u32,
+    violations: u32,
+    last_violation: Option<Instant>,
+    /// if the last message was dropped, so clients are only warned once for each flood
+    limited: bool,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &RateLimits) -> Self {
+        Self {
+            messages: TokenBucket::new(limits.messages_per_second, limits.message_burst),
+            bytes: TokenBucket::new(limits.bytes_per_second, limits.byte_burst),
+            max_violations: limits.max_violations,
+            violations: 0,
+            last_violation: None,
+            limited: false,
+        }
+    }
+
+    /// The client sent a message of `bytes` bytes, should it be handled?
+    pub fn check(&mut self, bytes: usize) -> Verdict {
+        let waits = [self.messages.take(1), self.bytes.take(bytes)];
+        let retry_after = match waits.iter().filter_map(|wait| wait.err()).max() {
+            None => {
+                self.limited = false;
+                return Verdict::Allow;
+            }
+            Some(retry_after) => retry_after,
+        };
+        if self.limited {
+            return Verdict::Drop;
+        }
+        self.limited = true;
+        // clients that behave for a while are forgiven
+        if self
+            .last_violation
+            .is_some_and(|last| last.elapsed() >= Duration::from_secs(FORGIVE_VIOLATIONS_SECS))
+        {
+            self.violations = 0;
+        }
+        self.last_violation = Some(Instant::now());
+        self.violations += 1;
+        if self.violations > self.max_violations {
+            Verdict::Kick
+        } else {
+            Verdict::Warn {
+                retry_after,
+                warnings_left: self.max_violations - self.violations,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(messages_per_second: u32, message_burst: u32, max_violations: u32) -> RateLimits {
+        RateLimits {
+            messages_per_second,
+            message_burst,
+            bytes_per_second: 1000,
+            byte_burst: 1000,
+            max_violations,
+        }
+    }
+
+    #[test]
+    fn buckets_let_bursts_through_then_refill() {
+        let mut bucket = TokenBucket::new(1, 3);
+        for _ in 0..3 {
+            assert!(bucket.take(1).is_ok());
+        }
+        let wait = bucket.take(1).unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+        bucket.last_fill -= Duration::from_secs(2);
+        assert!(bucket.take(2).is_ok());
+        assert!(bucket.take(1).is_err());
+        // it never fills past its capacity
+        bucket.last_fill -= Duration::from_secs(100);
+        assert!(bucket.take(3).is_ok());
+        assert!(bucket.take(1).is_err());
+    }
+
+    #[test]
+    fn buckets_without_a_rate_have_no_limit() {
+        let mut bucket = TokenBucket::new(0, 1);
+        for _ in 0..1000 {
+            assert!(bucket.take(1_000_000).is_ok());
+        }
+    }
+
+    #[test]
+    fn anything_bigger_than_the_bucket_needs_it_full() {
+        let mut bucket = TokenBucket::new(10, 100);
+        assert!(bucket.take(5000).is_ok());
+        assert!(bucket.take(5000).is_err());
+        bucket.last_fill -= Duration::from_secs(10);
+        assert!(bucket.take(5000).is_ok());
+    }
+
+    #[test]
+    fn floods_are_warned_about_once_then_dropped() {
+        let mut limiter = RateLimiter::new(&limits(1, 2, 3));
+        assert!(matches!(limiter.check(10), Verdict::Allow));
+        assert!(matches!(limiter.check(10), Verdict::Allow));
+        assert!(matches!(limiter.check(10), Verdict::Warn { warnings_left: 2, .. }));
+        assert!(matches!(limiter.check(10), Verdict::Drop));
+        assert!(matches!(limiter.check(10), Verdict::Drop));
+        // the next flood gets a new warning
+        limiter.messages.last_fill -= Duration::from_secs(1);
+        assert!(matches!(limiter.check(10), Verdict::Allow));
+        assert!(matches!(limiter.check(10), Verdict::Warn { warnings_left: 1, .. }));
+    }
+
+    #[test]
+    fn too_many_bytes_count_as_a_flood() {
+        let mut limiter = RateLimiter::new(&limits(100, 100, 3));
+        assert!(matches!(limiter.check(800), Verdict::Allow));
+        assert!(matches!(limiter.check(800), Verdict::Warn { .. }));
+    }
+
+    #[test]
+    fn clients_are_kicked_after_too_many_warnings_unless_they_behave() {
+        let mut limiter = RateLimiter::new(&limits(1, 1, 2));
+        let flood = |limiter: &mut RateLimiter| {
+            limiter.messages.last_fill -= Duration::from_secs(1);
+            assert!(matches!(limiter.check(1), Verdict::Allow));
+            limiter.check(1)
+        };
+        assert!(matches!(flood(&mut limiter), Verdict::Warn { warnings_left: 1, .. }));
+        assert!(matches!(flood(&mut limiter), Verdict::Warn { warnings_left: 0, .. }));
+        // behaving for a while clears the warnings
+        limiter.last_violation = limiter
+            .last_violation
+            .map(|last| last - Duration::from_secs(FORGIVE_VIOLATIONS_SECS));
+        assert!(matches!(flood(&mut limiter), Verdict::Warn { warnings_left: 1, .. }));
+        assert!(matches!(flood(&mut limiter), Verdict::Warn { warnings_left: 0, .. }));
+        assert!(matches!(flood(&mut limiter), Verdict::Kick));
+    }
+}
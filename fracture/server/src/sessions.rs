@@ -0,0 +1,240 @@
+/// Sessions let a client that lost its connection come back as the same user, without everyone seeing it leave and join again.
+///
+/// Every session is owned by one connection at a time. When a client resumes its session, the new connection takes it over,
+/// and the old one (if the server had not noticed it was gone yet) is told to close without releasing anything.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rand_core::{OsRng, RngCore};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use fracture_core::msg::Message;
+
+#[derive(Debug)]
+struct Session {
+    uuid: Uuid,
+    name: String,
+    /// bumped every time the session is resumed, so connections can tell if they still own it
+    generation: u64,
+    /// the connection that owned it was lost, and it is waiting to be resumed
+    detached: bool,
+    /// wakes up the connection that owns it, when it is taken over
+    takeover: Arc<Notify>,
+    /// direct messages sent to it while it was detached, for whoever resumes it
+    held: Vec<Message>,
+}
+
+/// Proof that a connection owns a session, until it is taken over
+#[derive(Clone, Debug)]
+pub struct SessionHandle {
+    pub token: String,
+    generation: u64,
+    takeover: Arc<Notify>,
+}
+
+impl SessionHandle {
+    /// Wait until another connection takes the session over
+    pub async fn taken_over(&self) {
+        self.takeover.notified().await;
+    }
+}
+
+/// A session that was taken over by a new connection
+pub struct Resumed {
+    pub uuid: Uuid,
+    pub name: String,
+    pub handle: SessionHandle,
+    /// direct messages sent while it was detached, oldest first
+    pub held: Vec<Message>,
+}
+
+/// What `Sessions::hold` did with a message
+#[derive(Debug, PartialEq, Eq)]
+pub enum Hold {
+    /// the user is not waiting to resume a session, so the message should be sent as usual
+    NotDetached,
+    /// it is kept until the user resumes their session
+    Held,
+    /// the user is waiting to resume a session, but too much is kept for them already
+    Full,
+}
+
+/// Every session, by token. clones share the same sessions
+#[derive(Clone, Debug, Default)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl Sessions {
+    /// Start a new session for the user `uuid`, who is called `name`
+    pub fn start(&self, uuid: Uuid, name: String) -> SessionHandle {
+        let token = new_token();
+        let takeover = Arc::new(Notify::new());
+        self.sessions.lock().insert(
+            token.clone(),
+            Session {
+                uuid,
+                name,
+                generation: 0,
+                detached: false,
+                takeover: takeover.clone(),
+                held: vec![],
+            },
+        );
+        SessionHandle {
+            token,
+            generation: 0,
+            takeover,
+        }
+    }
+
+    /// The user the session `token` is for, without taking it over
+    pub fn user(&self, token: &str) -> Option<Uuid> {
+        self.sessions.lock().get(token).map(|session| session.uuid)
+    }
+
+    /// Take over the session `token` is for, if it is still around
+    pub fn resume(&self, token: &str) -> Option<Resumed> {
+        let mut sessions = self.sessions.lock();
+        let session = sessions.get_mut(token)?;
+        Some(take_over(token, session))
+    }
+
+    /// Take over a session of the user `uuid` that lost its connection, if there is one.
+    /// for clients logging in to a account again without a token (like after restarting), while the old connection is waiting to be resumed
+    pub fn resume_user(&self, uuid: Uuid) -> Option<Resumed> {
+        let mut sessions = self.sessions.lock();
+        let (token, session) = sessions
+            .iter_mut()
+            .find(|(_, session)| session.uuid == uuid && session.detached)?;
+        Some(take_over(token, session))
+    }
+
+    /// Keep a direct message for the user `uuid` if they lost their connection and may resume their session,
+    /// since it would be lost otherwise
+    pub fn hold(&self, uuid: Uuid, msg: Message) -> Hold {
+        let mut sessions = self.sessions.lock();
+        match sessions
+            .values_mut()
+            .find(|session| session.uuid == uuid && session.detached)
+        {
+            None => Hold::NotDetached,
+            Some(session) if session.held.len() >= crate::conf::MAX_HELD_DIRECT => Hold::Full,
+            Some(session) => {
+                session.held.push(msg);
+                Hold::Held
+            }
+        }
+    }
+
+    /// If the connection with `handle` still owns its session
+    pub fn owns(&self, handle: &SessionHandle) -> bool {
+        self.sessions
+            .lock()
+            .get(&handle.token)
+            .is_some_and(|session| session.generation == handle.generation)
+    }
+
+    /// The connection with `handle` was lost, so the session waits to be resumed
+    pub fn detach(&self, handle: &SessionHandle) {
+        if let Some(session) = self.sessions.lock().get_mut(&handle.token) {
+            if session.generation == handle.generation {
+                session.detached = true;
+            }
+        }
+    }
+
+    /// End the session, if the connection with `handle` still owns it. returns if it did
+    pub fn end(&self, handle: &SessionHandle) -> bool {
+        let mut sessions = self.sessions.lock();
+        if sessions
+            .get(&handle.token)
+            .is_some_and(|session| session.generation == handle.generation)
+        {
+            sessions.remove(&handle.token);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Give `session` to a new connection, waking up the one that had it
+fn take_over(token: &str, session: &mut Session) -> Resumed {
+    session.takeover.notify_one();
+    session.generation += 1;
+    session.detached = false;
+    // a fresh one, so the permit left by `notify_one` (if nothing was waiting) does not wake up the new owner
+    session.takeover = Arc::new(Notify::new());
+    Resumed {
+        uuid: session.uuid,
+        name: session.name.clone(),
+        handle: SessionHandle {
+            token: token.to_string(),
+            generation: session.generation,
+            takeover: session.takeover.clone(),
+        },
+        held: std::mem::take(&mut session.held),
+    }
+}
+
+/// A random token, that can not be guessed
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fracture_core::msg::MessageVarient;
+
+    fn message(n: u64) -> Message {
+        Message {
+            data: MessageVarient::ChatFailed {
+                nonce: n,
+                reason: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn messages_are_held_for_detached_sessions() {
+        let sessions = Sessions::default();
+        let uuid = Uuid::new_v4();
+        let handle = sessions.start(uuid, String::from("alice"));
+        assert_eq!(sessions.hold(uuid, message(0)), Hold::NotDetached);
+        sessions.detach(&handle);
+        assert_eq!(sessions.user(&handle.token), Some(uuid));
+        assert_eq!(sessions.hold(uuid, message(1)), Hold::Held);
+        assert_eq!(sessions.hold(uuid, message(2)), Hold::Held);
+        let resumed = sessions.resume(&handle.token).unwrap();
+        let held: Vec<_> = resumed
+            .held
+            .iter()
+            .map(|msg| match msg.data {
+                MessageVarient::ChatFailed { nonce, .. } => nonce,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(held, vec![1, 2]);
+        assert_eq!(sessions.hold(uuid, message(3)), Hold::NotDetached);
+        assert!(sessions.resume(&handle.token).unwrap().held.is_empty());
+    }
+
+    #[test]
+    fn only_so_many_messages_are_held() {
+        let sessions = Sessions::default();
+        let uuid = Uuid::new_v4();
+        let handle = sessions.start(uuid, String::from("alice"));
+        sessions.detach(&handle);
+        for n in 0..crate::conf::MAX_HELD_DIRECT as u64 {
+            assert_eq!(sessions.hold(uuid, message(n)), Hold::Held);
+        }
+        assert_eq!(sessions.hold(uuid, message(0)), Hold::Full);
+        assert_eq!(sessions.resume_user(uuid).unwrap().held.len(), crate::conf::MAX_HELD_DIRECT);
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use parking_lot::Mutex;
+
+use fracture_core::heartbeat::HeartbeatSettings;
+
+use crate::accounts::Accounts;
+use crate::channels::ChannelRegistry;
+use crate::connections::Connections;
+use crate::history::{HistoryStore, MessageIds};
+use crate::interface::tracker::ClientTracker;
+use crate::moderation::{BanList, Mutes};
+use crate::ratelimit::RateLimits;
+use crate::sessions::Sessions;
+
+/// Settings and data shared by every client interface
+#[derive(Clone, Debug)]
+pub struct SharedState {
+    pub server_name: String,
+    /// the largest message a client may send
+    pub max_message_size: usize,
+    /// password clients must send to join, if any
+    pub password: Option<String>,
+    /// the account store, if clients have to log in
+    pub accounts: Option<Accounts>,
+    /// names of everyone connected
+    pub tracker: ClientTracker,
+    pub channels: ChannelRegistry,
+    /// what has been said in channels
+    pub history: Arc<Mutex<HistoryStore>>,
+    pub message_ids: MessageIds,
+    /// names of the users who can edit and delete anyones messages
+    pub moderators: Vec<String>,
+    /// names of the users who can kick, mute and ban people
+    pub admins: Vec<String>,
+    /// banned accounts and addresses
+    pub bans: Arc<Mutex<BanList>>,
+    /// who can not say anything for a while
+    pub mutes: Mutes,
+    /// how fast each client may send messages
+    pub rate_limits: RateLimits,
+    /// how many clients are connected, and how many can be
+    pub connections: Connections,
+    /// how often clients are pinged, and how many pings they can miss
+    pub heartbeat: HeartbeatSettings,
+    /// sessions clients can resume after losing their connection
+    pub sessions: Sessions,
+}
+
+impl SharedState {
+    /// If the user with the account `uuid` is a moderator (admins are too).
+    /// names are only checked when clients have to log in, otherwise anyone could call themselves a moderator
+    pub fn is_moderator(&self, uuid: Uuid) -> bool {
+        self.is_admin(uuid) || self.moderators.iter().any(|moderator| self.is_account(moderator, uuid))
+    }
+
+    /// If the user with the account `uuid` is a admin, who can do anything moderators can too.
+    /// like moderators, this is only for users who had to log in
+    pub fn is_admin(&self, uuid: Uuid) -> bool {
+        self.admins.iter().any(|admin| self.is_account(admin, uuid))
+    }
+
+    /// If `name` is exactly the name of the account `uuid`.
+    /// checking the account instead of the name a client goes by means nobody can pass for someone with a name that only differs in case
+    fn is_account(&self, name: &str, uuid: Uuid) -> bool {
+        self.accounts
+            .as_ref()
+            .and_then(|accounts| accounts.credentials(name))
+            .is_some_and(|(account, _)| account == uuid)
+    }
+}
@@ -0,0 +1,111 @@
+/// TLS for client connections, using a self signed certificate that is generated on first launch
+///
+/// The certificate and key are stored as `<base>.cert.pem` and `<base>.key.pem`,
+/// where `<base>` is the configuration file without its extension.
+/// Clients pin the certificate the first time they connect, so it must stay the same between launches.
+use std::fs;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[allow(unused_imports)]
+use log::{trace, debug, info, warn, error};
+
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+use fracture_core::utils::fingerprint::fingerprint;
+
+/// name the certificate is issued for. clients only check the fingerprint, so this does not need to match anything
+const CERT_SUBJECT: &str = "fracture-server";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsSetupError {
+    #[error("Could not read or write the TLS identity {path}: {err}")]
+    Io { path: PathBuf, err: io::Error },
+    #[error("Failed to generate a certificate: {0}")]
+    Generate(#[from] rcgen::RcgenError),
+    #[error("{0} does not contain a usable certificate or private key")]
+    Invalid(PathBuf),
+    #[error("Invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// paths of the certificate and private key for the identity at `base`
+fn identity_paths(base: &Path) -> (PathBuf, PathBuf) {
+    (
+        base.with_extension("cert.pem"),
+        base.with_extension("key.pem"),
+    )
+}
+
+fn read_identity(cert_path: &Path, key_path: &Path) -> Result<(Certificate, PrivateKey), TlsSetupError> {
+    let open = |path: &Path| {
+        fs::File::open(path)
+            .map(BufReader::new)
+            .map_err(|err| TlsSetupError::Io { path: path.to_path_buf(), err })
+    };
+    let cert = rustls_pemfile::certs(&mut open(cert_path)?)
+        .map_err(|err| TlsSetupError::Io { path: cert_path.to_path_buf(), err })?
+        .into_iter()
+        .next()
+        .ok_or_else(|| TlsSetupError::Invalid(cert_path.to_path_buf()))?;
+    let key = rustls_pemfile::pkcs8_private_keys(&mut open(key_path)?)
+        .map_err(|err| TlsSetupError::Io { path: key_path.to_path_buf(), err })?
+        .into_iter()
+        .next()
+        .ok_or_else(|| TlsSetupError::Invalid(key_path.to_path_buf()))?;
+    Ok((Certificate(cert), PrivateKey(key)))
+}
+
+fn generate_identity(cert_path: &Path, key_path: &Path) -> Result<(Certificate, PrivateKey), TlsSetupError> {
+    let generated = rcgen::generate_simple_self_signed(vec![CERT_SUBJECT.to_string()])?;
+    let write = |path: &Path, contents: String| {
+        fs::write(path, contents).map_err(|err| TlsSetupError::Io { path: path.to_path_buf(), err })
+    };
+    write(cert_path, generated.serialize_pem()?)?;
+    write_private(key_path, generated.serialize_private_key_pem())
+        .map_err(|err| TlsSetupError::Io { path: key_path.to_path_buf(), err })?;
+    // serializing again would sign the certificate again, giving a different one than what was saved
+    read_identity(cert_path, key_path)
+}
+
+/// Write the private key so only the owner can read it, from the moment the file exists
+fn write_private(path: &Path, contents: String) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // the mode only applies to new files, a key left over from before could be readable by anyone
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())
+}
+
+/// Load the identity at `base`, generating (and saving) a new one if it does not exist yet
+pub fn load_or_generate(base: &Path) -> Result<TlsAcceptor, TlsSetupError> {
+    let (cert_path, key_path) = identity_paths(base);
+    let (cert, key) = if cert_path.exists() && key_path.exists() {
+        debug!("Loading TLS identity from {}", cert_path.display());
+        read_identity(&cert_path, &key_path)?
+    } else {
+        info!(
+            "No TLS identity found, generating a new one at {}",
+            cert_path.display()
+        );
+        generate_identity(&cert_path, &key_path)?
+    };
+    info!("TLS certificate fingerprint is {}", fingerprint(&cert.0));
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert], key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}